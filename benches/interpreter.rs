@@ -0,0 +1,63 @@
+//! Measures how many instructions per second the interpreter executes on the bundled ROMs and on a tight loop of
+//! arithmetic, and with `--features jit` how many the JIT executes. Compare two versions of the CPU by saving a
+//! baseline on the first with `--save-baseline` and running the second with `--baseline`.
+use std::path::Path;
+
+use criterion::{criterion_group, criterion_main, BatchSize, Criterion, Throughput};
+use rs_chip8::cpu::{Timing, CPU};
+use rs_chip8::quirks::Quirks;
+use rs_chip8::rom;
+
+const FRAMES: u32 = 60;
+const INSTRUCTIONS_PER_FRAME: u32 = 1000;
+
+/// Adds V1 to V0 and counts V2 up until it wraps around, forever.
+const ARITHMETIC_LOOP: [u8; 12] = [0x61, 0x03, 0x80, 0x14, 0x72, 0x01, 0x32, 0x00, 0x12, 0x02, 0x12, 0x00];
+
+fn bundled_rom(name: &str) -> Vec<u8> {
+    let path = Path::new(env!("CARGO_MANIFEST_DIR")).join("roms").join(name);
+    rom::load(&path, Quirks::default().memory_size).unwrap()
+}
+
+fn bench_interpreter(c: &mut Criterion) {
+    bench_programs(c, "instructions", |_| {});
+    #[cfg(feature = "jit")]
+    bench_programs(c, "jit", |cpu| cpu.enable_jit().unwrap());
+}
+
+/// Runs each program on CPUs set up by `setup` in a group named `group`.
+fn bench_programs(c: &mut Criterion, group: &str, setup: impl Fn(&mut CPU)) {
+    let programs = [
+        ("pong", bundled_rom("pong.ch8")),
+        ("tetris", bundled_rom("tetris.ch8")),
+        ("test_opcode", bundled_rom("test_opcode.ch8")),
+        ("arithmetic_loop", ARITHMETIC_LOOP.to_vec()),
+    ];
+
+    let mut group = c.benchmark_group(group);
+    group.throughput(Throughput::Elements((FRAMES * INSTRUCTIONS_PER_FRAME) as u64));
+    for (name, program) in programs.iter() {
+        group.bench_function(*name, |b| {
+            b.iter_batched(
+                || {
+                    let mut cpu = CPU::new();
+                    setup(&mut cpu);
+                    cpu.seed_rng(0);
+                    cpu.load(program).unwrap();
+                    cpu
+                },
+                |mut cpu| {
+                    for _ in 0..FRAMES {
+                        cpu.run_frame([false; 16], Timing::Instructions(INSTRUCTIONS_PER_FRAME)).unwrap();
+                    }
+                    cpu
+                },
+                BatchSize::LargeInput,
+            )
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, bench_interpreter);
+criterion_main!(benches);
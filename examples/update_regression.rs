@@ -0,0 +1,12 @@
+//! Writes `tests/golden/regression.txt` again from the current behaviour of the CPU, after an intentional change.
+//!
+//! ```shell
+//! cargo run --example update_regression
+//! ```
+#[path = "../tests/regression.rs"]
+mod regression;
+
+fn main() {
+    let path = regression::write_expectations();
+    println!("Wrote {}", path.display());
+}
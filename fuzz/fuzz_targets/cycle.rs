@@ -0,0 +1,31 @@
+//! Runs arbitrary bytes as a ROM one instruction at a time, with every quirk profile. The CPU must never panic: an
+//! instruction it cannot execute is reported as a `CpuError`, and keeps being reported until something changes.
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use rs_chip8::cpu::CPU;
+use rs_chip8::quirks::Quirks;
+
+const CYCLES: usize = 5000;
+
+fuzz_target!(|data: &[u8]| {
+    for (_, quirks) in Quirks::profiles().iter() {
+        let mut cpu = CPU::with_quirks(*quirks);
+        cpu.seed_rng(0);
+        if cpu.load(data).is_err() {
+            continue;
+        }
+        for cycle in 0..CYCLES {
+            // Presses a key now and then so FX0A and the key skips take both paths.
+            let mut keypad = [false; 16];
+            keypad[cycle % 16] = cycle % 7 == 0;
+            if let Err(error) = cpu.cycle(keypad) {
+                assert_eq!(cpu.cycle(keypad).err(), Some(error));
+                break;
+            }
+            if cycle % 10 == 0 {
+                cpu.tick_timers();
+            }
+        }
+    }
+});
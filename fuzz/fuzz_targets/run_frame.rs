@@ -0,0 +1,36 @@
+//! Runs arbitrary bytes as a ROM frame by frame with both timings, the first byte picking the quirk profile and the
+//! number of instructions per frame. Frames stop at the first `CpuError` without panicking.
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use rs_chip8::cpu::{Reset, Timing, CPU};
+use rs_chip8::quirks::Quirks;
+
+const FRAMES: usize = 120;
+
+fuzz_target!(|data: &[u8]| {
+    let (&settings, rom) = match data.split_first() {
+        Some(split) => split,
+        None => return,
+    };
+    let profiles = Quirks::profiles();
+    let (_, quirks) = profiles[settings as usize % profiles.len()];
+    let timing = match settings >> 4 {
+        0 => Timing::Vip,
+        instructions => Timing::Instructions(instructions as u32 * 4),
+    };
+
+    let mut cpu = CPU::with_quirks(quirks);
+    cpu.seed_rng(settings as u64);
+    if cpu.load(rom).is_err() {
+        return;
+    }
+    for reset in [Reset::Hard, Reset::Soft].iter() {
+        for _ in 0..FRAMES {
+            if cpu.run_frame([false; 16], timing).is_err() {
+                break;
+            }
+        }
+        cpu.reset(*reset);
+    }
+});
@@ -0,0 +1,634 @@
+//! Static analysis of ROMs. Every path from the entry point is followed without running the program, tracking which
+//! values of I and V0 to VF are known at each instruction, to build the control-flow graph and find what the program
+//! does that a single run would not show.
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt::Write;
+use std::ops::Range;
+
+use serde_json::{json, Value as Json};
+
+use crate::cpu::{Instruction, OpCode};
+use crate::disassembler::disassemble_with_symbols;
+use crate::framebuffer::{HEIGHT, WIDTH};
+use crate::quirks::Quirks;
+use crate::rom::PROGRAM_START;
+use crate::symbols::Symbols;
+
+const OPCODE_SIZE: usize = 2;
+
+/// The value of a register before an instruction, if it is the same on every path reaching it.
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Value {
+    Known(u16),
+    Unknown,
+}
+
+impl Value {
+    fn join(self, other: Value) -> Value {
+        if self == other { self } else { Value::Unknown }
+    }
+}
+
+/// What is known about the registers before an instruction.
+#[derive(Clone, Copy, Debug, PartialEq)]
+struct State {
+    index: Value,
+    registers: [Value; 16],
+}
+
+impl State {
+    /// The registers after a reset, which clears them.
+    fn reset() -> Self {
+        State { index: Value::Known(0), registers: [Value::Known(0); 16] }
+    }
+
+    fn unknown() -> Self {
+        State { index: Value::Unknown, registers: [Value::Unknown; 16] }
+    }
+
+    /// These registers, from before a call, with the ones in `changes` taken from `returned`, the state of the
+    /// subroutine when it returns.
+    fn after_return(&self, returned: &State, (registers, index): (u16, bool)) -> State {
+        let mut state = *self;
+        if index {
+            state.index = returned.index;
+        }
+        for (register, value) in state.registers.iter_mut().enumerate() {
+            if registers >> register & 1 == 1 {
+                *value = returned.registers[register];
+            }
+        }
+        state
+    }
+
+    fn join(&self, other: &State) -> State {
+        let mut state = *self;
+        state.index = self.index.join(other.index);
+        for (register, other) in state.registers.iter_mut().zip(other.registers) {
+            *register = register.join(other);
+        }
+        state
+    }
+
+    /// The registers after `opcode` runs on these ones.
+    fn after(&self, opcode: &OpCode) -> State {
+        let mut state = *self;
+        let x = opcode.lr as usize;
+        match opcode.instruction() {
+            Instruction::Op6XNN => state.registers[x] = Value::Known(opcode.get_nn() as u16),
+            Instruction::Op7XNN => {
+                if let Value::Known(value) = state.registers[x] {
+                    state.registers[x] = Value::Known((value + opcode.get_nn() as u16) & 0xFF);
+                }
+            }
+            Instruction::Op8XY0 => state.registers[x] = state.registers[opcode.rl as usize],
+            Instruction::Op8XY1
+            | Instruction::Op8XY2
+            | Instruction::Op8XY3
+            | Instruction::Op8XY4
+            | Instruction::Op8XY5
+            | Instruction::Op8XY6
+            | Instruction::Op8XY7
+            | Instruction::Op8XYE => {
+                state.registers[x] = Value::Unknown;
+                state.registers[0xF] = Value::Unknown;
+            }
+            Instruction::OpCXNN | Instruction::OpFX07 | Instruction::OpFX0A => state.registers[x] = Value::Unknown,
+            Instruction::OpDXYN => state.registers[0xF] = Value::Unknown,
+            Instruction::OpFX65 => state.registers[..=x].fill(Value::Unknown),
+            Instruction::OpANNN => state.index = Value::Known(opcode.get_nnn() as u16),
+            Instruction::OpFX1E => {
+                state.index = match (state.index, state.registers[x]) {
+                    (Value::Known(index), Value::Known(value)) => Value::Known(index.wrapping_add(value)),
+                    _ => Value::Unknown,
+                }
+            }
+            Instruction::OpFX29 => state.index = Value::Unknown,
+            _ => {}
+        }
+        state
+    }
+}
+
+/// The registers `opcode` may change, as a mask with a bit for each of V0 to VF, and whether it may change I.
+fn changes(opcode: &OpCode) -> (u16, bool) {
+    let x = 1 << opcode.lr;
+    match opcode.instruction() {
+        Instruction::Op6XNN
+        | Instruction::Op7XNN
+        | Instruction::Op8XY0
+        | Instruction::OpCXNN
+        | Instruction::OpFX07
+        | Instruction::OpFX0A => (x, false),
+        Instruction::Op8XY1
+        | Instruction::Op8XY2
+        | Instruction::Op8XY3
+        | Instruction::Op8XY4
+        | Instruction::Op8XY5
+        | Instruction::Op8XY6
+        | Instruction::Op8XY7
+        | Instruction::Op8XYE => (x | 1 << 0xF, false),
+        Instruction::OpDXYN => (1 << 0xF, false),
+        Instruction::OpFX65 => (0xFFFF >> (0xF - opcode.lr), false),
+        Instruction::OpANNN | Instruction::OpFX1E | Instruction::OpFX29 => (0, true),
+        _ => (0, false),
+    }
+}
+
+/// How control passes from an instruction to the next one it runs.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Edge {
+    /// Runs the instruction that follows, also once a subroutine returns.
+    Next,
+    /// Skips the instruction that follows.
+    Skip,
+    Jump,
+    /// Calls a subroutine with 2NNN, or with 0NNN, which also runs as a call.
+    Call,
+}
+
+/// The instruction `opcode` is followed as. The SUPER-CHIP and XO-CHIP instructions in 00XX, which this CPU runs as
+/// 00E0 or 00EE, carry on to the next instruction as on the platforms that added them, so that the rest of their
+/// ROMs is analyzed.
+fn flow_instruction(opcode: &OpCode) -> Instruction {
+    match opcode.instruction() {
+        Instruction::Op00E0 | Instruction::Op00EE if platform(opcode.get_nn() as u16) > 0 => Instruction::Unknown,
+        instruction => instruction,
+    }
+}
+
+/// The instructions `opcode` at `address` can pass control to. Returns and computed jumps have none.
+fn successors(address: usize, opcode: &OpCode) -> Vec<(usize, Edge)> {
+    let next = address + OPCODE_SIZE;
+    match flow_instruction(opcode) {
+        Instruction::Op00EE | Instruction::OpBNNN => vec![],
+        Instruction::Op1NNN => vec![(opcode.get_nnn(), Edge::Jump)],
+        Instruction::Op0NNN | Instruction::Op2NNN => vec![(opcode.get_nnn(), Edge::Call), (next, Edge::Next)],
+        Instruction::Op3XNN
+        | Instruction::Op4XNN
+        | Instruction::Op5XY0
+        | Instruction::Op9XY0
+        | Instruction::OpEX9E
+        | Instruction::OpEXA1 => vec![(next, Edge::Next), (next + OPCODE_SIZE, Edge::Skip)],
+        _ => vec![(next, Edge::Next)],
+    }
+}
+
+/// The behaviour interpreters disagree on that an instruction depends on, if any.
+fn quirk(opcode: &OpCode, state: &State) -> Option<&'static str> {
+    match opcode.instruction() {
+        // Whether VY or VX is shifted.
+        Instruction::Op8XY6 | Instruction::Op8XYE => Some("shift"),
+        // Whether I is incremented.
+        Instruction::OpFX55 | Instruction::OpFX65 => Some("load_store"),
+        // Whether V0 or VX is added.
+        Instruction::OpBNNN => Some("jump_with_offset"),
+        Instruction::OpDXYN => match (state.registers[opcode.lr as usize], state.registers[opcode.rl as usize]) {
+            (Value::Known(x), Value::Known(y))
+                if x as usize % WIDTH + 8 > WIDTH || y as usize % HEIGHT + opcode.rr as usize > HEIGHT =>
+            {
+                Some("sprite_wrap")
+            }
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+/// The platform that added `opcode`, as an index into the quirk profiles of CHIP-8, SUPER-CHIP and XO-CHIP.
+fn platform(opcode: u16) -> usize {
+    let (x, nn, n) = ((opcode >> 8) & 0xF, opcode & 0xFF, opcode & 0xF);
+    match opcode >> 12 {
+        // 00CN, 00FB to 00FF and 00DN scroll the screen, change its resolution or exit.
+        0x0 if x == 0 && ((0xC0..=0xCF).contains(&nn) || nn >= 0xFB) => 1,
+        0x0 if x == 0 && (0xD0..=0xDF).contains(&nn) => 2,
+        // DXY0 draws a 16 by 16 sprite.
+        0xD if n == 0 => 1,
+        0x5 if n == 2 || n == 3 => 2,
+        0xF if matches!(nn, 0x30 | 0x75 | 0x85) => 1,
+        0xF if nn == 0x01 || nn == 0x3A || (x == 0 && (nn == 0x00 || nn == 0x02)) => 2,
+        _ => 0,
+    }
+}
+
+/// The keys a ROM reads, as far as they are known without running it.
+#[derive(Debug, Default, PartialEq)]
+pub struct Keys {
+    /// Keys tested by EX9E and EXA1.
+    pub tested: BTreeSet<u8>,
+    /// EX9E and EXA1 testing a key whose number depends on the path taken or on input.
+    pub computed: Vec<usize>,
+    /// FX0A waiting for any key.
+    pub waits: Vec<usize>,
+}
+
+/// What a subroutine does to its caller, from the instructions it and the subroutines it calls can run.
+struct Subroutine {
+    /// The registers it may change, as returned by `changes`.
+    changes: (u16, bool),
+    /// The 00EE instructions it may return with.
+    returns: Vec<usize>,
+}
+
+/// Instructions that run one after the other, entered only through the first one.
+#[derive(Debug, PartialEq)]
+pub struct Block {
+    pub instructions: Vec<usize>,
+    /// The first instructions of the blocks that can run next.
+    pub successors: Vec<(usize, Edge)>,
+}
+
+/// What the analysis of a ROM loaded at 0x200 found.
+pub struct Analysis {
+    rom: Vec<u8>,
+    /// What is known before each reachable instruction, by address.
+    states: BTreeMap<usize, State>,
+    /// Whether a reachable instruction uses each byte of the ROM.
+    code: Vec<bool>,
+    blocks: BTreeMap<usize, Block>,
+    /// The addresses calling each subroutine, by the address of the subroutine.
+    subroutines: BTreeMap<usize, BTreeSet<usize>>,
+    /// Jumps and instructions running past the end of the ROM, by address, with the address they pass control to.
+    exits: BTreeSet<(usize, usize)>,
+}
+
+impl Analysis {
+    pub fn new(rom: &[u8]) -> Self {
+        let mut analysis = Analysis {
+            rom: rom.to_vec(),
+            states: BTreeMap::new(),
+            code: vec![false; rom.len()],
+            blocks: BTreeMap::new(),
+            subroutines: BTreeMap::new(),
+            exits: BTreeSet::new(),
+        };
+        if analysis.contains(PROGRAM_START) {
+            analysis.follow_paths();
+            analysis.find_blocks();
+        }
+        analysis
+    }
+
+    /// Whether the instruction at `address` is inside the ROM.
+    fn contains(&self, address: usize) -> bool {
+        address >= PROGRAM_START && address + OPCODE_SIZE <= PROGRAM_START + self.rom.len()
+    }
+
+    fn opcode(&self, address: usize) -> OpCode {
+        let offset = address - PROGRAM_START;
+        OpCode::new(self.rom[offset], self.rom[offset + 1])
+    }
+
+    fn word(&self, address: usize) -> u16 {
+        let offset = address - PROGRAM_START;
+        u16::from_be_bytes([self.rom[offset], self.rom[offset + 1]])
+    }
+
+    /// Visits every reachable instruction, until what is known about the registers before each one stops changing.
+    /// The instruction after a call is visited once the subroutine can return.
+    fn follow_paths(&mut self) {
+        let subroutines = self.find_subroutines();
+        let mut returning: BTreeMap<usize, Vec<usize>> = BTreeMap::new();
+        for (&entry, subroutine) in &subroutines {
+            for &address in &subroutine.returns {
+                returning.entry(address).or_default().push(entry);
+            }
+        }
+
+        self.states.insert(PROGRAM_START, State::reset());
+        let mut pending = vec![PROGRAM_START];
+        while let Some(address) = pending.pop() {
+            let opcode = self.opcode(address);
+            for entry in returning.get(&address).into_iter().flatten() {
+                let callers: Vec<usize> = self.subroutines.get(entry).into_iter().flatten().copied().collect();
+                for caller in callers {
+                    if let Some(state) = self.after_call(caller, &subroutines) {
+                        self.visit(caller + OPCODE_SIZE, state, &mut pending);
+                    }
+                }
+            }
+
+            let after = self.states[&address].after(&opcode);
+            for (target, edge) in successors(address, &opcode) {
+                if !self.contains(target) {
+                    self.exits.insert((address, target));
+                    continue;
+                }
+                let state = match (flow_instruction(&opcode), edge) {
+                    (_, Edge::Call) => {
+                        self.subroutines.entry(target).or_default().insert(address);
+                        after
+                    }
+                    (Instruction::Op0NNN | Instruction::Op2NNN, Edge::Next) => match self.after_call(address, &subroutines) {
+                        Some(state) => state,
+                        None => continue,
+                    },
+                    _ => after,
+                };
+                self.visit(target, state, &mut pending);
+            }
+        }
+        for &address in self.states.keys() {
+            let offset = address - PROGRAM_START;
+            self.code[offset..offset + OPCODE_SIZE].fill(true);
+        }
+    }
+
+    /// Joins `state` into what is known before the instruction at `address`, which is visited again if that changed.
+    fn visit(&mut self, address: usize, state: State, pending: &mut Vec<usize>) {
+        let joined = match self.states.get(&address) {
+            Some(previous) => previous.join(&state),
+            None => state,
+        };
+        if self.states.get(&address) != Some(&joined) {
+            self.states.insert(address, joined);
+            pending.push(address);
+        }
+    }
+
+    /// What is known once the subroutine called at `address` returns, or `None` if no return was reached yet.
+    fn after_call(&self, address: usize, subroutines: &BTreeMap<usize, Subroutine>) -> Option<State> {
+        let before = &self.states[&address];
+        let subroutine = match subroutines.get(&self.opcode(address).get_nnn()) {
+            Some(subroutine) => subroutine,
+            // Machine code outside the ROM may change anything.
+            None => return Some(State::unknown()),
+        };
+        let returned = subroutine
+            .returns
+            .iter()
+            .filter_map(|address| self.states.get(address))
+            .copied()
+            .reduce(|state, other| state.join(&other))?;
+        Some(before.after_return(&returned, subroutine.changes))
+    }
+
+    /// The subroutines called from anywhere the program can reach, by address.
+    fn find_subroutines(&self) -> BTreeMap<usize, Subroutine> {
+        let entries: BTreeSet<usize> = self
+            .reachable_from(PROGRAM_START)
+            .into_iter()
+            .map(|address| self.opcode(address))
+            .filter(|opcode| matches!(flow_instruction(opcode), Instruction::Op0NNN | Instruction::Op2NNN))
+            .map(|opcode| opcode.get_nnn())
+            .filter(|&entry| self.contains(entry))
+            .collect();
+        entries
+            .into_iter()
+            .map(|entry| {
+                let mut subroutine = Subroutine { changes: (0, false), returns: vec![] };
+                for address in self.reachable_from(entry) {
+                    let opcode = self.opcode(address);
+                    let (registers, index) = changes(&opcode);
+                    subroutine.changes = (subroutine.changes.0 | registers, subroutine.changes.1 || index);
+                    if flow_instruction(&opcode) == Instruction::Op00EE {
+                        subroutine.returns.push(address);
+                    }
+                }
+                (entry, subroutine)
+            })
+            .collect()
+    }
+
+    /// The instructions in the ROM that can run after the one at `start`, whatever the registers hold.
+    fn reachable_from(&self, start: usize) -> BTreeSet<usize> {
+        let mut reachable = BTreeSet::from([start]);
+        let mut pending = vec![start];
+        while let Some(address) = pending.pop() {
+            for (target, _) in successors(address, &self.opcode(address)) {
+                if self.contains(target) && reachable.insert(target) {
+                    pending.push(target);
+                }
+            }
+        }
+        reachable
+    }
+
+    /// Splits the reachable instructions into blocks, which start at the entry point, at the targets of jumps, calls
+    /// and skips, and after instructions that may not run the next one.
+    fn find_blocks(&mut self) {
+        let mut leaders = BTreeSet::from([PROGRAM_START]);
+        for &address in self.states.keys() {
+            let successors = successors(address, &self.opcode(address));
+            if successors != [(address + OPCODE_SIZE, Edge::Next)] {
+                leaders.extend(successors.iter().map(|&(target, _)| target));
+            }
+        }
+        leaders.retain(|address| self.states.contains_key(address));
+
+        for &start in &leaders {
+            let mut block = Block { instructions: vec![], successors: vec![] };
+            let mut address = start;
+            loop {
+                block.instructions.push(address);
+                let successors = successors(address, &self.opcode(address));
+                let next = address + OPCODE_SIZE;
+                if successors == [(next, Edge::Next)] && self.states.contains_key(&next) && !leaders.contains(&next) {
+                    address = next;
+                    continue;
+                }
+                block.successors = successors.into_iter().filter(|(target, _)| self.states.contains_key(target)).collect();
+                break;
+            }
+            self.blocks.insert(start, block);
+        }
+    }
+
+    pub fn blocks(&self) -> &BTreeMap<usize, Block> {
+        &self.blocks
+    }
+
+    /// The addresses calling each subroutine, by the address of the subroutine.
+    pub fn subroutines(&self) -> &BTreeMap<usize, BTreeSet<usize>> {
+        &self.subroutines
+    }
+
+    /// Ranges of bytes of the ROM that no reachable instruction uses, such as sprites, other data and code only
+    /// reached through computed jumps.
+    pub fn unreachable(&self) -> Vec<Range<usize>> {
+        let mut ranges: Vec<Range<usize>> = vec![];
+        for (offset, _) in self.code.iter().enumerate().filter(|(_, &code)| !code) {
+            let address = PROGRAM_START + offset;
+            match ranges.last_mut() {
+                Some(range) if range.end == address => range.end += 1,
+                _ => ranges.push(address..address + 1),
+            }
+        }
+        ranges
+    }
+
+    /// The addresses of the BNNN jumps, whose targets depend on V0 and are not followed.
+    pub fn computed_jumps(&self) -> Vec<usize> {
+        self.find(|opcode, _| opcode.instruction() == Instruction::OpBNNN)
+    }
+
+    /// The FX33 and FX55 instructions that write over reachable code, with the bytes they write.
+    pub fn writes_to_code(&self) -> Vec<(usize, Range<usize>)> {
+        self.writes()
+            .into_iter()
+            .filter_map(|(address, written)| Some((address, written?)))
+            .filter(|(_, written)| written.clone().any(|address| self.is_code(address)))
+            .collect()
+    }
+
+    /// The FX33 and FX55 instructions whose I is not known, which may write anywhere.
+    pub fn unresolved_writes(&self) -> Vec<usize> {
+        self.writes().into_iter().filter(|(_, written)| written.is_none()).map(|(address, _)| address).collect()
+    }
+
+    /// The instructions writing to memory, with the bytes they write when I is known.
+    fn writes(&self) -> Vec<(usize, Option<Range<usize>>)> {
+        let mut writes = vec![];
+        for (&address, state) in &self.states {
+            let opcode = self.opcode(address);
+            let length = match opcode.instruction() {
+                Instruction::OpFX33 => 3,
+                Instruction::OpFX55 => opcode.lr as usize + 1,
+                _ => continue,
+            };
+            let written = match state.index {
+                Value::Known(index) => Some(index as usize..index as usize + length),
+                Value::Unknown => None,
+            };
+            writes.push((address, written));
+        }
+        writes
+    }
+
+    fn is_code(&self, address: usize) -> bool {
+        address >= PROGRAM_START && self.code.get(address - PROGRAM_START) == Some(&true)
+    }
+
+    /// The instructions that depend on each behaviour interpreters disagree on, by the name of the behaviour. Sprites
+    /// are only listed when they are drawn across an edge of the screen at a position known without running the ROM.
+    pub fn quirks(&self) -> BTreeMap<&'static str, Vec<usize>> {
+        let mut quirks: BTreeMap<&'static str, Vec<usize>> =
+            ["shift", "load_store", "jump_with_offset", "sprite_wrap"].iter().map(|&name| (name, vec![])).collect();
+        for (&address, state) in &self.states {
+            if let Some(name) = quirk(&self.opcode(address), state) {
+                quirks.entry(name).or_default().push(address);
+            }
+        }
+        quirks
+    }
+
+    /// The keys the reachable instructions read.
+    pub fn keys(&self) -> Keys {
+        let mut keys = Keys::default();
+        for (&address, state) in &self.states {
+            let opcode = self.opcode(address);
+            match opcode.instruction() {
+                Instruction::OpEX9E | Instruction::OpEXA1 => match state.registers[opcode.lr as usize] {
+                    Value::Known(key) => {
+                        keys.tested.insert(key as u8 & 0xF);
+                    }
+                    Value::Unknown => keys.computed.push(address),
+                },
+                Instruction::OpFX0A => keys.waits.push(address),
+                _ => {}
+            }
+        }
+        keys
+    }
+
+    /// The name of the first quirk profile, of CHIP-8, SUPER-CHIP and XO-CHIP, whose platform has every reachable
+    /// instruction.
+    pub fn platform(&self) -> &'static str {
+        let platform = self.states.keys().map(|&address| platform(self.word(address))).max().unwrap_or(0);
+        Quirks::profiles()[platform].0
+    }
+
+    /// Reachable instructions that no interpreter knows, which this one skips.
+    pub fn unknown_opcodes(&self) -> Vec<usize> {
+        self.find(|opcode, _| opcode.instruction() == Instruction::Unknown)
+    }
+
+    fn find(&self, predicate: impl Fn(&OpCode, &State) -> bool) -> Vec<usize> {
+        self.states
+            .iter()
+            .filter(|(&address, state)| predicate(&self.opcode(address), state))
+            .map(|(&address, _)| address)
+            .collect()
+    }
+
+    /// The control-flow graph in the Graphviz DOT language, with a node for each block listing its instructions.
+    /// Subroutines are drawn with a double border, calls with dashed edges, and blocks writing over code in red.
+    pub fn to_dot(&self, symbols: &Symbols) -> String {
+        let writers: BTreeSet<usize> = self.writes_to_code().iter().map(|&(address, _)| address).collect();
+        let mut dot = String::from("digraph rom {\n    node [shape=box, fontname=monospace];\n");
+        for (&start, block) in &self.blocks {
+            let mut label = String::new();
+            for &address in &block.instructions {
+                if let Some(name) = symbols.name(address) {
+                    write!(label, "{}:\\l", escape(name)).unwrap();
+                }
+                let instruction = disassemble_with_symbols(self.word(address), symbols);
+                write!(label, "{:03X}  {}\\l", address, escape(&instruction)).unwrap();
+            }
+            let mut attributes = format!("label=\"{}\"", label);
+            if self.subroutines.contains_key(&start) {
+                attributes.push_str(", peripheries=2");
+            }
+            if block.instructions.iter().any(|address| writers.contains(address)) {
+                attributes.push_str(", color=red");
+            }
+            writeln!(dot, "    n{:03X} [{}];", start, attributes).unwrap();
+
+            for &(target, edge) in &block.successors {
+                let attributes = match edge {
+                    Edge::Next | Edge::Jump => "",
+                    Edge::Skip => " [label=skip]",
+                    Edge::Call => " [style=dashed]",
+                };
+                writeln!(dot, "    n{:03X} -> n{:03X}{};", start, target, attributes).unwrap();
+            }
+            let last = *block.instructions.last().unwrap();
+            if self.opcode(last).instruction() == Instruction::OpBNNN {
+                let base = self.opcode(last).get_nnn();
+                writeln!(dot, "    n{:03X}_computed [label=\"0x{:03X} + v0\", shape=ellipse];", start, base).unwrap();
+                writeln!(dot, "    n{:03X} -> n{:03X}_computed;", start, start).unwrap();
+            }
+        }
+        dot.push_str("}\n");
+        dot
+    }
+
+    /// The findings of the analysis as JSON. Addresses are numbers, and ranges have an inclusive start and an
+    /// exclusive end.
+    pub fn to_json(&self, symbols: &Symbols) -> Json {
+        let range = |range: &Range<usize>| json!({ "start": range.start, "end": range.end });
+        let keys = self.keys();
+        json!({
+            "size": self.rom.len(),
+            "platform": self.platform(),
+            "instructions": self.states.len(),
+            "blocks": self.blocks.len(),
+            "subroutines": self.subroutines.iter().map(|(&address, callers)| {
+                json!({ "address": address, "name": symbols.name(address), "callers": callers })
+            }).collect::<Vec<_>>(),
+            "unreachable": self.unreachable().iter().map(range).collect::<Vec<_>>(),
+            "computed_jumps": self.computed_jumps().iter().map(|&address| {
+                json!({ "address": address, "base": self.opcode(address).get_nnn() })
+            }).collect::<Vec<_>>(),
+            "writes_to_code": self.writes_to_code().iter().map(|(address, written)| {
+                json!({ "address": address, "written": range(written) })
+            }).collect::<Vec<_>>(),
+            "unresolved_writes": self.unresolved_writes(),
+            "exits": self.exits.iter().map(|&(address, target)| {
+                json!({ "address": address, "target": target })
+            }).collect::<Vec<_>>(),
+            "keys": { "tested": keys.tested, "computed": keys.computed, "waits": keys.waits },
+            "unknown_opcodes": self.unknown_opcodes(),
+            "quirks": self.quirks(),
+        })
+    }
+}
+
+/// Escapes quotes and backslashes in a DOT string.
+fn escape(text: &str) -> String {
+    text.replace('\\', "\\\\").replace('"', "\\\"")
+}
+
+#[cfg(test)]
+#[path = "./analyzer_tests.rs"]
+mod analyzer_tests;
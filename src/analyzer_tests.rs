@@ -0,0 +1,126 @@
+use super::*;
+
+fn analyze(opcodes: &[u16]) -> Analysis {
+    let rom: Vec<u8> = opcodes.iter().flat_map(|opcode| opcode.to_be_bytes()).collect();
+    Analysis::new(&rom)
+}
+
+#[test]
+fn test_splits_blocks_at_calls_and_skips() {
+    // Calls a subroutine, then skips to one of two loops.
+    let analysis = analyze(&[0x6005, 0x220A, 0x3005, 0x1206, 0x1208, 0x00EE]);
+
+    let blocks: Vec<(usize, &Block)> = analysis.blocks().iter().map(|(&start, block)| (start, block)).collect();
+    assert_eq!(blocks, [
+        (0x200, &Block { instructions: vec![0x200, 0x202], successors: vec![(0x20A, Edge::Call), (0x204, Edge::Next)] }),
+        (0x204, &Block { instructions: vec![0x204], successors: vec![(0x206, Edge::Next), (0x208, Edge::Skip)] }),
+        (0x206, &Block { instructions: vec![0x206], successors: vec![(0x206, Edge::Jump)] }),
+        (0x208, &Block { instructions: vec![0x208], successors: vec![(0x208, Edge::Jump)] }),
+        (0x20A, &Block { instructions: vec![0x20A], successors: vec![] }),
+    ]);
+    assert_eq!(analysis.subroutines()[&0x20A], BTreeSet::from([0x202]));
+    assert!(analysis.unreachable().is_empty());
+}
+
+#[test]
+fn test_reports_unreachable_bytes_computed_jumps_and_quirks() {
+    // Draws a sprite, shifts and jumps through a table, which leaves the FX65 after it and the sprite unreachable.
+    let analysis = analyze(&[0xA20A, 0xD015, 0x8016, 0xB20C, 0xF065, 0xF090, 0x90F0]);
+
+    let unreachable = analysis.unreachable();
+    assert_eq!(unreachable.len(), 1);
+    assert_eq!(unreachable[0], 0x208..0x20E);
+    assert_eq!(analysis.computed_jumps(), [0x206]);
+    let quirks = analysis.quirks();
+    assert_eq!(quirks["shift"], [0x204]);
+    assert_eq!(quirks["jump_with_offset"], [0x206]);
+    assert!(quirks["load_store"].is_empty());
+    assert!(quirks["sprite_wrap"].is_empty());
+}
+
+#[test]
+fn test_reports_sprites_crossing_the_screen_edges() {
+    // The first sprite is drawn 60 rows down, past the bottom edge, and the second one where the path taken decides.
+    let analysis = analyze(&[0x603C, 0xD105, 0x6100, 0xC001, 0x3000, 0x6101, 0xD015, 0x120E, 0x120E]);
+
+    assert_eq!(analysis.quirks()["sprite_wrap"], [0x202]);
+}
+
+#[test]
+fn test_reports_writes_to_code() {
+    // Saves V0 over the 7001 it jumps to, then repeats.
+    let analysis = analyze(&[0xA206, 0xF055, 0x1206, 0x7001, 0x1200]);
+
+    assert_eq!(analysis.writes_to_code(), [(0x202, 0x206..0x207)]);
+    assert!(analysis.unresolved_writes().is_empty());
+}
+
+#[test]
+fn test_reports_writes_to_unknown_addresses() {
+    let analysis = analyze(&[0xA300, 0xC0FF, 0xF01E, 0xF033, 0x1208]);
+
+    assert!(analysis.writes_to_code().is_empty());
+    assert_eq!(analysis.unresolved_writes(), [0x206]);
+}
+
+#[test]
+fn test_follows_registers_through_subroutines() {
+    // The subroutine sets V1 and I, which the caller then tests a key with and saves to, and leaves V2 as it was.
+    let analysis = analyze(&[0x6205, 0x220C, 0xE19E, 0xE2A1, 0xF055, 0x120A, 0x6107, 0xA300, 0x00EE]);
+
+    assert_eq!(analysis.keys(), Keys { tested: BTreeSet::from([5, 7]), computed: vec![], waits: vec![] });
+    assert!(analysis.unresolved_writes().is_empty());
+
+    // A random V1 and an I that depends on it.
+    let analysis = analyze(&[0x6205, 0x220C, 0xE19E, 0xE2A1, 0xF055, 0x120A, 0xC107, 0xF11E, 0x00EE]);
+
+    assert_eq!(analysis.keys(), Keys { tested: BTreeSet::from([5]), computed: vec![0x204], waits: vec![] });
+    assert_eq!(analysis.unresolved_writes(), [0x208]);
+}
+
+#[test]
+fn test_skips_the_return_site_of_subroutines_that_never_return() {
+    let analysis = analyze(&[0x2204, 0xF00A, 0x1204]);
+
+    assert_eq!(analysis.keys().waits, Vec::<usize>::new());
+    let unreachable = analysis.unreachable();
+    assert_eq!(unreachable.len(), 1);
+    assert_eq!(unreachable[0], 0x202..0x204);
+}
+
+#[test]
+fn test_detects_platform_from_instructions() {
+    assert_eq!(analyze(&[0x00E0, 0xF00A, 0x1204]).platform(), "chip8");
+    assert_eq!(analyze(&[0x00FF, 0xD120, 0x1204]).platform(), "schip");
+    assert_eq!(analyze(&[0x00FF, 0x5122, 0x1204]).platform(), "xochip");
+    // Data that is never run does not count.
+    assert_eq!(analyze(&[0x1202, 0x1202, 0x00FF]).platform(), "chip8");
+}
+
+#[test]
+fn test_writes_dot_and_json() {
+    let symbols = Symbols::parse("20A draw\n").unwrap();
+    let analysis = analyze(&[0x6005, 0x220A, 0x3005, 0x1206, 0x1208, 0x00EE, 0x6000]);
+
+    let dot = analysis.to_dot(&symbols);
+    assert!(dot.starts_with("digraph rom {\n"));
+    assert!(dot.contains("    n200 [label=\"200  v0 := 0x05\\l202  :call draw\\l\"];\n"));
+    assert!(dot.contains("    n200 -> n20A [style=dashed];\n"));
+    assert!(dot.contains("    n204 -> n208 [label=skip];\n"));
+    assert!(dot.contains("    n20A [label=\"draw:\\l20A  return\\l\", peripheries=2];\n"));
+
+    let json = analysis.to_json(&symbols);
+    assert_eq!(json["instructions"], 6);
+    assert_eq!(json["subroutines"], json!([{ "address": 0x20A, "name": "draw", "callers": [0x202] }]));
+    assert_eq!(json["unreachable"], json!([{ "start": 0x20C, "end": 0x20E }]));
+}
+
+#[test]
+fn test_reports_paths_leaving_the_rom() {
+    let analysis = analyze(&[0x6000, 0x3000, 0x1100]);
+
+    assert_eq!(analysis.to_json(&Symbols::default())["exits"], json!([
+        { "address": 0x202, "target": 0x206 },
+        { "address": 0x204, "target": 0x100 },
+    ]));
+}
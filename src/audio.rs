@@ -0,0 +1,96 @@
+//! Renders the emulated audio to samples, a frame at a time, for the audio device and WAV recordings.
+use crate::cpu::{OutputState, FRAME_RATE};
+
+/// Sample rate asked of the audio device and used for recordings without one.
+pub const SAMPLE_RATE: i32 = 44100;
+
+const BEEP_FREQUENCY: f32 = 440.0;
+
+struct SquareWave {
+    phase_inc: f32,
+    phase: f32,
+    volume: f32,
+}
+
+impl SquareWave {
+    fn render(&mut self, count: usize, out: &mut Vec<f32>) {
+        for _ in 0..count {
+            out.push(if self.phase >= 0.0 && self.phase < 0.5 { self.volume } else { -self.volume });
+            self.phase = (self.phase + self.phase_inc) % 1.0;
+        }
+    }
+}
+
+/// XO-CHIP audio: a 128 sample 1-bit pattern looped at a rate set by the pitch register.
+struct PatternWave {
+    phase: f64,
+    volume: f32,
+}
+
+impl PatternWave {
+    fn render(&mut self, pattern: &[u8; 16], pitch: u8, sample_rate: i32, count: usize, out: &mut Vec<f32>) {
+        let playback_rate = 4000.0 * 2f64.powf((pitch as f64 - 64.0) / 48.0);
+        let phase_inc = playback_rate / sample_rate as f64;
+        for _ in 0..count {
+            let bit = self.phase as usize;
+            let set = (pattern[bit / 8] >> (7 - bit % 8)) & 0x01 == 1;
+            out.push(if set { self.volume } else { -self.volume });
+            self.phase = (self.phase + phase_inc) % 128.0;
+        }
+    }
+}
+
+/// Turns the sound state of each emulated frame into samples. Frames get a whole number of samples, with the
+/// fraction left over carried to the next one so that the audio keeps pace with the frames.
+pub struct AudioRenderer {
+    sample_rate: i32,
+    wave: SquareWave,
+    pattern_wave: PatternWave,
+    samples_per_frame: f64,
+    sample_remainder: f64,
+}
+
+impl AudioRenderer {
+    pub fn new(sample_rate: i32) -> Self {
+        AudioRenderer {
+            sample_rate,
+            wave: SquareWave {
+                phase_inc: BEEP_FREQUENCY / sample_rate as f32,
+                phase: 0.0,
+                volume: 1.0,
+            },
+            pattern_wave: PatternWave {
+                phase: 0.0,
+                volume: 1.0,
+            },
+            samples_per_frame: sample_rate as f64 / FRAME_RATE as f64,
+            sample_remainder: 0.0,
+        }
+    }
+
+    pub fn sample_rate(&self) -> i32 {
+        self.sample_rate
+    }
+
+    pub fn samples_per_frame(&self) -> f64 {
+        self.samples_per_frame
+    }
+
+    /// Appends the samples of one emulated frame to `out`. While the sound timer is active this is the XO-CHIP audio
+    /// pattern if the program loaded one, or the default beep otherwise, and silence the rest of the time.
+    pub fn render_frame(&mut self, output: &OutputState, out: &mut Vec<f32>) {
+        let samples = self.samples_per_frame + self.sample_remainder;
+        let count = samples as usize;
+        self.sample_remainder = samples - count as f64;
+
+        match (output.beep, output.audio_pattern) {
+            (false, _) => out.resize(out.len() + count, 0.0),
+            (true, Some(pattern)) => self.pattern_wave.render(pattern, output.pitch, self.sample_rate, count, out),
+            (true, None) => self.wave.render(count, out),
+        }
+    }
+}
+
+#[cfg(test)]
+#[path = "./audio_tests.rs"]
+mod audio_tests;
@@ -0,0 +1,86 @@
+use super::*;
+use crate::cpu::DEFAULT_PITCH;
+use crate::framebuffer::Framebuffer;
+
+/// Renders a frame with the given sound state.
+fn render(renderer: &mut AudioRenderer, beep: bool, pattern: Option<&[u8; 16]>, pitch: u8) -> Vec<f32> {
+    let vram = Framebuffer::new();
+    let output = OutputState {
+        vram: &vram,
+        vram_changed: false,
+        beep,
+        audio_pattern: pattern,
+        pitch,
+        instructions: 0,
+    };
+    let mut samples = Vec::new();
+    renderer.render_frame(&output, &mut samples);
+    samples
+}
+
+#[test]
+fn test_frame_has_735_samples_at_44100_hz() {
+    let mut renderer = AudioRenderer::new(SAMPLE_RATE);
+
+    assert_eq!(render(&mut renderer, false, None, DEFAULT_PITCH).len(), 735);
+    assert_eq!(render(&mut renderer, true, None, DEFAULT_PITCH).len(), 735);
+}
+
+#[test]
+fn test_fraction_of_a_sample_is_carried_to_the_next_frame() {
+    // 735.5 samples per frame.
+    let mut renderer = AudioRenderer::new(44130);
+
+    let counts: Vec<usize> = (0..4).map(|_| render(&mut renderer, false, None, DEFAULT_PITCH).len()).collect();
+
+    assert_eq!(counts, [735, 736, 735, 736]);
+}
+
+#[test]
+fn test_silence_without_sound_timer() {
+    let mut renderer = AudioRenderer::new(SAMPLE_RATE);
+
+    let samples = render(&mut renderer, false, Some(&[0xFF; 16]), DEFAULT_PITCH);
+
+    assert!(samples.iter().all(|&sample| sample == 0.0));
+}
+
+#[test]
+fn test_beep_without_pattern_is_a_square_wave() {
+    // Two samples per period of the 440 Hz beep.
+    let mut renderer = AudioRenderer::new(880);
+
+    let samples = render(&mut renderer, true, None, DEFAULT_PITCH);
+
+    assert_eq!(samples[..4], [1.0, -1.0, 1.0, -1.0]);
+}
+
+#[test]
+fn test_pattern_bits_play_most_significant_first() {
+    // One sample per bit at the 4000 Hz of the default pitch.
+    let mut renderer = AudioRenderer::new(4000);
+    let mut pattern = [0x00; 16];
+    pattern[0] = 0x80;
+    pattern[1] = 0x01;
+
+    // 66 samples a frame, so two frames to see the pattern loop.
+    let mut samples = render(&mut renderer, true, Some(&pattern), DEFAULT_PITCH);
+    samples.extend(render(&mut renderer, true, Some(&pattern), DEFAULT_PITCH));
+
+    assert_eq!(samples[0], 1.0);
+    assert!(samples[1..15].iter().all(|&sample| sample == -1.0));
+    assert_eq!(samples[15], 1.0);
+    assert_eq!(samples[16], -1.0);
+    // The pattern loops after 128 bits.
+    assert_eq!(samples[128], 1.0);
+}
+
+#[test]
+fn test_pitch_sets_pattern_rate() {
+    // 48 above the default pitch doubles the rate to 8000 Hz, which skips every other bit at 4000 samples a second.
+    let mut renderer = AudioRenderer::new(4000);
+
+    let samples = render(&mut renderer, true, Some(&[0xAA; 16]), DEFAULT_PITCH + 48);
+
+    assert!(samples.iter().all(|&sample| sample == 1.0));
+}
@@ -1,12 +1,82 @@
-use rand::Rng;
+use std::fmt;
+use std::ops::Range;
+
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
 use crate::fonts::FONT_SET;
+use crate::framebuffer::{Framebuffer, HEIGHT, WIDTH};
+use crate::profiler::Profile;
+use crate::quirks::{Collision, Quirks};
+use crate::trace::Tracer;
+#[cfg(feature = "jit")]
+use crate::jit::{Jit, Machine};
 
 pub struct OutputState<'a> {
-    pub vram: &'a [[u8; 64]; 32],
+    pub vram: &'a Framebuffer,
+    /// Whether any pixel changed since the start of the frame, see `Framebuffer::dirty_rows` for which.
     pub vram_changed: bool,
     pub beep: bool,
+    pub audio_pattern: Option<&'a [u8; 16]>,
+    pub pitch: u8,
+    /// Instructions executed in the last frame.
+    pub instructions: u32,
+}
+
+/// Registers and timers at one point of the execution, with the instruction at the program counter.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Snapshot {
+    pub pc: usize,
+    pub opcode: u16,
+    pub registers: [u8; 16],
+    pub index: u16,
+    pub delay_timer: u8,
+    pub sound_timer: u8,
 }
 
+/// A register as exposed to debuggers.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Register {
+    V(usize),
+    I,
+    Pc,
+    Sp,
+    Delay,
+    Sound,
+}
+
+/// Why the CPU could not execute an instruction. The program counter is left on the instruction.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum CpuError {
+    /// The ROM does not fit in memory above 0x200.
+    RomTooLarge { size: usize, max_size: usize },
+    /// The program counter left memory.
+    PcOutOfBounds { pc: usize },
+    /// A subroutine was called with all 16 levels of the stack in use.
+    StackOverflow { pc: usize },
+    /// A subroutine returned without having been called.
+    StackUnderflow { pc: usize },
+    /// An instruction read or wrote memory past its end, starting at `address`.
+    MemoryOutOfBounds { pc: usize, address: usize },
+}
+
+impl fmt::Display for CpuError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            CpuError::RomTooLarge { size, max_size } => {
+                write!(f, "The ROM is {} bytes, only {} fit in memory", size, max_size)
+            }
+            CpuError::PcOutOfBounds { pc } => write!(f, "The program counter left memory at {:03X}", pc),
+            CpuError::StackOverflow { pc } => write!(f, "Stack overflow at {:03X}", pc),
+            CpuError::StackUnderflow { pc } => write!(f, "Return without a call at {:03X}", pc),
+            CpuError::MemoryOutOfBounds { pc, address } => {
+                write!(f, "Memory access past the end of memory at {:03X}, from address {:03X}", pc, address)
+            }
+        }
+    }
+}
+
+impl std::error::Error for CpuError {}
+
 #[derive(PartialEq)]
 #[derive(Debug)]
 enum ProgramCounter {
@@ -17,8 +87,37 @@ enum ProgramCounter {
 
 const OPCODE_SIZE: usize = 2;
 
-#[derive(Debug)]
-struct OpCode {
+/// Rate of the emulated frames, at which the delay and sound timers count down.
+pub const FRAME_RATE: u32 = 60;
+
+/// Machine cycles the COSMAC VIP interpreter gets per 60 Hz frame: a 1.76 MHz clock with 8 clocks per machine cycle.
+pub const VIP_CYCLES_PER_FRAME: i32 = 3668;
+
+/// How many instructions run in each emulated frame.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Timing {
+    /// A fixed number of instructions per frame.
+    Instructions(u32),
+    /// Each instruction costs its COSMAC VIP machine cycles, and drawing a sprite waits for the next vertical blank.
+    Vip,
+}
+
+/// What `CPU::reset` reinitialises besides the registers, timers, stack and screen.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Reset {
+    /// Keeps RAM as the program left it, like the reset switch of machines whose memory survives it, if the quirk
+    /// profile has `keep_ram_on_reset`. Otherwise the same as `Hard`.
+    Soft,
+    /// Clears RAM and loads the ROM again, like powering the machine off and on.
+    Hard,
+}
+
+/// XO-CHIP pitch register value that plays the audio pattern at 4000 Hz.
+pub const DEFAULT_PITCH: u8 = 64;
+
+/// An instruction split into its four hexadecimal digits, from the most significant one.
+#[derive(Clone, Copy, Debug)]
+pub struct OpCode {
     pub ll: u8,
     pub lr: u8,
     pub rl: u8,
@@ -42,380 +141,945 @@ impl OpCode {
     pub fn get_nn(&self) -> u8 {
         ((self.rl) << 4) | self.rr
     }
+
+    /// The instruction this opcode encodes. Any 00X0 clears the screen and any other 00XX returns, so that a program
+    /// running into zeroed memory clears the screen instead of calling address 0x000 until the stack overflows.
+    pub fn instruction(&self) -> Instruction {
+        match (self.ll, self.lr, self.rl, self.rr) {
+            (0x0, 0x0,   _, 0x0) => Instruction::Op00E0,
+            (0x0, 0x0,   _,   _) => Instruction::Op00EE,
+            (0x0,   _,   _,   _) => Instruction::Op0NNN,
+            (0x1,   _,   _,   _) => Instruction::Op1NNN,
+            (0x2,   _,   _,   _) => Instruction::Op2NNN,
+            (0x3,   _,   _,   _) => Instruction::Op3XNN,
+            (0x4,   _,   _,   _) => Instruction::Op4XNN,
+            (0x5,   _,   _, 0x0) => Instruction::Op5XY0,
+            (0x6,   _,   _,   _) => Instruction::Op6XNN,
+            (0x7,   _,   _,   _) => Instruction::Op7XNN,
+            (0x8,   _,   _, 0x0) => Instruction::Op8XY0,
+            (0x8,   _,   _, 0x1) => Instruction::Op8XY1,
+            (0x8,   _,   _, 0x2) => Instruction::Op8XY2,
+            (0x8,   _,   _, 0x3) => Instruction::Op8XY3,
+            (0x8,   _,   _, 0x4) => Instruction::Op8XY4,
+            (0x8,   _,   _, 0x5) => Instruction::Op8XY5,
+            (0x8,   _,   _, 0x6) => Instruction::Op8XY6,
+            (0x8,   _,   _, 0x7) => Instruction::Op8XY7,
+            (0x8,   _,   _, 0xE) => Instruction::Op8XYE,
+            (0x9,   _,   _, 0x0) => Instruction::Op9XY0,
+            (0xA,   _,   _,   _) => Instruction::OpANNN,
+            (0xB,   _,   _,   _) => Instruction::OpBNNN,
+            (0xC,   _,   _,   _) => Instruction::OpCXNN,
+            (0xD,   _,   _,   _) => Instruction::OpDXYN,
+            (0xE,   _, 0x9, 0xE) => Instruction::OpEX9E,
+            (0xE,   _, 0xA, 0x1) => Instruction::OpEXA1,
+            (0xF, 0x0, 0x0, 0x2) => Instruction::OpF002,
+            (0xF,   _, 0x0, 0x7) => Instruction::OpFX07,
+            (0xF,   _, 0x0, 0xA) => Instruction::OpFX0A,
+            (0xF,   _, 0x1, 0x5) => Instruction::OpFX15,
+            (0xF,   _, 0x1, 0x8) => Instruction::OpFX18,
+            (0xF,   _, 0x1, 0xE) => Instruction::OpFX1E,
+            (0xF,   _, 0x2, 0x9) => Instruction::OpFX29,
+            (0xF,   _, 0x3, 0x3) => Instruction::OpFX33,
+            (0xF,   _, 0x3, 0xA) => Instruction::OpFX3A,
+            (0xF,   _, 0x5, 0x5) => Instruction::OpFX55,
+            (0xF,   _, 0x6, 0x5) => Instruction::OpFX65,
+            _ => Instruction::Unknown,
+        }
+    }
+}
+
+/// The kind of an opcode, which picks its handler once per address instead of matching the opcode on every cycle.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Instruction {
+    Op00E0,
+    Op00EE,
+    Op0NNN,
+    Op1NNN,
+    Op2NNN,
+    Op3XNN,
+    Op4XNN,
+    Op5XY0,
+    Op6XNN,
+    Op7XNN,
+    Op8XY0,
+    Op8XY1,
+    Op8XY2,
+    Op8XY3,
+    Op8XY4,
+    Op8XY5,
+    Op8XY6,
+    Op8XY7,
+    Op8XYE,
+    Op9XY0,
+    OpANNN,
+    OpBNNN,
+    OpCXNN,
+    OpDXYN,
+    OpEX9E,
+    OpEXA1,
+    OpF002,
+    OpFX07,
+    OpFX0A,
+    OpFX15,
+    OpFX18,
+    OpFX1E,
+    OpFX29,
+    OpFX33,
+    OpFX3A,
+    OpFX55,
+    OpFX65,
+    Unknown,
+}
+
+/// An instruction decoded once and kept until the memory holding it is written.
+#[derive(Clone, Copy)]
+struct Decoded {
+    opcode: OpCode,
+    instruction: Instruction,
 }
 
+#[allow(clippy::upper_case_acronyms)]
 pub struct CPU {
     pc: usize,
     sp: usize,
     registers: [u8; 16],
     stack: [usize; 16],
-    ram: [u8; 4096],
-    vram: [[u8; 64]; 32],
-    vram_changed: bool,
+    ram: Vec<u8>,
+    /// Instructions already decoded, by address.
+    decoded: Vec<Option<Decoded>>,
+    rom: Vec<u8>,
+    vram: Framebuffer,
     index: u16,
     delay_timer: u8,
     sound_timer: u8,
     keypad: [bool; 16],
     keypad_waiting: bool,
     keypad_register: usize,
+    keypad_pressed: Option<usize>,
+    quirks: Quirks,
+    audio_pattern: Option<[u8; 16]>,
+    pitch: u8,
+    cycle_budget: i32,
+    frame_cycles: u32,
+    frame_instructions: u32,
+    frame_in_progress: bool,
+    tracer: Option<Tracer>,
+    profile: Option<Profile>,
+    rng: StdRng,
+    #[cfg(feature = "jit")]
+    jit: Option<Jit>,
+}
+
+impl Default for CPU {
+    fn default() -> Self {
+        CPU::new()
+    }
 }
 
 impl CPU {
     pub fn new() -> Self {
+        CPU::with_quirks(Quirks::default())
+    }
 
-        let mut ram = [0u8; 4096];
-        for i in 0..FONT_SET.len() {
-            ram[i] = FONT_SET[i];
-        }
+    pub fn with_quirks(quirks: Quirks) -> Self {
+
+        let mut ram = vec![0u8; quirks.memory_size];
+        ram[..FONT_SET.len()].copy_from_slice(&FONT_SET);
 
         CPU {
             pc: 0x200,
             sp: 0,
             registers: [0; 16],
             stack: [0; 16],
+            decoded: vec![None; ram.len()],
             ram,
-            vram: [[0; 64]; 32],
-            vram_changed: false,
+            rom: Vec::new(),
+            vram: Framebuffer::new(),
             index: 0,
             delay_timer: 0,
             sound_timer: 0,
             keypad: [false; 16],
             keypad_waiting: false,
             keypad_register: 0,
+            keypad_pressed: None,
+            quirks,
+            audio_pattern: None,
+            pitch: DEFAULT_PITCH,
+            cycle_budget: 0,
+            frame_cycles: 0,
+            frame_instructions: 0,
+            frame_in_progress: false,
+            tracer: None,
+            profile: None,
+            rng: StdRng::from_entropy(),
+            #[cfg(feature = "jit")]
+            jit: None,
         }
     }
 
-    pub fn load(&mut self, data: &[u8]) {
-        self.ram[0x200..(0x200 + data.len())].clone_from_slice(data)
+    pub fn load(&mut self, data: &[u8]) -> Result<(), CpuError> {
+        let max_size = self.ram.len() - 0x200;
+        if data.len() > max_size {
+            return Err(CpuError::RomTooLarge { size: data.len(), max_size });
+        }
+        self.ram[0x200..(0x200 + data.len())].clone_from_slice(data);
+        self.invalidate(0x200..0x200 + data.len());
+        self.rom = data.to_vec();
+        Ok(())
     }
 
-    pub fn cycle(&mut self, keypad: [bool; 16]) -> OutputState{
-        self.keypad = keypad;
-        self.vram_changed = false;
+    /// Restarts the loaded program without creating a new CPU.
+    pub fn reset(&mut self, reset: Reset) {
+        if reset == Reset::Hard || !self.quirks.keep_ram_on_reset {
+            let rom = std::mem::take(&mut self.rom);
+            self.ram.fill(0);
+            self.ram[..FONT_SET.len()].copy_from_slice(&FONT_SET);
+            self.decoded.fill(None);
+            #[cfg(feature = "jit")]
+            if let Some(jit) = &mut self.jit {
+                jit.clear();
+            }
+            // The ROM already fitted in memory when it was first loaded.
+            let _ = self.load(&rom);
+        }
 
-        if self.keypad_waiting {
-            for i in 0..keypad.len() {
-                if keypad[i] {
-                    self.keypad_waiting = false;
-                    self.registers[self.keypad_register] = i as u8;
-                    break;
+        self.pc = 0x200;
+        self.sp = 0;
+        self.registers = [0; 16];
+        self.stack = [0; 16];
+        self.vram.clear();
+        self.index = 0;
+        self.delay_timer = 0;
+        self.sound_timer = 0;
+        self.keypad_waiting = false;
+        self.keypad_pressed = None;
+        self.audio_pattern = None;
+        self.pitch = DEFAULT_PITCH;
+        self.cycle_budget = 0;
+        self.frame_in_progress = false;
+    }
+
+    /// Makes CXNN produce the same numbers on every run, for tests and reproducible traces.
+    pub fn seed_rng(&mut self, seed: u64) {
+        self.rng = StdRng::seed_from_u64(seed);
+    }
+
+    pub fn pc(&self) -> usize {
+        self.pc
+    }
+
+    pub fn vram(&self) -> &Framebuffer {
+        &self.vram
+    }
+
+    /// The state before the next instruction, with opcode 0000 if the program counter left memory.
+    pub fn snapshot(&self) -> Snapshot {
+        let opcode = self.fetch().unwrap_or(OpCode::new(0, 0));
+        Snapshot {
+            pc: self.pc,
+            opcode: ((opcode.ll as u16) << 12) | ((opcode.lr as u16) << 8) | ((opcode.rl as u16) << 4) | opcode.rr as u16,
+            registers: self.registers,
+            index: self.index,
+            delay_timer: self.delay_timer,
+            sound_timer: self.sound_timer,
+        }
+    }
+
+    pub fn register(&self, register: Register) -> u16 {
+        match register {
+            Register::V(x) => self.registers[x] as u16,
+            Register::I => self.index,
+            Register::Pc => self.pc as u16,
+            Register::Sp => self.sp as u16,
+            Register::Delay => self.delay_timer as u16,
+            Register::Sound => self.sound_timer as u16,
+        }
+    }
+
+    /// Changes a register, keeping the program and stack pointers inside memory and the stack.
+    pub fn set_register(&mut self, register: Register, value: u16) {
+        match register {
+            Register::V(x) => self.registers[x] = value as u8,
+            Register::I => self.index = value,
+            Register::Pc => self.pc = (value as usize).min(self.ram.len() - OPCODE_SIZE),
+            Register::Sp => self.sp = (value as usize).min(self.stack.len()),
+            Register::Delay => self.delay_timer = value as u8,
+            Register::Sound => self.sound_timer = value as u8,
+        }
+    }
+
+    /// Return addresses of the subroutines being run, the innermost last.
+    pub fn call_stack(&self) -> &[usize] {
+        &self.stack[..self.sp]
+    }
+
+    pub fn memory(&self) -> &[u8] {
+        &self.ram
+    }
+
+    /// Memory to write to, which throws away every decoded instruction since any of them may change.
+    pub fn memory_mut(&mut self) -> &mut [u8] {
+        self.decoded.fill(None);
+        #[cfg(feature = "jit")]
+        if let Some(jit) = &mut self.jit {
+            jit.clear();
+        }
+        &mut self.ram
+    }
+
+    /// Logs every instruction executed from now on to `tracer`.
+    pub fn set_tracer(&mut self, tracer: Tracer) {
+        self.tracer = Some(tracer);
+    }
+
+    /// Counts the instructions run at each address and the reads and writes of each byte from now on.
+    pub fn enable_profiling(&mut self) {
+        self.profile = Some(Profile::new(self.ram.len()));
+    }
+
+    pub fn profile(&self) -> Option<&Profile> {
+        self.profile.as_ref()
+    }
+
+    /// Runs basic blocks of instruction timing frames as native code from now on. Since `run_frame_until` only checks
+    /// whether to stop between blocks, and tracing and profiling need every instruction, debuggers and tracers should not enable it.
+    /// Fails if no executable memory can be mapped.
+    #[cfg(feature = "jit")]
+    pub fn enable_jit(&mut self) -> std::io::Result<()> {
+        self.jit = Some(Jit::new(self.ram.len())?);
+        Ok(())
+    }
+
+    #[cfg(feature = "jit")]
+    pub fn jit(&self) -> Option<&Jit> {
+        self.jit.as_ref()
+    }
+
+    /// Runs one emulated frame: executes the instructions that fit in it under `timing` and then counts the timers down.
+    pub fn run_frame(&mut self, keypad: [bool; 16], timing: Timing) -> Result<OutputState<'_>, CpuError> {
+        self.run_frame_until(keypad, timing, |_| false)?;
+        Ok(self.output_state())
+    }
+
+    /// Like `run_frame`, but stops before executing an instruction when `stop` returns true for the CPU about to run it.
+    /// Returns whether the frame completed. A stopped frame carries on from where it stopped on the next call, and so
+    /// does a frame that failed once the cause of the error is dealt with.
+    pub fn run_frame_until(
+        &mut self,
+        keypad: [bool; 16],
+        timing: Timing,
+        mut stop: impl FnMut(&CPU) -> bool,
+    ) -> Result<bool, CpuError> {
+        if !self.frame_in_progress {
+            self.frame_in_progress = true;
+            self.vram.clear_dirty();
+            self.frame_cycles = 0;
+            self.frame_instructions = 0;
+            if timing == Timing::Vip {
+                // Cycles overspent by the last instruction of a frame are taken from the next one.
+                self.cycle_budget += VIP_CYCLES_PER_FRAME;
+            }
+        }
+
+        match timing {
+            Timing::Instructions(instructions) => {
+                while self.frame_cycles < instructions {
+                    if !self.keypad_waiting && stop(self) {
+                        return Ok(false);
+                    }
+                    #[cfg(feature = "jit")]
+                    if let Some(instructions) = self.run_block(keypad, instructions - self.frame_cycles) {
+                        self.frame_cycles += instructions;
+                        continue;
+                    }
+                    self.step(keypad)?;
+                    self.frame_cycles += 1;
                 }
             }
+            Timing::Vip => {
+                while self.cycle_budget > 0 {
+                    if self.keypad_waiting {
+                        self.step(keypad)?;
+                        if self.keypad_waiting {
+                            self.cycle_budget = 0;
+                        }
+                        continue;
+                    }
+                    if stop(self) {
+                        return Ok(false);
+                    }
+
+                    let opcode = self.decode()?.opcode;
+                    self.step(keypad)?;
+                    self.cycle_budget -= self.vip_cycles(&opcode) as i32;
+
+                    if opcode.ll == 0xD {
+                        // The VIP interpreter waits for the vertical blank before drawing a sprite.
+                        self.cycle_budget = self.cycle_budget.min(0);
+                    }
+                }
+            }
+        }
+        self.frame_in_progress = false;
+        if let Some(profile) = &mut self.profile {
+            profile.end_frame();
+        }
+        self.tick_timers();
+        Ok(true)
+    }
+
+    pub fn cycle(&mut self, keypad: [bool; 16]) -> Result<OutputState<'_>, CpuError> {
+        self.step(keypad)?;
+        Ok(self.output_state())
+    }
+
+    /// Runs the compiled block at the program counter if there is one with no more than `max_instructions`, and returns
+    /// the instructions it ran.
+    #[cfg(feature = "jit")]
+    fn run_block(&mut self, keypad: [bool; 16], max_instructions: u32) -> Option<u32> {
+        if self.keypad_waiting || self.tracer.is_some() || self.profile.is_some() {
+            return None;
+        }
+        let jit = self.jit.as_mut()?;
+        let machine = Machine {
+            registers: &mut self.registers,
+            index: &mut self.index,
+            delay_timer: &mut self.delay_timer,
+            sound_timer: &mut self.sound_timer,
+            keypad: &keypad,
+        };
+        let (pc, instructions) = jit.run(self.pc, &self.ram, machine, max_instructions)?;
+        self.pc = pc;
+        self.frame_instructions += instructions;
+        Some(instructions)
+    }
+
+    /// Executes one instruction, or goes on waiting for a key.
+    fn step(&mut self, keypad: [bool; 16]) -> Result<(), CpuError> {
+        self.keypad = keypad;
+
+        if self.keypad_waiting {
+            self.wait_for_key(keypad);
         } else {
-            if self.delay_timer > 0 {
-                self.delay_timer -= 1;
+            let before = self.tracer.as_ref().map(|_| self.snapshot());
+            let decoded = self.decode()?;
+            if self.profile.is_some() {
+                self.profile_instruction(&decoded.opcode);
             }
-            if self.sound_timer > 0 {
-                self.sound_timer -= 1;
+            self.execute(&decoded)?;
+            self.frame_instructions += 1;
+
+            if let Some(before) = before {
+                let after = self.snapshot();
+                if let Some(tracer) = &mut self.tracer {
+                    tracer.log(&before, &after);
+                }
             }
-            self.run_opcode(&self.get_opcode());
         }
+        Ok(())
+    }
+
+    fn profile_instruction(&mut self, opcode: &OpCode) {
+        let cycles = self.vip_cycles(opcode);
+        if let Some(profile) = &mut self.profile {
+            profile.executed(self.pc, cycles);
+        }
+    }
+
+    fn profile_read(&mut self, range: Range<usize>) {
+        if let Some(profile) = &mut self.profile {
+            profile.read(range);
+        }
+    }
+
+    fn profile_write(&mut self, range: Range<usize>) {
+        if let Some(profile) = &mut self.profile {
+            profile.written(range);
+        }
+    }
+
+    /// Completes FX0A once a key is pressed, or once it is released again with the key release quirk.
+    fn wait_for_key(&mut self, keypad: [bool; 16]) {
+        let key = match self.keypad_pressed {
+            Some(key) if keypad[key] => return,
+            Some(key) => key,
+            None => match keypad.iter().position(|&pressed| pressed) {
+                Some(key) if self.quirks.key_release => {
+                    self.keypad_pressed = Some(key);
+                    return;
+                }
+                Some(key) => key,
+                None => return,
+            },
+        };
 
+        self.keypad_waiting = false;
+        self.keypad_pressed = None;
+        self.registers[self.keypad_register] = key as u8;
+    }
+
+    /// Counts the delay and sound timers down, once per emulated frame.
+    pub fn tick_timers(&mut self) {
+        if self.delay_timer > 0 {
+            self.delay_timer -= 1;
+        }
+        if self.sound_timer > 0 {
+            self.sound_timer -= 1;
+        }
+    }
+
+    pub fn output_state(&self) -> OutputState<'_> {
         OutputState {
             vram: &self.vram,
-            vram_changed: self.vram_changed,
-            beep: self.sound_timer > 0,
-        }
-    }
-
-    fn get_opcode(&self) -> OpCode {
-        OpCode::new(self.ram[self.pc], self.ram[self.pc + 1])
-    }
-
-    fn run_opcode(&mut self, opcode: &OpCode) {
-        let pc_change = match (opcode.ll, opcode.lr, opcode.rl, opcode.rr) {
-            (0x0, 0x0,   _, 0x0) => self.execute_op_00e0(opcode),
-            (0x0, 0x0,   _,   _) => self.execute_op_00ee(opcode),
-            (0x0,   _,   _,   _) => self.execute_op_0nnn(opcode),
-            (0x1,   _,   _,   _) => self.execute_op_1nnn(opcode),
-            (0x2,   _,   _,   _) => self.execute_op_2nnn(opcode),
-            (0x3,   _,   _,   _) => self.execute_op_3xnn(opcode),
-            (0x4,   _,   _,   _) => self.execute_op_4xnn(opcode),
-            (0x5,   _,   _, 0x0) => self.execute_op_5xy0(opcode),
-            (0x6,   _,   _,   _) => self.execute_op_6xnn(opcode),
-            (0x7,   _,   _,   _) => self.execute_op_7xnn(opcode),
-            (0x8,   _,   _, 0x0) => self.execute_op_8xy0(opcode),
-            (0x8,   _,   _, 0x1) => self.execute_op_8xy1(opcode),
-            (0x8,   _,   _, 0x2) => self.execute_op_8xy2(opcode),
-            (0x8,   _,   _, 0x3) => self.execute_op_8xy3(opcode),
-            (0x8,   _,   _, 0x4) => self.execute_op_8xy4(opcode),
-            (0x8,   _,   _, 0x5) => self.execute_op_8xy5(opcode),
-            (0x8,   _,   _, 0x6) => self.execute_op_8xy6(opcode),
-            (0x8,   _,   _, 0x7) => self.execute_op_8xy7(opcode),
-            (0x8,   _,   _, 0xE) => self.execute_op_8xye(opcode),
-            (0x9,   _,   _, 0x0) => self.execute_op_9xy0(opcode),
-            (0xA,   _,   _,   _) => self.execute_op_annn(opcode),
-            (0xB,   _,   _,   _) => self.execute_op_bnnn(opcode),
-            (0xC,   _,   _,   _) => self.execute_op_cxnn(opcode),
-            (0xD,   _,   _,   _) => self.execute_op_dxyn(opcode),
-            (0xE,   _, 0x9, 0xE) => self.execute_op_ex9e(opcode),
-            (0xE,   _, 0xA, 0x1) => self.execute_op_exa1(opcode),
-            (0xF,   _, 0x0, 0x7) => self.execute_op_fx07(opcode),
-            (0xF,   _, 0x0, 0xA) => self.execute_op_fx0a(opcode),
-            (0xF,   _, 0x1, 0x5) => self.execute_op_fx15(opcode),
-            (0xF,   _, 0x1, 0x8) => self.execute_op_fx18(opcode),
-            (0xF,   _, 0x1, 0xE) => self.execute_op_fx1e(opcode),
-            (0xF,   _, 0x2, 0x9) => self.execute_op_fx29(opcode),
-            (0xF,   _, 0x3, 0x3) => self.execute_op_fx33(opcode),
-            (0xF,   _, 0x5, 0x5) => self.execute_op_fx55(opcode),
-            (0xF,   _, 0x6, 0x5) => self.execute_op_fx65(opcode),
-            _ => ProgramCounter::Next,
-        };
+            vram_changed: self.vram.dirty_rows() != 0,
+            beep: self.sound_timer > 0 || self.keypad_pressed.is_some(),
+            audio_pattern: self.audio_pattern.as_ref(),
+            pitch: self.pitch,
+            instructions: self.frame_instructions,
+        }
+    }
+
+    /// Machine cycles the COSMAC VIP interpreter takes to execute `opcode` with the current register values.
+    /// Costs are approximations from published measurements of the original interpreter.
+    fn vip_cycles(&self, opcode: &OpCode) -> u32 {
+        let vx = self.registers[opcode.lr as usize] as u32;
+        match (opcode.ll, opcode.lr, opcode.rl, opcode.rr) {
+            (0x0, 0x0,   _, 0x0) => 24,
+            (0x0, 0x0,   _,   _) => 10,
+            (0x0,   _,   _,   _) => 10,
+            (0x1,   _,   _,   _) => 12,
+            (0x2,   _,   _,   _) => 26,
+            (0x3,   _,   _,   _) | (0x4,   _,   _,   _) => 10,
+            (0x5,   _,   _,   _) | (0x9,   _,   _,   _) => 14,
+            (0x6,   _,   _,   _) => 6,
+            (0x7,   _,   _,   _) => 10,
+            (0x8,   _,   _,   _) => 44,
+            (0xA,   _,   _,   _) => 12,
+            (0xB,   _,   _,   _) => 22,
+            (0xC,   _,   _,   _) => 36,
+            (0xD,   _,   _,   n) => {
+                // Sprites not aligned to a byte boundary are shifted and written to two bytes per row.
+                let row_cycles = if vx & 0x07 == 0 { 22 } else { 34 };
+                26 + n as u32 * row_cycles
+            }
+            (0xE,   _,   _,   _) => 14,
+            (0xF,   _, 0x0, 0x7) => 10,
+            (0xF,   _, 0x0, 0xA) => 38,
+            (0xF,   _, 0x1, 0x5) | (0xF,   _, 0x1, 0x8) => 10,
+            (0xF,   _, 0x1, 0xE) => 16,
+            (0xF,   _, 0x2, 0x9) => 16,
+            (0xF,   _, 0x3, 0x3) => {
+                // The digits are found by repeated subtraction.
+                80 + 16 * (vx / 100 + (vx / 10) % 10 + vx % 10)
+            }
+            (0xF,   x, 0x5, 0x5) | (0xF,   x, 0x6, 0x5) => 18 + 14 * (x as u32 + 1),
+            _ => 10,
+        }
+    }
+
+    fn fetch(&self) -> Result<OpCode, CpuError> {
+        match self.ram.get(self.pc..self.pc + OPCODE_SIZE) {
+            Some(bytes) => Ok(OpCode::new(bytes[0], bytes[1])),
+            None => Err(CpuError::PcOutOfBounds { pc: self.pc }),
+        }
+    }
+
+    /// The `length` bytes of memory starting at I, if they are all inside memory.
+    fn index_range(&self, length: usize) -> Result<Range<usize>, CpuError> {
+        let start = self.index as usize;
+        if start + length > self.ram.len() {
+            return Err(CpuError::MemoryOutOfBounds { pc: self.pc, address: start });
+        }
+        Ok(start..start + length)
+    }
+
+    fn push(&mut self, address: usize) -> Result<(), CpuError> {
+        if self.sp == self.stack.len() {
+            return Err(CpuError::StackOverflow { pc: self.pc });
+        }
+        self.stack[self.sp] = address;
+        self.sp += 1;
+        Ok(())
+    }
+
+    /// The instruction at the program counter, decoded from memory the first time it runs.
+    // Inlined, like the handlers, into the loop of `run_frame_until`, which is what makes the cache pay off.
+    #[inline(always)]
+    fn decode(&mut self) -> Result<Decoded, CpuError> {
+        if let Some(Some(decoded)) = self.decoded.get(self.pc) {
+            return Ok(*decoded);
+        }
+        let opcode = self.fetch()?;
+        let decoded = Decoded { opcode, instruction: opcode.instruction() };
+        self.decoded[self.pc] = Some(decoded);
+        Ok(decoded)
+    }
+
+    /// Forgets the decoded instructions that use any byte of `range`, after it is written.
+    fn invalidate(&mut self, range: Range<usize>) {
+        let start = range.start.saturating_sub(OPCODE_SIZE - 1);
+        self.decoded[start..range.end].fill(None);
+        #[cfg(feature = "jit")]
+        if let Some(jit) = &mut self.jit {
+            jit.written(range);
+        }
+    }
+
+    #[inline(always)]
+    fn execute(&mut self, decoded: &Decoded) -> Result<(), CpuError> {
+        let opcode = &decoded.opcode;
+        let pc_change = match decoded.instruction {
+            Instruction::Op00E0 => self.execute_op_00e0(opcode),
+            Instruction::Op00EE => self.execute_op_00ee(opcode),
+            Instruction::Op0NNN => self.execute_op_0nnn(opcode),
+            Instruction::Op1NNN => self.execute_op_1nnn(opcode),
+            Instruction::Op2NNN => self.execute_op_2nnn(opcode),
+            Instruction::Op3XNN => self.execute_op_3xnn(opcode),
+            Instruction::Op4XNN => self.execute_op_4xnn(opcode),
+            Instruction::Op5XY0 => self.execute_op_5xy0(opcode),
+            Instruction::Op6XNN => self.execute_op_6xnn(opcode),
+            Instruction::Op7XNN => self.execute_op_7xnn(opcode),
+            Instruction::Op8XY0 => self.execute_op_8xy0(opcode),
+            Instruction::Op8XY1 => self.execute_op_8xy1(opcode),
+            Instruction::Op8XY2 => self.execute_op_8xy2(opcode),
+            Instruction::Op8XY3 => self.execute_op_8xy3(opcode),
+            Instruction::Op8XY4 => self.execute_op_8xy4(opcode),
+            Instruction::Op8XY5 => self.execute_op_8xy5(opcode),
+            Instruction::Op8XY6 => self.execute_op_8xy6(opcode),
+            Instruction::Op8XY7 => self.execute_op_8xy7(opcode),
+            Instruction::Op8XYE => self.execute_op_8xye(opcode),
+            Instruction::Op9XY0 => self.execute_op_9xy0(opcode),
+            Instruction::OpANNN => self.execute_op_annn(opcode),
+            Instruction::OpBNNN => self.execute_op_bnnn(opcode),
+            Instruction::OpCXNN => self.execute_op_cxnn(opcode),
+            Instruction::OpDXYN => self.execute_op_dxyn(opcode),
+            Instruction::OpEX9E => self.execute_op_ex9e(opcode),
+            Instruction::OpEXA1 => self.execute_op_exa1(opcode),
+            Instruction::OpF002 => self.execute_op_f002(opcode),
+            Instruction::OpFX07 => self.execute_op_fx07(opcode),
+            Instruction::OpFX0A => self.execute_op_fx0a(opcode),
+            Instruction::OpFX15 => self.execute_op_fx15(opcode),
+            Instruction::OpFX18 => self.execute_op_fx18(opcode),
+            Instruction::OpFX1E => self.execute_op_fx1e(opcode),
+            Instruction::OpFX29 => self.execute_op_fx29(opcode),
+            Instruction::OpFX33 => self.execute_op_fx33(opcode),
+            Instruction::OpFX3A => self.execute_op_fx3a(opcode),
+            Instruction::OpFX55 => self.execute_op_fx55(opcode),
+            Instruction::OpFX65 => self.execute_op_fx65(opcode),
+            Instruction::Unknown => self.execute_op_unknown(opcode),
+        }?;
 
         match pc_change {
             ProgramCounter::Next => self.pc += OPCODE_SIZE,
             ProgramCounter::Skip => self.pc += OPCODE_SIZE + OPCODE_SIZE,
             ProgramCounter::Jump(addr) => self.pc = addr,
         }
+        Ok(())
     }
     
+    /// Does nothing, for opcodes that no supported interpreter defines.
+    fn execute_op_unknown(&mut self, _opcode: &OpCode) -> Result<ProgramCounter, CpuError> {
+        Ok(ProgramCounter::Next)
+    }
+
     /// Calls machine code routine (RCA 1802 for COSMAC VIP) at address NNN.
-    fn execute_op_0nnn(&mut self, opcode: &OpCode) -> ProgramCounter {
-        self.stack[self.sp] = self.pc + OPCODE_SIZE;
-        self.sp += 1;
-        ProgramCounter::Jump(opcode.get_nnn())
+    fn execute_op_0nnn(&mut self, opcode: &OpCode) -> Result<ProgramCounter, CpuError> {
+        self.push(self.pc + OPCODE_SIZE)?;
+        Ok(ProgramCounter::Jump(opcode.get_nnn()))
     }
 
     /// Clears the screen.
-    fn execute_op_00e0(&mut self, _opcode: &OpCode) -> ProgramCounter {
-        self.vram.iter_mut().for_each(|x| x.iter_mut().for_each(|y| *y = 0));
-        self.vram_changed = true;
-        ProgramCounter::Next
+    fn execute_op_00e0(&mut self, _opcode: &OpCode) -> Result<ProgramCounter, CpuError> {
+        self.vram.clear();
+        Ok(ProgramCounter::Next)
     }
 
     /// Returns from a subroutine.
-    fn execute_op_00ee(&mut self, _opcode: &OpCode) -> ProgramCounter {
+    fn execute_op_00ee(&mut self, _opcode: &OpCode) -> Result<ProgramCounter, CpuError> {
+        if self.sp == 0 {
+            return Err(CpuError::StackUnderflow { pc: self.pc });
+        }
         self.sp -= 1;
-        ProgramCounter::Jump(self.stack[self.sp])
+        Ok(ProgramCounter::Jump(self.stack[self.sp]))
     }
 
     /// Jumps to address NNN.
-    fn execute_op_1nnn(&mut self, opcode: &OpCode) -> ProgramCounter {
-        ProgramCounter::Jump(opcode.get_nnn())
+    fn execute_op_1nnn(&mut self, opcode: &OpCode) -> Result<ProgramCounter, CpuError> {
+        Ok(ProgramCounter::Jump(opcode.get_nnn()))
     }
 
     /// Calls subroutine at NNN.
-    fn execute_op_2nnn(&mut self, opcode: &OpCode) -> ProgramCounter {
-        self.stack[self.sp] = self.pc + OPCODE_SIZE;
-        self.sp += 1;
-        ProgramCounter::Jump(opcode.get_nnn())
+    fn execute_op_2nnn(&mut self, opcode: &OpCode) -> Result<ProgramCounter, CpuError> {
+        self.push(self.pc + OPCODE_SIZE)?;
+        Ok(ProgramCounter::Jump(opcode.get_nnn()))
     }
 
     /// Skips the next instruction if VX equals NN. (Usually the next instruction is a jump to skip a code block)
-    fn execute_op_3xnn(&mut self, opcode: &OpCode) -> ProgramCounter {
+    fn execute_op_3xnn(&mut self, opcode: &OpCode) -> Result<ProgramCounter, CpuError> {
         if self.registers[opcode.lr as usize] == opcode.get_nn() {
-            return ProgramCounter::Skip;
+            return Ok(ProgramCounter::Skip);
         }
-        ProgramCounter::Next
+        Ok(ProgramCounter::Next)
     }
 
     /// Skips the next instruction if VX doesn't equal NN. (Usually the next instruction is a jump to skip a code block)
-    fn execute_op_4xnn(&mut self, opcode: &OpCode) -> ProgramCounter {
+    fn execute_op_4xnn(&mut self, opcode: &OpCode) -> Result<ProgramCounter, CpuError> {
         if self.registers[opcode.lr as usize] != opcode.get_nn() {
-            return ProgramCounter::Skip;
+            return Ok(ProgramCounter::Skip);
         }
-        ProgramCounter::Next
+        Ok(ProgramCounter::Next)
     }
 
     /// Skips the next instruction if VX equals VY. (Usually the next instruction is a jump to skip a code block)
-    fn execute_op_5xy0(&mut self, opcode: &OpCode) -> ProgramCounter {
+    fn execute_op_5xy0(&mut self, opcode: &OpCode) -> Result<ProgramCounter, CpuError> {
         if self.registers[opcode.lr as usize] == self.registers[opcode.rl as usize] {
-            return ProgramCounter::Skip;
+            return Ok(ProgramCounter::Skip);
         }
-        ProgramCounter::Next
+        Ok(ProgramCounter::Next)
     }
 
     /// Sets VX to NN.
-    fn execute_op_6xnn(&mut self, opcode: &OpCode) -> ProgramCounter {
+    fn execute_op_6xnn(&mut self, opcode: &OpCode) -> Result<ProgramCounter, CpuError> {
         self.registers[opcode.lr as usize] = opcode.get_nn();
-        ProgramCounter::Next
+        Ok(ProgramCounter::Next)
     }
 
     /// Adds NN to VX. (Carry flag is not changed)
-    fn execute_op_7xnn(&mut self, opcode: &OpCode) -> ProgramCounter {
+    fn execute_op_7xnn(&mut self, opcode: &OpCode) -> Result<ProgramCounter, CpuError> {
         let result: u16 = self.registers[opcode.lr as usize] as u16 + opcode.get_nn() as u16;
         self.registers[opcode.lr as usize] = result as u8;
-        ProgramCounter::Next
+        Ok(ProgramCounter::Next)
     }
 
     /// Sets VX to the value of VY.
-    fn execute_op_8xy0(&mut self, opcode: &OpCode) -> ProgramCounter {
+    fn execute_op_8xy0(&mut self, opcode: &OpCode) -> Result<ProgramCounter, CpuError> {
         self.registers[opcode.lr as usize] = self.registers[opcode.rl as usize];
-        ProgramCounter::Next
+        Ok(ProgramCounter::Next)
     }
 
     /// Sets VX to VX or VY. (Bitwise OR operation)
-    fn execute_op_8xy1(&mut self, opcode: &OpCode) -> ProgramCounter {
+    fn execute_op_8xy1(&mut self, opcode: &OpCode) -> Result<ProgramCounter, CpuError> {
         self.registers[opcode.lr as usize] |= self.registers[opcode.rl as usize];
-        ProgramCounter::Next
+        Ok(ProgramCounter::Next)
     }
 
     /// Sets VX to VX and VY. (Bitwise AND operation)
-    fn execute_op_8xy2(&mut self, opcode: &OpCode) -> ProgramCounter {
+    fn execute_op_8xy2(&mut self, opcode: &OpCode) -> Result<ProgramCounter, CpuError> {
         self.registers[opcode.lr as usize] &= self.registers[opcode.rl as usize];
-        ProgramCounter::Next
+        Ok(ProgramCounter::Next)
     }
 
     /// Sets VX to VX xor VY. (Bitwise XOR operation)
-    fn execute_op_8xy3(&mut self, opcode: &OpCode) -> ProgramCounter {
+    fn execute_op_8xy3(&mut self, opcode: &OpCode) -> Result<ProgramCounter, CpuError> {
         self.registers[opcode.lr as usize] ^= self.registers[opcode.rl as usize];
-        ProgramCounter::Next
+        Ok(ProgramCounter::Next)
     }
 
     /// Adds VY to VX. VF is set to 1 when there's a carry, and to 0 when there isn't.
-    fn execute_op_8xy4(&mut self, opcode: &OpCode) -> ProgramCounter {
+    fn execute_op_8xy4(&mut self, opcode: &OpCode) -> Result<ProgramCounter, CpuError> {
         let result: u16 = self.registers[opcode.lr as usize] as u16 + self.registers[opcode.rl as usize] as u16;
         self.registers[opcode.lr as usize] = result as u8;
         self.registers[15] = if result > 0xFF { 1 } else { 0 };
-        ProgramCounter::Next
+        Ok(ProgramCounter::Next)
     }
 
     /// VY is subtracted from VX. VF is set to 0 when there's a borrow, and 1 when there isn't.
-    fn execute_op_8xy5(&mut self, opcode: &OpCode) -> ProgramCounter {
-        self.registers[15] = if self.registers[opcode.lr as usize] > self.registers[opcode.rl as usize] { 1 } else { 0 };
-        self.registers[opcode.lr as usize] = self.registers[opcode.lr as usize].wrapping_sub(self.registers[opcode.rl as usize]);
-        ProgramCounter::Next
+    /// VF is written last so that it holds the flag when X is F.
+    fn execute_op_8xy5(&mut self, opcode: &OpCode) -> Result<ProgramCounter, CpuError> {
+        let (vx, vy) = (self.registers[opcode.lr as usize], self.registers[opcode.rl as usize]);
+        self.registers[opcode.lr as usize] = vx.wrapping_sub(vy);
+        self.registers[15] = (vx >= vy) as u8;
+        Ok(ProgramCounter::Next)
     }
 
     /// Stores the least significant bit of VX in VF and then shifts VX to the right by 1.
-    fn execute_op_8xy6(&mut self, opcode: &OpCode) -> ProgramCounter {
-        self.registers[15] = self.registers[opcode.lr as usize] & 0b00000001;
-        self.registers[opcode.lr as usize] >>= 1;
-        ProgramCounter::Next
+    fn execute_op_8xy6(&mut self, opcode: &OpCode) -> Result<ProgramCounter, CpuError> {
+        let vx = self.registers[opcode.lr as usize];
+        self.registers[opcode.lr as usize] = vx >> 1;
+        self.registers[15] = vx & 0b00000001;
+        Ok(ProgramCounter::Next)
     }
 
     /// Sets VX to VY minus VX. VF is set to 0 when there's a borrow, and 1 when there isn't.
-    fn execute_op_8xy7(&mut self, opcode: &OpCode) -> ProgramCounter {
-        self.registers[15] = if self.registers[opcode.rl as usize] > self.registers[opcode.lr as usize] { 1 } else { 0 };
-        self.registers[opcode.lr as usize] = self.registers[opcode.rl as usize].wrapping_sub(self.registers[opcode.lr as usize]);
-        ProgramCounter::Next
+    fn execute_op_8xy7(&mut self, opcode: &OpCode) -> Result<ProgramCounter, CpuError> {
+        let (vx, vy) = (self.registers[opcode.lr as usize], self.registers[opcode.rl as usize]);
+        self.registers[opcode.lr as usize] = vy.wrapping_sub(vx);
+        self.registers[15] = (vy >= vx) as u8;
+        Ok(ProgramCounter::Next)
     }
 
     /// Stores the most significant bit of VX in VF and then shifts VX to the left by 1.
-    fn execute_op_8xye(&mut self, opcode: &OpCode) -> ProgramCounter {
-        self.registers[15] = self.registers[opcode.lr as usize] >> 7 & 0b00000001;
-        self.registers[opcode.lr as usize] <<= 1;
-        ProgramCounter::Next
+    fn execute_op_8xye(&mut self, opcode: &OpCode) -> Result<ProgramCounter, CpuError> {
+        let vx = self.registers[opcode.lr as usize];
+        self.registers[opcode.lr as usize] = vx << 1;
+        self.registers[15] = vx >> 7 & 0b00000001;
+        Ok(ProgramCounter::Next)
     }
 
     /// Skips the next instruction if VX doesn't equal VY. (Usually the next instruction is a jump to skip a code block)
-    fn execute_op_9xy0(&mut self, opcode: &OpCode) -> ProgramCounter {
+    fn execute_op_9xy0(&mut self, opcode: &OpCode) -> Result<ProgramCounter, CpuError> {
         if self.registers[opcode.lr as usize] != self.registers[opcode.rl as usize] {
-            return ProgramCounter::Skip;
+            return Ok(ProgramCounter::Skip);
         }
-        ProgramCounter::Next
+        Ok(ProgramCounter::Next)
     }
 
     /// Sets I to the address NNN.
-    fn execute_op_annn(&mut self, opcode: &OpCode) -> ProgramCounter {
+    fn execute_op_annn(&mut self, opcode: &OpCode) -> Result<ProgramCounter, CpuError> {
         self.index = opcode.get_nnn() as u16;
-        ProgramCounter::Next
+        Ok(ProgramCounter::Next)
     }
 
     /// Jumps to the address NNN plus V0.
-    fn execute_op_bnnn(&mut self, opcode: &OpCode) -> ProgramCounter {
+    fn execute_op_bnnn(&mut self, opcode: &OpCode) -> Result<ProgramCounter, CpuError> {
         let address = opcode.get_nnn() + self.registers[0] as usize;
-        ProgramCounter::Jump(address)
+        Ok(ProgramCounter::Jump(address))
     }
 
     /// Sets VX to the result of a bitwise and operation on a random number (Typically: 0 to 255) and NN.
-    fn execute_op_cxnn(&mut self, opcode: &OpCode) -> ProgramCounter {
-        let random_number: u8 = rand::thread_rng().gen::<u8>().into();
+    fn execute_op_cxnn(&mut self, opcode: &OpCode) -> Result<ProgramCounter, CpuError> {
+        let random_number: u8 = self.rng.gen::<u8>();
         self.registers[opcode.lr as usize] = random_number & opcode.get_nn();
-        ProgramCounter::Next
-    }
-
-    /// Draws a sprite at coordinate (VX, VY) that has a width of 8 pixels and a height of N+1 pixels.
-    fn execute_op_dxyn(&mut self, opcode: &OpCode) -> ProgramCounter {
-        self.registers[15] = 0;
-        for byte in 0..opcode.rr {
-            let y = (self.registers[opcode.rl as usize] + byte) % 32;
-            for bit in 0..8 {
-                let x = (self.registers[opcode.lr as usize] + bit) % 64;
-                let color = (self.ram[(self.index + byte as u16) as usize] >> (7 - bit)) & 0x01;
-                self.registers[15] |= color & self.vram[y as usize][x as usize];
-                self.vram[y as usize][x as usize] ^= color;          
+        Ok(ProgramCounter::Next)
+    }
+
+    /// Draws a sprite at coordinate (VX, VY) that has a width of 8 pixels and a height of N pixels.
+    /// The starting coordinate wraps around the screen, the rest of the sprite is clipped unless the sprite wrap quirk is set.
+    fn execute_op_dxyn(&mut self, opcode: &OpCode) -> Result<ProgramCounter, CpuError> {
+        let start_x = self.registers[opcode.lr as usize] as usize % WIDTH;
+        let start_y = self.registers[opcode.rl as usize] as usize % HEIGHT;
+        let mut collided_rows = 0;
+        let mut clipped_rows = 0;
+        let drawn_rows = match self.quirks.sprite_wrap {
+            true => opcode.rr as usize,
+            false => (opcode.rr as usize).min(HEIGHT - start_y),
+        };
+        let sprite = self.index_range(drawn_rows)?;
+        self.profile_read(sprite.clone());
+
+        for byte in 0..opcode.rr as usize {
+            let y = start_y + byte;
+            if y >= HEIGHT && !self.quirks.sprite_wrap {
+                clipped_rows = opcode.rr - byte as u8;
+                break;
+            }
+
+            // Moves the sprite byte to the left edge of the row and then to its column, dropping or rotating in the
+            // pixels that cross the right edge.
+            let sprite_row = (self.ram[sprite.start + byte] as u64) << (WIDTH - 8);
+            let bits = if self.quirks.sprite_wrap {
+                sprite_row.rotate_right(start_x as u32)
+            } else {
+                sprite_row >> start_x
+            };
+            if self.vram.xor_row(y % HEIGHT, bits) {
+                collided_rows += 1;
             }
         }
-        self.vram_changed = true;
-        ProgramCounter::Next
+
+        self.registers[15] = match self.quirks.collision {
+            Collision::Flag => (collided_rows > 0) as u8,
+            Collision::RowCount => collided_rows + clipped_rows,
+        };
+        Ok(ProgramCounter::Next)
     }
 
     /// Skips the next instruction if the key stored in VX is pressed. (Usually the next instruction is a jump to skip a code block)
-    fn execute_op_ex9e(&mut self, opcode: &OpCode) -> ProgramCounter {
-        if self.keypad[self.registers[opcode.lr as usize] as usize] {
-            return ProgramCounter::Skip;
+    fn execute_op_ex9e(&mut self, opcode: &OpCode) -> Result<ProgramCounter, CpuError> {
+        if self.keypad[(self.registers[opcode.lr as usize] & 0xF) as usize] {
+            return Ok(ProgramCounter::Skip);
         }
-        ProgramCounter::Next
+        Ok(ProgramCounter::Next)
     }
     
     /// Skips the next instruction if the key stored in VX isn't pressed. (Usually the next instruction is a jump to skip a code block)
-    fn execute_op_exa1(&mut self, opcode: &OpCode) -> ProgramCounter {
-        if !self.keypad[self.registers[opcode.lr as usize] as usize] {
-            return ProgramCounter::Skip;
+    fn execute_op_exa1(&mut self, opcode: &OpCode) -> Result<ProgramCounter, CpuError> {
+        if !self.keypad[(self.registers[opcode.lr as usize] & 0xF) as usize] {
+            return Ok(ProgramCounter::Skip);
         }
-        ProgramCounter::Next
+        Ok(ProgramCounter::Next)
+    }
+
+    /// Loads the 16 bytes starting at I into the XO-CHIP audio pattern buffer, played as 128 1-bit samples.
+    fn execute_op_f002(&mut self, _opcode: &OpCode) -> Result<ProgramCounter, CpuError> {
+        let mut pattern = [0u8; 16];
+        let range = self.index_range(16)?;
+        pattern.copy_from_slice(&self.ram[range.clone()]);
+        self.profile_read(range);
+        self.audio_pattern = Some(pattern);
+        Ok(ProgramCounter::Next)
     }
 
     /// Sets VX to the value of the delay timer.
-    fn execute_op_fx07(&mut self, opcode: &OpCode) -> ProgramCounter {
+    fn execute_op_fx07(&mut self, opcode: &OpCode) -> Result<ProgramCounter, CpuError> {
         self.registers[opcode.lr as usize] = self.delay_timer;
-        ProgramCounter::Next
+        Ok(ProgramCounter::Next)
     }
 
     /// A key press is awaited, and then stored in VX. (Blocking Operation. All instruction halted until next key event)
-    fn execute_op_fx0a(&mut self, opcode: &OpCode) -> ProgramCounter {
+    fn execute_op_fx0a(&mut self, opcode: &OpCode) -> Result<ProgramCounter, CpuError> {
         self.keypad_waiting = true;
         self.keypad_register = opcode.lr.into();
-        ProgramCounter::Next
+        Ok(ProgramCounter::Next)
     }
 
     /// Sets the delay timer to VX.
-    fn execute_op_fx15(&mut self, opcode: &OpCode) -> ProgramCounter {
+    fn execute_op_fx15(&mut self, opcode: &OpCode) -> Result<ProgramCounter, CpuError> {
         self.delay_timer = self.registers[opcode.lr as usize];
-        ProgramCounter::Next
+        Ok(ProgramCounter::Next)
     }
 
     /// Sets the sound timer to VX.
-    fn execute_op_fx18(&mut self, opcode: &OpCode) -> ProgramCounter {
+    fn execute_op_fx18(&mut self, opcode: &OpCode) -> Result<ProgramCounter, CpuError> {
         self.sound_timer = self.registers[opcode.lr as usize];
-        ProgramCounter::Next
+        Ok(ProgramCounter::Next)
     }
 
     /// Adds VX to I. VF is not affected.
-    fn execute_op_fx1e(&mut self, opcode: &OpCode) -> ProgramCounter {
-        self.index += self.registers[opcode.lr as usize] as u16;
-        ProgramCounter::Next
+    fn execute_op_fx1e(&mut self, opcode: &OpCode) -> Result<ProgramCounter, CpuError> {
+        self.index = self.index.wrapping_add(self.registers[opcode.lr as usize] as u16);
+        Ok(ProgramCounter::Next)
     }
 
     /// Sets I to the location of the sprite for the character in VX. Characters 0-F (in hexadecimal) are represented by a 4x5 font.
-    fn execute_op_fx29(&mut self, opcode: &OpCode) -> ProgramCounter {
-        self.index = ((self.registers[opcode.lr as usize]) * 5) as u16;
-        ProgramCounter::Next
+    fn execute_op_fx29(&mut self, opcode: &OpCode) -> Result<ProgramCounter, CpuError> {
+        self.index = (self.registers[opcode.lr as usize] & 0xF) as u16 * 5;
+        Ok(ProgramCounter::Next)
     }
 
     /// Stores the binary-coded decimal representation of VX, with the most significant of three digits at the address in I, the middle digit at I plus 1, and the least significant digit at I plus 2.
-    fn execute_op_fx33(&mut self, opcode: &OpCode) -> ProgramCounter {
-        self.ram[self.index as usize] = (self.registers[opcode.lr as usize] / 100) as u8;
-        self.ram[self.index as usize + 1] = ((self.registers[opcode.lr as usize] % 100) / 10) as u8;
-        self.ram[self.index as usize + 2] = (self.registers[opcode.lr as usize] % 10) as u8;
-        ProgramCounter::Next
+    fn execute_op_fx33(&mut self, opcode: &OpCode) -> Result<ProgramCounter, CpuError> {
+        let digits = self.index_range(3)?;
+        let value = self.registers[opcode.lr as usize];
+        self.ram[digits.clone()].copy_from_slice(&[value / 100, (value % 100) / 10, value % 10]);
+        self.profile_write(digits.clone());
+        self.invalidate(digits);
+        Ok(ProgramCounter::Next)
+    }
+
+    /// Sets the XO-CHIP pitch register to VX. The audio pattern plays at 4000*2^((VX-64)/48) Hz.
+    fn execute_op_fx3a(&mut self, opcode: &OpCode) -> Result<ProgramCounter, CpuError> {
+        self.pitch = self.registers[opcode.lr as usize];
+        Ok(ProgramCounter::Next)
     }
 
     /// Stores V0 to VX (including VX) in memory starting at address I.
-    fn execute_op_fx55(&mut self, opcode: &OpCode) -> ProgramCounter {
-        for i in 0..opcode.lr as usize + 1 {
-            self.ram[self.index as usize + i as usize] = self.registers[i as usize] as u8;
-        }
-        ProgramCounter::Next
+    fn execute_op_fx55(&mut self, opcode: &OpCode) -> Result<ProgramCounter, CpuError> {
+        let count = opcode.lr as usize + 1;
+        let range = self.index_range(count)?;
+        self.ram[range.clone()].copy_from_slice(&self.registers[..count]);
+        self.profile_write(range.clone());
+        self.invalidate(range);
+        Ok(ProgramCounter::Next)
     }
 
     /// Fills V0 to VX (including VX) with values from memory starting at address I.
-    fn execute_op_fx65(&mut self, opcode: &OpCode) -> ProgramCounter {
-        for i in 0..opcode.lr as usize + 1 {
-            self.registers[i as usize] = self.ram[self.index as usize + i as usize].into();
-        }
-        ProgramCounter::Next
+    fn execute_op_fx65(&mut self, opcode: &OpCode) -> Result<ProgramCounter, CpuError> {
+        let count = opcode.lr as usize + 1;
+        let range = self.index_range(count)?;
+        self.registers[..count].copy_from_slice(&self.ram[range.clone()]);
+        self.profile_read(range);
+        Ok(ProgramCounter::Next)
     }
 }
 
@@ -7,19 +7,19 @@ fn test_load_rom() {
     data[0] = 11;
     data[1] = 22;
     data[2] = 33;
-    cpu.load(&data);
+    cpu.load(&data).unwrap();
     assert_eq!(cpu.ram[0x200], 11);
     assert_eq!(cpu.ram[0x201], 22);
     assert_eq!(cpu.ram[0x202], 33);
 }
 
 #[test]
-fn test_get_opcode() {
+fn test_fetch() {
     let mut cpu = CPU::new();
     cpu.ram[0x200] = 0xCA;
     cpu.ram[0x201] = 0xFE;
 
-    let opcode = cpu.get_opcode();
+    let opcode = cpu.fetch().unwrap();
     let expected_opcode = OpCode {
         ll: 0xC,
         lr: 0xA,
@@ -32,6 +32,115 @@ fn test_get_opcode() {
     assert_eq!(opcode.rr, expected_opcode.rr);
 }
 
+#[test]
+fn test_decode_00xx() {
+    assert_eq!(OpCode::new(0x00, 0xE0).instruction(), Instruction::Op00E0);
+    assert_eq!(OpCode::new(0x00, 0x00).instruction(), Instruction::Op00E0);
+    assert_eq!(OpCode::new(0x00, 0xEE).instruction(), Instruction::Op00EE);
+    assert_eq!(OpCode::new(0x00, 0xFF).instruction(), Instruction::Op00EE);
+    assert_eq!(OpCode::new(0x01, 0x23).instruction(), Instruction::Op0NNN);
+}
+
+#[test]
+fn test_load_rejects_rom_too_large() {
+    let mut cpu = CPU::new();
+
+    assert_eq!(cpu.load(&[0; 4000]), Err(CpuError::RomTooLarge { size: 4000, max_size: 3584 }));
+}
+
+#[test]
+fn test_errors_leave_pc_on_instruction() {
+    let run = |program: &[u8]| {
+        let mut cpu = CPU::new();
+        cpu.load(program).unwrap();
+        let error = cpu.run_frame([false; 16], Timing::Instructions(100)).err();
+        (error, cpu.pc())
+    };
+
+    assert_eq!(run(&[0x22, 0x00]), (Some(CpuError::StackOverflow { pc: 0x200 }), 0x200));
+    assert_eq!(run(&[0x00, 0xEE]), (Some(CpuError::StackUnderflow { pc: 0x200 }), 0x200));
+    assert_eq!(run(&[0xAF, 0xFE, 0xF2, 0x55]), (Some(CpuError::MemoryOutOfBounds { pc: 0x202, address: 0xFFE }), 0x202));
+    assert_eq!(run(&[0x1F, 0xFF]), (Some(CpuError::PcOutOfBounds { pc: 0xFFF }), 0xFFF));
+}
+
+#[test]
+fn test_random_roms_only_return_errors() {
+    let mut rng = StdRng::seed_from_u64(0xC8);
+    for _ in 0..100 {
+        let rom: Vec<u8> = (0..256).map(|_| rng.gen()).collect();
+        for (_, quirks) in Quirks::profiles().iter() {
+            let mut cpu = CPU::with_quirks(*quirks);
+            cpu.load(&rom).unwrap();
+            for cycle in 0..1000 {
+                if cpu.cycle([cycle % 3 == 0; 16]).is_err() {
+                    break;
+                }
+            }
+        }
+    }
+}
+
+#[test]
+fn test_writes_to_code_replace_decoded_instructions() {
+    let mut cpu = CPU::new();
+    // Calls a subroutine setting V3 to 5, overwrites it with FX55 to set V3 to 7 and calls it again.
+    cpu.load(&[
+        0x22, 0x0C, 0xA2, 0x0C, 0x60, 0x63, 0x61, 0x07, 0xF1, 0x55, 0x22, 0x0C, 0x63, 0x05, 0x00, 0xEE,
+    ]).unwrap();
+
+    for _ in 0..3 {
+        cpu.cycle([false; 16]).unwrap();
+    }
+    assert_eq!(cpu.registers[3], 5);
+    for _ in 0..6 {
+        cpu.cycle([false; 16]).unwrap();
+    }
+    assert_eq!(cpu.registers[3], 7);
+
+    cpu.memory_mut()[0x20D] = 0x09;
+    cpu.pc = 0x20C;
+    cpu.cycle([false; 16]).unwrap();
+    assert_eq!(cpu.registers[3], 9);
+}
+
+#[test]
+fn test_run_frame_ticks_timers_once() {
+    let mut cpu = CPU::new();
+    cpu.delay_timer = 10;
+    cpu.sound_timer = 1;
+
+    let output = cpu.run_frame([false; 16], Timing::Instructions(8)).unwrap();
+
+    assert!(!output.beep);
+    assert_eq!(cpu.delay_timer, 9);
+    assert_eq!(cpu.sound_timer, 0);
+    assert_eq!(cpu.pc, 0x200 + 8 * OPCODE_SIZE);
+}
+
+#[test]
+fn test_run_frame_vip_timing_waits_for_vblank_after_drawing() {
+    let mut cpu = CPU::new();
+    // 6000 6000 D001 6001: only the instructions up to the sprite draw run in the first frame.
+    cpu.load(&[0x60, 0x00, 0x60, 0x00, 0xD0, 0x01, 0x60, 0x01]).unwrap();
+
+    cpu.run_frame([false; 16], Timing::Vip).unwrap();
+
+    assert_eq!(cpu.pc, 0x206);
+    assert_eq!(cpu.registers[0], 0);
+}
+
+#[test]
+fn test_run_frame_vip_timing_spends_frame_cycles() {
+    let mut cpu = CPU::new();
+    // 7001 1200: an increment and jump loop costing 22 cycles per iteration.
+    cpu.load(&[0x70, 0x01, 0x12, 0x00]).unwrap();
+
+    cpu.run_frame([false; 16], Timing::Vip).unwrap();
+
+    let iterations = (VIP_CYCLES_PER_FRAME + 21) / 22;
+    assert_eq!(cpu.registers[0], iterations as u8);
+}
+
 #[test]
 fn test_execute_op_0nnn() {
     let mut cpu = CPU::new();
@@ -41,7 +150,7 @@ fn test_execute_op_0nnn() {
         rl: 0x2,
         rr: 0x1,
     };
-    let expected_program_counter = ProgramCounter::Jump(0x0321);
+    let expected_program_counter = Ok(ProgramCounter::Jump(0x0321));
 
     let program_counter = cpu.execute_op_0nnn(&opcode);
     
@@ -51,23 +160,23 @@ fn test_execute_op_0nnn() {
 #[test]
 fn test_execute_op_00e0() {
     let mut cpu = CPU::new();
-    cpu.vram[0][0] = 0x1u8;
-    cpu.vram[1][1] = 0x13u8;
-    cpu.vram[10][10] = 0xFFu8;
+    cpu.vram.set_pixel(0, 0, true);
+    cpu.vram.set_pixel(1, 1, true);
+    cpu.vram.set_pixel(10, 10, true);
     let opcode = OpCode {
         ll: 0x0,
         lr: 0x0,
         rl: 0xE,
         rr: 0x0,
     };
-    let expected_program_counter = ProgramCounter::Next;
+    let expected_program_counter = Ok(ProgramCounter::Next);
 
     let program_counter = cpu.execute_op_00e0(&opcode);
 
     assert_eq!(program_counter, expected_program_counter);
-    assert_eq!(cpu.vram[0][0], 0x0u8);
-    assert_eq!(cpu.vram[1][1], 0x0u8);
-    assert_eq!(cpu.vram[10][10], 0x0u8);
+    assert!(!cpu.vram.pixel(0, 0));
+    assert!(!cpu.vram.pixel(1, 1));
+    assert!(!cpu.vram.pixel(10, 10));
 }
 
 #[test]
@@ -81,7 +190,7 @@ fn test_execute_op_00ee() {
     };
     cpu.sp = 1;
     cpu.stack[0] = 0x0321;
-    let expected_program_counter = ProgramCounter::Jump(0x0321);
+    let expected_program_counter = Ok(ProgramCounter::Jump(0x0321));
 
     let program_counter = cpu.execute_op_00ee(&opcode);
     
@@ -97,7 +206,7 @@ fn test_execute_op_1nnn() {
         rl: 0x5,
         rr: 0x6,
     };
-    let expected_program_counter = ProgramCounter::Jump(0x0456);
+    let expected_program_counter = Ok(ProgramCounter::Jump(0x0456));
 
     let program_counter = cpu.execute_op_1nnn(&opcode);
     
@@ -113,7 +222,7 @@ fn test_execute_op_2nnn() {
         rl: 0x2,
         rr: 0x1,
     };
-    let expected_program_counter = ProgramCounter::Jump(0x0321);
+    let expected_program_counter = Ok(ProgramCounter::Jump(0x0321));
 
     let program_counter = cpu.execute_op_2nnn(&opcode);
     
@@ -130,7 +239,7 @@ fn test_execute_op_3xnn_skip() {
         rr: 0x4,
     };
     cpu.registers[1] = 0x44;
-    let expected_program_counter = ProgramCounter::Skip;
+    let expected_program_counter = Ok(ProgramCounter::Skip);
 
     let program_counter = cpu.execute_op_3xnn(&opcode);
     
@@ -147,7 +256,7 @@ fn test_execute_op_3xnn_next() {
         rr: 0x4,
     };
     cpu.registers[1] = 0x40;
-    let expected_program_counter = ProgramCounter::Next;
+    let expected_program_counter = Ok(ProgramCounter::Next);
 
     let program_counter = cpu.execute_op_3xnn(&opcode);
     
@@ -164,7 +273,7 @@ fn test_execute_op_4xnn_skip() {
         rr: 0x4,
     };
     cpu.registers[1] = 0x40;
-    let expected_program_counter = ProgramCounter::Skip;
+    let expected_program_counter = Ok(ProgramCounter::Skip);
 
     let program_counter = cpu.execute_op_4xnn(&opcode);
     
@@ -181,7 +290,7 @@ fn test_execute_op_4xnn_next() {
         rr: 0x4,
     };
     cpu.registers[1] = 0x44;
-    let expected_program_counter = ProgramCounter::Next;
+    let expected_program_counter = Ok(ProgramCounter::Next);
 
     let program_counter = cpu.execute_op_4xnn(&opcode);
     
@@ -199,7 +308,7 @@ fn test_execute_op_5xy0_skip() {
     };
     cpu.registers[1] = 0x44;
     cpu.registers[2] = 0x44;
-    let expected_program_counter = ProgramCounter::Skip;
+    let expected_program_counter = Ok(ProgramCounter::Skip);
 
     let program_counter = cpu.execute_op_5xy0(&opcode);
     
@@ -217,7 +326,7 @@ fn test_execute_op_5xy0_next() {
     };
     cpu.registers[1] = 0x44;
     cpu.registers[2] = 0x40;
-    let expected_program_counter = ProgramCounter::Next;
+    let expected_program_counter = Ok(ProgramCounter::Next);
 
     let program_counter = cpu.execute_op_5xy0(&opcode);
     
@@ -233,7 +342,7 @@ fn test_execute_op_6xnn() {
         rl: 0x4,
         rr: 0x4,
     };
-    let expected_program_counter = ProgramCounter::Next;
+    let expected_program_counter = Ok(ProgramCounter::Next);
 
     let program_counter = cpu.execute_op_6xnn(&opcode);
     
@@ -251,7 +360,7 @@ fn test_execute_op_7xnn() {
         rr: 0x2,
     };
     cpu.registers[1] = 0x05;
-    let expected_program_counter = ProgramCounter::Next;
+    let expected_program_counter = Ok(ProgramCounter::Next);
 
     let program_counter = cpu.execute_op_7xnn(&opcode);
     
@@ -270,7 +379,7 @@ fn test_execute_op_8xy0() {
     };
     cpu.registers[1] = 0x0;
     cpu.registers[2] = 0x44;
-    let expected_program_counter = ProgramCounter::Next;
+    let expected_program_counter = Ok(ProgramCounter::Next);
 
     let program_counter = cpu.execute_op_8xy0(&opcode);
     
@@ -290,7 +399,7 @@ fn test_execute_op_8xy1() {
     };
     cpu.registers[1] = 0b11010100;
     cpu.registers[2] = 0b01100110;
-    let expected_program_counter = ProgramCounter::Next;
+    let expected_program_counter = Ok(ProgramCounter::Next);
 
     let program_counter = cpu.execute_op_8xy1(&opcode);
     
@@ -310,7 +419,7 @@ fn test_execute_op_8xy2() {
     };
     cpu.registers[1] = 0b11010100;
     cpu.registers[2] = 0b01100110;
-    let expected_program_counter = ProgramCounter::Next;
+    let expected_program_counter = Ok(ProgramCounter::Next);
 
     let program_counter = cpu.execute_op_8xy2(&opcode);
     
@@ -330,7 +439,7 @@ fn test_execute_op_8xy3() {
     };
     cpu.registers[1] = 0b11010100;
     cpu.registers[2] = 0b01100110;
-    let expected_program_counter = ProgramCounter::Next;
+    let expected_program_counter = Ok(ProgramCounter::Next);
 
     let program_counter = cpu.execute_op_8xy3(&opcode);
     
@@ -350,7 +459,7 @@ fn test_execute_op_8xy4_no_carry() {
     };
     cpu.registers[1] = 120;
     cpu.registers[2] = 103;
-    let expected_program_counter = ProgramCounter::Next;
+    let expected_program_counter = Ok(ProgramCounter::Next);
 
     let program_counter = cpu.execute_op_8xy4(&opcode);
     
@@ -371,7 +480,7 @@ fn test_execute_op_8xy4_carry() {
     };
     cpu.registers[1] = 200;
     cpu.registers[2] = 200;
-    let expected_program_counter = ProgramCounter::Next;
+    let expected_program_counter = Ok(ProgramCounter::Next);
 
     let program_counter = cpu.execute_op_8xy4(&opcode);
     
@@ -392,7 +501,7 @@ fn test_execute_op_8xy5_no_borrow() {
     };
     cpu.registers[1] = 50;
     cpu.registers[2] = 20;
-    let expected_program_counter = ProgramCounter::Next;
+    let expected_program_counter = Ok(ProgramCounter::Next);
 
     let program_counter = cpu.execute_op_8xy5(&opcode);
     
@@ -413,7 +522,7 @@ fn test_execute_op_8xy5_borrow() {
     };
     cpu.registers[1] = 100;
     cpu.registers[2] = 200;
-    let expected_program_counter = ProgramCounter::Next;
+    let expected_program_counter = Ok(ProgramCounter::Next);
 
     let program_counter = cpu.execute_op_8xy5(&opcode);
     
@@ -423,6 +532,47 @@ fn test_execute_op_8xy5_borrow() {
     assert_eq!(cpu.registers[15], 0);
 }
 
+#[test]
+fn test_execute_op_8xy5_equal() {
+    let mut cpu = CPU::new();
+    let opcode = OpCode {
+        ll: 0x8,
+        lr: 0x1,
+        rl: 0x2,
+        rr: 0x5,
+    };
+    cpu.registers[1] = 42;
+    cpu.registers[2] = 42;
+    let expected_program_counter = Ok(ProgramCounter::Next);
+
+    let program_counter = cpu.execute_op_8xy5(&opcode);
+    
+    assert_eq!(program_counter, expected_program_counter);
+    assert_eq!(cpu.registers[1], 0);
+    assert_eq!(cpu.registers[2], 42);
+    assert_eq!(cpu.registers[15], 1);
+}
+
+#[test]
+fn test_execute_op_8xy5_vf_as_x() {
+    let mut cpu = CPU::new();
+    let opcode = OpCode {
+        ll: 0x8,
+        lr: 0xF,
+        rl: 0x2,
+        rr: 0x5,
+    };
+    cpu.registers[15] = 50;
+    cpu.registers[2] = 20;
+    let expected_program_counter = Ok(ProgramCounter::Next);
+
+    let program_counter = cpu.execute_op_8xy5(&opcode);
+    
+    assert_eq!(program_counter, expected_program_counter);
+    assert_eq!(cpu.registers[2], 20);
+    assert_eq!(cpu.registers[15], 1);
+}
+
 #[test]
 fn test_execute_op_8xy6() {
     let mut cpu = CPU::new();
@@ -433,7 +583,7 @@ fn test_execute_op_8xy6() {
         rr: 0x6,
     };
     cpu.registers[1] = 0b01011101;
-    let expected_program_counter = ProgramCounter::Next;
+    let expected_program_counter = Ok(ProgramCounter::Next);
 
     let program_counter = cpu.execute_op_8xy6(&opcode);
     
@@ -453,7 +603,7 @@ fn test_execute_op_8xy7_no_borrow() {
     };
     cpu.registers[1] = 101;
     cpu.registers[2] = 155;
-    let expected_program_counter = ProgramCounter::Next;
+    let expected_program_counter = Ok(ProgramCounter::Next);
 
     let program_counter = cpu.execute_op_8xy7(&opcode);
     
@@ -474,7 +624,7 @@ fn test_execute_op_8xy7_borrow() {
     };
     cpu.registers[1] = 155;
     cpu.registers[2] = 101;
-    let expected_program_counter = ProgramCounter::Next;
+    let expected_program_counter = Ok(ProgramCounter::Next);
 
     let program_counter = cpu.execute_op_8xy7(&opcode);
     
@@ -484,6 +634,47 @@ fn test_execute_op_8xy7_borrow() {
     assert_eq!(cpu.registers[15], 0);
 }
 
+#[test]
+fn test_execute_op_8xy7_equal() {
+    let mut cpu = CPU::new();
+    let opcode = OpCode {
+        ll: 0x8,
+        lr: 0x1,
+        rl: 0x2,
+        rr: 0x7,
+    };
+    cpu.registers[1] = 42;
+    cpu.registers[2] = 42;
+    let expected_program_counter = Ok(ProgramCounter::Next);
+
+    let program_counter = cpu.execute_op_8xy7(&opcode);
+    
+    assert_eq!(program_counter, expected_program_counter);
+    assert_eq!(cpu.registers[1], 0);
+    assert_eq!(cpu.registers[2], 42);
+    assert_eq!(cpu.registers[15], 1);
+}
+
+#[test]
+fn test_execute_op_8xy7_vf_as_x() {
+    let mut cpu = CPU::new();
+    let opcode = OpCode {
+        ll: 0x8,
+        lr: 0xF,
+        rl: 0x2,
+        rr: 0x7,
+    };
+    cpu.registers[15] = 20;
+    cpu.registers[2] = 50;
+    let expected_program_counter = Ok(ProgramCounter::Next);
+
+    let program_counter = cpu.execute_op_8xy7(&opcode);
+    
+    assert_eq!(program_counter, expected_program_counter);
+    assert_eq!(cpu.registers[2], 50);
+    assert_eq!(cpu.registers[15], 1);
+}
+
 #[test]
 fn test_execute_op_8xye() {
     let mut cpu = CPU::new();
@@ -494,7 +685,7 @@ fn test_execute_op_8xye() {
         rr: 0xe,
     };
     cpu.registers[1] = 0b11011101;
-    let expected_program_counter = ProgramCounter::Next;
+    let expected_program_counter = Ok(ProgramCounter::Next);
 
     let program_counter = cpu.execute_op_8xye(&opcode);
     
@@ -514,7 +705,7 @@ fn test_execute_op_9xy0_next() {
     };
     cpu.registers[1] = 23;
     cpu.registers[2] = 23;
-    let expected_program_counter = ProgramCounter::Next;
+    let expected_program_counter = Ok(ProgramCounter::Next);
 
     let program_counter = cpu.execute_op_9xy0(&opcode);
     
@@ -532,7 +723,7 @@ fn test_execute_op_9xy0_skip() {
     };
     cpu.registers[1] = 25;
     cpu.registers[2] = 23;
-    let expected_program_counter = ProgramCounter::Skip;
+    let expected_program_counter = Ok(ProgramCounter::Skip);
 
     let program_counter = cpu.execute_op_9xy0(&opcode);
     
@@ -548,7 +739,7 @@ fn test_execute_op_annn() {
         rl: 0x2,
         rr: 0x3,
     };
-    let expected_program_counter = ProgramCounter::Next;
+    let expected_program_counter = Ok(ProgramCounter::Next);
 
     let program_counter = cpu.execute_op_annn(&opcode);
     
@@ -566,7 +757,7 @@ fn test_execute_op_bnnn() {
         rr: 0x3,
     };
     cpu.registers[0] = 0x12;
-    let expected_program_counter = ProgramCounter::Jump(0x135);
+    let expected_program_counter = Ok(ProgramCounter::Jump(0x135));
 
     let program_counter = cpu.execute_op_bnnn(&opcode);
     
@@ -582,13 +773,27 @@ fn test_execute_op_cxnn() {
         rl: 0x2,
         rr: 0x3,
     };
-    let expected_program_counter = ProgramCounter::Next;
+    let expected_program_counter = Ok(ProgramCounter::Next);
 
     let program_counter = cpu.execute_op_cxnn(&opcode);
     
     assert_eq!(program_counter, expected_program_counter);
 }
 
+#[test]
+fn test_seeded_rng_repeats_numbers() {
+    let random_numbers = |seed| {
+        let mut cpu = CPU::new();
+        cpu.seed_rng(seed);
+        cpu.load(&[0xC0, 0xFF, 0xC1, 0xFF, 0xC2, 0xFF]).unwrap();
+        cpu.run_frame([false; 16], Timing::Instructions(3)).unwrap();
+        [cpu.registers[0], cpu.registers[1], cpu.registers[2]]
+    };
+
+    assert_eq!(random_numbers(7), random_numbers(7));
+    assert_ne!(random_numbers(7), random_numbers(8));
+}
+
 #[test]
 fn test_execute_op_dxyn() {
     let mut cpu = CPU::new();
@@ -602,15 +807,85 @@ fn test_execute_op_dxyn() {
     cpu.registers[2] = 1;
     cpu.index = 0x200;
     cpu.ram[0x200] = 0xFF;
-    let expected_program_counter = ProgramCounter::Next;
+    let expected_program_counter = Ok(ProgramCounter::Next);
 
     let program_counter = cpu.execute_op_dxyn(&opcode);
     
     assert_eq!(program_counter, expected_program_counter);
-    assert_eq!(cpu.vram[0][0], 0);
-    assert_eq!(cpu.vram[1][1], 1);
-    assert_eq!(cpu.vram[1][8], 1);
-    assert_eq!(cpu.vram[1][9], 0);
+    assert!(!cpu.vram.pixel(0, 0));
+    assert!(cpu.vram.pixel(1, 1));
+    assert!(cpu.vram.pixel(8, 1));
+    assert!(!cpu.vram.pixel(9, 1));
+}
+
+#[test]
+fn test_execute_op_dxyn_clips_at_edges() {
+    let mut cpu = CPU::with_quirks(Quirks::chip8());
+    let opcode = OpCode {
+        ll: 0xd,
+        lr: 0x1,
+        rl: 0x2,
+        rr: 0x2,
+    };
+    cpu.registers[1] = 60 + 64;
+    cpu.registers[2] = 31;
+    cpu.index = 0x300;
+    cpu.ram[0x300] = 0xFF;
+    cpu.ram[0x301] = 0xFF;
+
+    cpu.execute_op_dxyn(&opcode).unwrap();
+
+    assert!(cpu.vram.pixel(60, 31));
+    assert!(cpu.vram.pixel(63, 31));
+    assert!(!cpu.vram.pixel(0, 31));
+    assert!(!cpu.vram.pixel(60, 0));
+    assert_eq!(cpu.registers[15], 0);
+}
+
+#[test]
+fn test_execute_op_dxyn_wraps_with_quirk() {
+    let mut cpu = CPU::with_quirks(Quirks::xochip());
+    let opcode = OpCode {
+        ll: 0xd,
+        lr: 0x1,
+        rl: 0x2,
+        rr: 0x2,
+    };
+    cpu.registers[1] = 60;
+    cpu.registers[2] = 31;
+    cpu.index = 0x300;
+    cpu.ram[0x300] = 0xFF;
+    cpu.ram[0x301] = 0xFF;
+    cpu.vram.set_pixel(0, 0, true);
+
+    cpu.execute_op_dxyn(&opcode).unwrap();
+
+    assert!(cpu.vram.pixel(63, 31));
+    assert!(cpu.vram.pixel(3, 31));
+    assert!(cpu.vram.pixel(60, 0));
+    assert!(!cpu.vram.pixel(0, 0));
+    assert_eq!(cpu.registers[15], 1);
+}
+
+#[test]
+fn test_execute_op_dxyn_counts_collided_rows() {
+    let mut cpu = CPU::with_quirks(Quirks::schip());
+    let opcode = OpCode {
+        ll: 0xd,
+        lr: 0x1,
+        rl: 0x2,
+        rr: 0x4,
+    };
+    cpu.registers[1] = 0;
+    cpu.registers[2] = 29;
+    cpu.index = 0x300;
+    cpu.ram[0x300..0x304].copy_from_slice(&[0x80, 0x80, 0x80, 0x80]);
+    cpu.vram.set_pixel(0, 29, true);
+
+    cpu.execute_op_dxyn(&opcode).unwrap();
+
+    // One row collided and one row was clipped at the bottom edge.
+    assert_eq!(cpu.registers[15], 2);
 }
 
 #[test]
@@ -624,7 +899,7 @@ fn test_execute_op_ex9e_next() {
     };
     cpu.registers[1] = 5;
     cpu.keypad[5] = false;
-    let expected_program_counter = ProgramCounter::Next;
+    let expected_program_counter = Ok(ProgramCounter::Next);
 
     let program_counter = cpu.execute_op_ex9e(&opcode);
     
@@ -642,7 +917,7 @@ fn test_execute_op_ex9e_skip() {
     };
     cpu.registers[1] = 5;
     cpu.keypad[5] = true;
-    let expected_program_counter = ProgramCounter::Skip;
+    let expected_program_counter = Ok(ProgramCounter::Skip);
 
     let program_counter = cpu.execute_op_ex9e(&opcode);
     
@@ -660,7 +935,7 @@ fn test_execute_op_exa1_next() {
     };
     cpu.registers[1] = 5;
     cpu.keypad[5] = true;
-    let expected_program_counter = ProgramCounter::Next;
+    let expected_program_counter = Ok(ProgramCounter::Next);
 
     let program_counter = cpu.execute_op_exa1(&opcode);
     
@@ -678,13 +953,37 @@ fn test_execute_op_exa1_skip() {
     };
     cpu.registers[1] = 5;
     cpu.keypad[5] = false;
-    let expected_program_counter = ProgramCounter::Skip;
+    let expected_program_counter = Ok(ProgramCounter::Skip);
 
     let program_counter = cpu.execute_op_exa1(&opcode);
     
     assert_eq!(program_counter, expected_program_counter);
 }
 
+#[test]
+fn test_execute_op_f002() {
+    let mut cpu = CPU::new();
+    let opcode = OpCode {
+        ll: 0xf,
+        lr: 0x0,
+        rl: 0x0,
+        rr: 0x2,
+    };
+    for i in 0..16usize {
+        cpu.ram[0x300 + i] = 0xA0 + i as u8;
+    }
+    cpu.index = 0x300;
+    let expected_program_counter = Ok(ProgramCounter::Next);
+
+    let program_counter = cpu.execute_op_f002(&opcode);
+
+    assert_eq!(program_counter, expected_program_counter);
+    assert_eq!(cpu.audio_pattern, Some([
+        0xA0, 0xA1, 0xA2, 0xA3, 0xA4, 0xA5, 0xA6, 0xA7,
+        0xA8, 0xA9, 0xAA, 0xAB, 0xAC, 0xAD, 0xAE, 0xAF,
+    ]));
+}
+
 #[test]
 fn test_execute_op_fx07() {
     let mut cpu = CPU::new();
@@ -695,7 +994,7 @@ fn test_execute_op_fx07() {
         rr: 0x7,
     };
     cpu.delay_timer = 7;
-    let expected_program_counter = ProgramCounter::Next;
+    let expected_program_counter = Ok(ProgramCounter::Next);
 
     let program_counter = cpu.execute_op_fx07(&opcode);
     
@@ -712,15 +1011,53 @@ fn test_execute_op_fx0a() {
         rl: 0x0,
         rr: 0xa,
     };
-    let expected_program_counter = ProgramCounter::Next;
+    let expected_program_counter = Ok(ProgramCounter::Next);
 
     let program_counter = cpu.execute_op_fx0a(&opcode);
     
     assert_eq!(program_counter, expected_program_counter);
-    assert_eq!(cpu.keypad_waiting, true);
+    assert!(cpu.keypad_waiting);
     assert_eq!(cpu.keypad_register, 7);
 }
 
+#[test]
+fn test_fx0a_waits_for_key_release() {
+    let mut cpu = CPU::with_quirks(Quirks::chip8());
+    cpu.delay_timer = 10;
+    cpu.keypad_waiting = true;
+    cpu.keypad_register = 3;
+    let mut keypad = [false; 16];
+    keypad[0xB] = true;
+
+    let output = cpu.run_frame(keypad, Timing::Instructions(1)).unwrap();
+
+    assert!(output.beep);
+    assert!(cpu.keypad_waiting);
+    assert_eq!(cpu.delay_timer, 9);
+
+    let output = cpu.run_frame([false; 16], Timing::Instructions(1)).unwrap();
+
+    assert!(!output.beep);
+    assert!(!cpu.keypad_waiting);
+    assert_eq!(cpu.registers[3], 0xB);
+}
+
+#[test]
+fn test_fx0a_without_key_release_quirk() {
+    let mut quirks = Quirks::chip8();
+    quirks.key_release = false;
+    let mut cpu = CPU::with_quirks(quirks);
+    cpu.keypad_waiting = true;
+    cpu.keypad_register = 3;
+    let mut keypad = [false; 16];
+    keypad[0xB] = true;
+
+    cpu.cycle(keypad).unwrap();
+
+    assert!(!cpu.keypad_waiting);
+    assert_eq!(cpu.registers[3], 0xB);
+}
+
 #[test]
 fn test_execute_op_fx15() {
     let mut cpu = CPU::new();
@@ -731,7 +1068,7 @@ fn test_execute_op_fx15() {
         rr: 0x5,
     };
     cpu.registers[1] = 5;
-    let expected_program_counter = ProgramCounter::Next;
+    let expected_program_counter = Ok(ProgramCounter::Next);
 
     let program_counter = cpu.execute_op_fx15(&opcode);
     
@@ -749,7 +1086,7 @@ fn test_execute_op_fx18() {
         rr: 0x8,
     };
     cpu.registers[1] = 5;
-    let expected_program_counter = ProgramCounter::Next;
+    let expected_program_counter = Ok(ProgramCounter::Next);
 
     let program_counter = cpu.execute_op_fx18(&opcode);
     
@@ -768,7 +1105,7 @@ fn test_execute_op_fx1e() {
     };
     cpu.index = 7;
     cpu.registers[1] = 5;
-    let expected_program_counter = ProgramCounter::Next;
+    let expected_program_counter = Ok(ProgramCounter::Next);
 
     let program_counter = cpu.execute_op_fx1e(&opcode);
     
@@ -786,7 +1123,7 @@ fn test_execute_op_fx29() {
         rr: 0x9,
     };
     cpu.registers[1] = 5;
-    let expected_program_counter = ProgramCounter::Next;
+    let expected_program_counter = Ok(ProgramCounter::Next);
 
     let program_counter = cpu.execute_op_fx29(&opcode);
     
@@ -805,7 +1142,7 @@ fn test_execute_op_fx33() {
     };
     cpu.registers[1] = 123;
     cpu.index = 0x250;
-    let expected_program_counter = ProgramCounter::Next;
+    let expected_program_counter = Ok(ProgramCounter::Next);
 
     let program_counter = cpu.execute_op_fx33(&opcode);
     
@@ -815,6 +1152,24 @@ fn test_execute_op_fx33() {
     assert_eq!(cpu.ram[0x252], 3);
 }
 
+#[test]
+fn test_execute_op_fx3a() {
+    let mut cpu = CPU::new();
+    let opcode = OpCode {
+        ll: 0xf,
+        lr: 0x2,
+        rl: 0x3,
+        rr: 0xa,
+    };
+    cpu.registers[2] = 112;
+    let expected_program_counter = Ok(ProgramCounter::Next);
+
+    let program_counter = cpu.execute_op_fx3a(&opcode);
+
+    assert_eq!(program_counter, expected_program_counter);
+    assert_eq!(cpu.pitch, 112);
+}
+
 #[test]
 fn test_execute_op_fx55() {
     let mut cpu = CPU::new();
@@ -824,16 +1179,16 @@ fn test_execute_op_fx55() {
         rl: 0x5,
         rr: 0x5,
     };
-    for i in 0..9 as usize {
+    for i in 0..9usize {
         cpu.registers[i] = i as u8;
     }
     cpu.index = 0x250;
-    let expected_program_counter = ProgramCounter::Next;
+    let expected_program_counter = Ok(ProgramCounter::Next);
 
     let program_counter = cpu.execute_op_fx55(&opcode);
     
     assert_eq!(program_counter, expected_program_counter);
-    for i in 0..9 as usize {
+    for i in 0..9usize {
         assert_eq!(cpu.ram[0x250 + i], cpu.registers[i]);
     }
 }
@@ -847,16 +1202,61 @@ fn test_execute_op_fx65() {
         rl: 0x6,
         rr: 0x5,
     };
-    for i in 0..9 as usize {
+    for i in 0..9usize {
         cpu.ram[0x250 + i] = i as u8;
     }
     cpu.index = 0x250;
-    let expected_program_counter = ProgramCounter::Next;
+    let expected_program_counter = Ok(ProgramCounter::Next);
 
     let program_counter = cpu.execute_op_fx65(&opcode);
     
     assert_eq!(program_counter, expected_program_counter);
-    for i in 0..9 as usize {
+    for i in 0..9usize {
         assert_eq!(cpu.registers[i], cpu.ram[0x250 + i]);
     }
-}
\ No newline at end of file
+}
+
+#[test]
+fn test_soft_reset_keeps_ram() {
+    let mut cpu = CPU::new();
+    cpu.load(&[0x60, 0x05, 0xA3, 0x00]).unwrap();
+    cpu.run_frame([false; 16], Timing::Instructions(2)).unwrap();
+    cpu.ram[0x300] = 0xAB;
+    cpu.vram.xor_row(0, 1);
+
+    cpu.reset(Reset::Soft);
+
+    assert_eq!(cpu.pc, 0x200);
+    assert_eq!(cpu.registers[0], 0);
+    assert_eq!(cpu.index, 0);
+    assert_eq!(cpu.vram.row(0), 0);
+    assert_eq!(cpu.ram[0x300], 0xAB);
+}
+
+#[test]
+fn test_soft_reset_reloads_rom_without_keep_ram_quirk() {
+    let mut cpu = CPU::with_quirks(Quirks::schip());
+    cpu.load(&[0x60, 0x05]).unwrap();
+    cpu.ram[0x200] = 0x12;
+    cpu.ram[0x300] = 0xAB;
+
+    cpu.reset(Reset::Soft);
+
+    assert_eq!(cpu.pc, 0x200);
+    assert_eq!(cpu.ram[0x200..0x202], [0x60, 0x05]);
+    assert_eq!(cpu.ram[0x300], 0);
+}
+
+#[test]
+fn test_hard_reset_reloads_rom() {
+    let mut cpu = CPU::new();
+    cpu.load(&[0x60, 0x05]).unwrap();
+    cpu.ram[0x200] = 0x12;
+    cpu.ram[0x300] = 0xAB;
+
+    cpu.reset(Reset::Hard);
+
+    assert_eq!(cpu.ram[0x200..0x202], [0x60, 0x05]);
+    assert_eq!(cpu.ram[0x300], 0);
+    assert_eq!(cpu.ram[..FONT_SET.len()], FONT_SET);
+}
@@ -0,0 +1,181 @@
+use std::collections::BTreeSet;
+
+use crate::cpu::{CpuError, Register, Timing, CPU};
+
+/// Registers in the order debuggers number them, with their names and sizes in bytes.
+pub const REGISTERS: [(&str, Register, usize); 21] = [
+    ("v0", Register::V(0x0), 1),
+    ("v1", Register::V(0x1), 1),
+    ("v2", Register::V(0x2), 1),
+    ("v3", Register::V(0x3), 1),
+    ("v4", Register::V(0x4), 1),
+    ("v5", Register::V(0x5), 1),
+    ("v6", Register::V(0x6), 1),
+    ("v7", Register::V(0x7), 1),
+    ("v8", Register::V(0x8), 1),
+    ("v9", Register::V(0x9), 1),
+    ("va", Register::V(0xA), 1),
+    ("vb", Register::V(0xB), 1),
+    ("vc", Register::V(0xC), 1),
+    ("vd", Register::V(0xD), 1),
+    ("ve", Register::V(0xE), 1),
+    ("vf", Register::V(0xF), 1),
+    ("i", Register::I, 2),
+    ("pc", Register::Pc, 2),
+    ("sp", Register::Sp, 1),
+    ("dt", Register::Delay, 1),
+    ("st", Register::Sound, 1),
+];
+
+/// Why the CPU stopped running.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Stop {
+    Breakpoint,
+    Step,
+    /// The debugger asked the running CPU to stop.
+    Pause,
+    /// The CPU could not execute the instruction at the program counter.
+    Fault(CpuError),
+}
+
+/// What happened in a call to `Debugger::run_frame`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Run {
+    /// Nothing ran because the CPU is halted.
+    Halted,
+    /// A whole frame ran, or the rest of the one stopped earlier.
+    Completed,
+    Stopped(Stop),
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum State {
+    Halted,
+    Running,
+    Stepping { executed: bool },
+    /// Runs until the program counter reaches `pc`, if given, with at most `depth` subroutines on the call stack.
+    Until { pc: Option<usize>, depth: usize },
+}
+
+/// Execution control shared by the debugger frontends: breakpoints, single steps and halting the CPU.
+pub struct Debugger {
+    breakpoints: BTreeSet<usize>,
+    state: State,
+    /// Address the CPU was resumed from, whose breakpoint must not stop it again straight away.
+    resumed_at: Option<usize>,
+}
+
+impl Debugger {
+    /// Creates a debugger that holds the CPU halted until it is resumed.
+    pub fn new() -> Self {
+        Debugger {
+            breakpoints: BTreeSet::new(),
+            state: State::Halted,
+            resumed_at: None,
+        }
+    }
+
+    pub fn is_halted(&self) -> bool {
+        self.state == State::Halted
+    }
+
+    pub fn add_breakpoint(&mut self, address: usize) {
+        self.breakpoints.insert(address);
+    }
+
+    pub fn remove_breakpoint(&mut self, address: usize) {
+        self.breakpoints.remove(&address);
+    }
+
+    pub fn clear_breakpoints(&mut self) {
+        self.breakpoints.clear();
+    }
+
+    pub fn resume(&mut self, cpu: &CPU) {
+        self.state = State::Running;
+        self.resumed_at = Some(cpu.pc());
+    }
+
+    /// Resumes the CPU for a single instruction.
+    pub fn step(&mut self) {
+        self.state = State::Stepping { executed: false };
+    }
+
+    /// Steps over subroutine calls: a `2NNN` runs until the subroutine returns, other instructions are single steps.
+    pub fn step_over(&mut self, cpu: &CPU) {
+        let pc = cpu.pc();
+        if cpu.memory().get(pc).is_some_and(|&byte| byte >> 4 == 0x2) {
+            self.state = State::Until { pc: Some(pc + 2), depth: cpu.call_stack().len() };
+            self.resumed_at = Some(pc);
+        } else {
+            self.step();
+        }
+    }
+
+    /// Runs until the current subroutine returns, or on and on outside of any subroutine.
+    pub fn step_out(&mut self, cpu: &CPU) {
+        match cpu.call_stack().len() {
+            0 => self.resume(cpu),
+            depth => {
+                self.state = State::Until { pc: None, depth: depth - 1 };
+                self.resumed_at = Some(cpu.pc());
+            }
+        }
+    }
+
+    pub fn pause(&mut self) {
+        self.state = State::Halted;
+    }
+
+    /// Runs the CPU for the rest of the frame unless it is halted or stops on the way.
+    pub fn run_frame(&mut self, cpu: &mut CPU, keypad: [bool; 16], timing: Timing) -> Run {
+        if self.state == State::Halted {
+            return Run::Halted;
+        }
+
+        let mut stop = None;
+        let state = &mut self.state;
+        let breakpoints = &self.breakpoints;
+        let resumed_at = &mut self.resumed_at;
+        let result = cpu.run_frame_until(keypad, timing, |cpu| {
+            let pc = cpu.pc();
+            let reason = match *state {
+                State::Stepping { executed: false } => {
+                    *state = State::Stepping { executed: true };
+                    None
+                }
+                State::Stepping { executed: true } => Some(Stop::Step),
+                State::Until { pc: target, depth }
+                    if target.is_none_or(|target| target == pc) && cpu.call_stack().len() <= depth =>
+                {
+                    Some(Stop::Step)
+                }
+                _ if resumed_at.take() == Some(pc) => None,
+                _ if breakpoints.contains(&pc) => Some(Stop::Breakpoint),
+                _ => None,
+            };
+            stop = reason;
+            reason.is_some()
+        });
+
+        let completed = match result {
+            Ok(completed) => completed,
+            Err(error) => {
+                self.state = State::Halted;
+                return Run::Stopped(Stop::Fault(error));
+            }
+        };
+        match stop {
+            Some(reason) => {
+                self.state = State::Halted;
+                Run::Stopped(reason)
+            }
+            None if completed => Run::Completed,
+            None => Run::Halted,
+        }
+    }
+}
+
+#[cfg(test)]
+#[path = "./control_tests.rs"]
+mod control_tests;
@@ -0,0 +1,91 @@
+use super::*;
+
+const LOOP: [u8; 8] = [0x60, 0x01, 0x70, 0x01, 0x70, 0x01, 0x12, 0x02];
+
+fn cpu() -> CPU {
+    let mut cpu = CPU::new();
+    cpu.load(&LOOP).unwrap();
+    cpu
+}
+
+#[test]
+fn test_halted_until_resumed() {
+    let mut cpu = cpu();
+    let mut debugger = Debugger::new();
+
+    assert_eq!(debugger.run_frame(&mut cpu, [false; 16], Timing::Instructions(10)), Run::Halted);
+    assert_eq!(cpu.pc(), 0x200);
+
+    debugger.resume(&cpu);
+    assert_eq!(debugger.run_frame(&mut cpu, [false; 16], Timing::Instructions(10)), Run::Completed);
+}
+
+#[test]
+fn test_breakpoint_stops_before_instruction() {
+    let mut cpu = cpu();
+    let mut debugger = Debugger::new();
+    debugger.add_breakpoint(0x204);
+    debugger.resume(&cpu);
+
+    assert_eq!(debugger.run_frame(&mut cpu, [false; 16], Timing::Instructions(10)), Run::Stopped(Stop::Breakpoint));
+    assert_eq!(cpu.pc(), 0x204);
+    assert_eq!(cpu.register(Register::V(0)), 2);
+
+    // Resuming from the breakpoint runs past it and stops there again on the next loop.
+    debugger.resume(&cpu);
+    assert_eq!(debugger.run_frame(&mut cpu, [false; 16], Timing::Instructions(10)), Run::Stopped(Stop::Breakpoint));
+    assert_eq!(cpu.register(Register::V(0)), 4);
+}
+
+#[test]
+fn test_step_runs_one_instruction() {
+    let mut cpu = cpu();
+    let mut debugger = Debugger::new();
+
+    debugger.step();
+    assert_eq!(debugger.run_frame(&mut cpu, [false; 16], Timing::Instructions(10)), Run::Stopped(Stop::Step));
+    assert_eq!(cpu.pc(), 0x202);
+
+    // A step that ends a frame stops at the start of the next one.
+    debugger.step();
+    assert_eq!(debugger.run_frame(&mut cpu, [false; 16], Timing::Instructions(2)), Run::Completed);
+    assert_eq!(debugger.run_frame(&mut cpu, [false; 16], Timing::Instructions(2)), Run::Stopped(Stop::Step));
+    assert_eq!(cpu.pc(), 0x204);
+}
+
+#[test]
+fn test_step_over_and_out_of_subroutine() {
+    // 200: call 206, 202: v1 += 1, 204: jump 204, 206: v0 += 1, 208: v0 += 1, 20A: return
+    let program = [0x22, 0x06, 0x71, 0x01, 0x12, 0x04, 0x70, 0x01, 0x70, 0x01, 0x00, 0xEE];
+    let mut cpu = CPU::new();
+    cpu.load(&program).unwrap();
+    let mut debugger = Debugger::new();
+
+    debugger.step_over(&cpu);
+    assert_eq!(debugger.run_frame(&mut cpu, [false; 16], Timing::Instructions(10)), Run::Stopped(Stop::Step));
+    assert_eq!(cpu.pc(), 0x202);
+    assert_eq!(cpu.register(Register::V(0)), 2);
+
+    cpu.reset(crate::cpu::Reset::Soft);
+    debugger.step();
+    debugger.run_frame(&mut cpu, [false; 16], Timing::Instructions(10));
+    assert_eq!(cpu.pc(), 0x206);
+
+    debugger.step_out(&cpu);
+    assert_eq!(debugger.run_frame(&mut cpu, [false; 16], Timing::Instructions(10)), Run::Stopped(Stop::Step));
+    assert_eq!(cpu.pc(), 0x202);
+    assert!(cpu.call_stack().is_empty());
+}
+
+#[test]
+fn test_fault_halts_cpu() {
+    let mut cpu = CPU::new();
+    cpu.load(&[0x00, 0xEE]).unwrap();
+    let mut debugger = Debugger::new();
+    debugger.resume(&cpu);
+
+    let run = debugger.run_frame(&mut cpu, [false; 16], Timing::Instructions(10));
+
+    assert_eq!(run, Run::Stopped(Stop::Fault(CpuError::StackUnderflow { pc: 0x200 })));
+    assert!(debugger.is_halted());
+}
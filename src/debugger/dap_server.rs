@@ -0,0 +1,536 @@
+use std::collections::HashMap;
+use std::io::{self, BufRead, BufReader, Write};
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
+use std::sync::mpsc::{self, Receiver, TryRecvError};
+use std::thread;
+
+use base64::Engine;
+use serde_json::{json, Value};
+
+use crate::cpu::{Timing, CPU};
+use crate::disassembler::disassemble_with_symbols;
+use crate::symbols::Symbols;
+use super::control::{Debugger, Run, Stop, REGISTERS};
+use super::source_map::SourceMap;
+use super::Frontend;
+
+/// The CPU is presented as the only thread of the program.
+const THREAD_ID: u64 = 1;
+
+const REGISTERS_REFERENCE: u64 = 1;
+const STACK_REFERENCE: u64 = 2;
+const MEMORY_REFERENCE: u64 = 3;
+
+/// Bytes in each row of the memory view.
+const MEMORY_ROW: usize = 16;
+
+/// What the client asked to debug in its launch request.
+#[derive(Debug, Default, PartialEq)]
+pub struct Launch {
+    pub program: Option<String>,
+    pub source_map: Option<String>,
+    pub stop_on_entry: bool,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum StepKind {
+    In,
+    Over,
+}
+
+/// A step through source lines, repeated one instruction at a time until the CPU reaches another line.
+struct LineStep {
+    kind: StepKind,
+    from: Option<(PathBuf, u32)>,
+}
+
+/// Serves the Debug Adapter Protocol, used by Visual Studio Code and other editors.
+pub struct DapServer {
+    requests: Receiver<Value>,
+    output: Box<dyn Write>,
+    sequence: u64,
+    debugger: Debugger,
+    source_map: SourceMap,
+    symbols: Rc<Symbols>,
+    /// Breakpoint addresses of each source file, replaced as a whole by every `setBreakpoints` request.
+    breakpoints: HashMap<PathBuf, Vec<usize>>,
+    /// Addresses of the breakpoints set on symbols by `setFunctionBreakpoints`.
+    function_breakpoints: Vec<usize>,
+    launch_request: Option<Value>,
+    stop_on_entry: bool,
+    line_step: Option<LineStep>,
+    quit: bool,
+}
+
+impl DapServer {
+    /// Speaks the protocol over standard input and output, so editors can run the emulator as a debug adapter.
+    pub fn stdio() -> Self {
+        let (sender, requests) = mpsc::channel();
+        thread::spawn(move || {
+            let mut input = BufReader::new(io::stdin());
+            while let Ok(Some(message)) = read_message(&mut input) {
+                if sender.send(message).is_err() {
+                    break;
+                }
+            }
+        });
+        DapServer::new(requests, Box::new(io::stdout()))
+    }
+
+    pub fn new(requests: Receiver<Value>, output: Box<dyn Write>) -> Self {
+        DapServer {
+            requests,
+            output,
+            sequence: 0,
+            debugger: Debugger::new(),
+            source_map: SourceMap::default(),
+            symbols: Rc::default(),
+            breakpoints: HashMap::new(),
+            function_breakpoints: Vec::new(),
+            launch_request: None,
+            stop_on_entry: false,
+            line_step: None,
+            quit: false,
+        }
+    }
+
+    /// Answers the client until it sends the launch request, which is answered by `finish_launch`.
+    /// Returns None if the client goes away first.
+    pub fn wait_for_launch(&mut self) -> Option<Launch> {
+        loop {
+            let request = self.requests.recv().ok()?;
+            match request["command"].as_str().unwrap_or("") {
+                "initialize" => self.respond(&request, capabilities()),
+                "launch" => {
+                    let arguments = &request["arguments"];
+                    let launch = Launch {
+                        program: arguments["program"].as_str().map(String::from),
+                        source_map: arguments["sourceMap"].as_str().map(String::from),
+                        stop_on_entry: arguments["stopOnEntry"].as_bool().unwrap_or(false),
+                    };
+                    self.stop_on_entry = launch.stop_on_entry;
+                    self.launch_request = Some(request);
+                    return Some(launch);
+                }
+                "disconnect" | "terminate" => {
+                    self.respond(&request, Value::Null);
+                    return None;
+                }
+                _ => self.respond_error(&request, "The program is not launched yet"),
+            }
+        }
+    }
+
+    /// Answers the launch request once the program is loaded, or failed to.
+    pub fn finish_launch(&mut self, result: Result<(), String>) {
+        if let Some(request) = self.launch_request.take() {
+            match result {
+                Ok(()) => {
+                    self.respond(&request, Value::Null);
+                    // Breakpoints and the other configuration requests follow, ended by configurationDone.
+                    self.send_event("initialized", Value::Null);
+                }
+                Err(error) => self.respond_error(&request, &error),
+            }
+        }
+    }
+
+    pub fn set_source_map(&mut self, source_map: SourceMap) {
+        self.source_map = source_map;
+    }
+
+    fn poll(&mut self, cpu: &mut CPU) {
+        loop {
+            match self.requests.try_recv() {
+                Ok(request) => self.handle(cpu, &request),
+                Err(TryRecvError::Empty) => break,
+                Err(TryRecvError::Disconnected) => {
+                    self.quit = true;
+                    break;
+                }
+            }
+        }
+    }
+
+    fn handle(&mut self, cpu: &mut CPU, request: &Value) {
+        let arguments = &request["arguments"];
+        let body = match request["command"].as_str().unwrap_or("") {
+            "initialize" => capabilities(),
+            "setBreakpoints" => self.set_breakpoints(arguments),
+            "setFunctionBreakpoints" => self.set_function_breakpoints(arguments),
+            "setExceptionBreakpoints" => json!({ "breakpoints": [] }),
+            "configurationDone" => {
+                self.respond(request, Value::Null);
+                if self.stop_on_entry {
+                    self.send_stopped("entry");
+                } else {
+                    self.debugger.resume(cpu);
+                }
+                return;
+            }
+            "threads" => json!({ "threads": [{ "id": THREAD_ID, "name": "CHIP-8" }] }),
+            "stackTrace" => self.stack_trace(cpu),
+            "scopes" => json!({
+                "scopes": [
+                    { "name": "Registers", "variablesReference": REGISTERS_REFERENCE, "expensive": false },
+                    { "name": "Stack", "variablesReference": STACK_REFERENCE, "expensive": false },
+                    {
+                        "name": "Memory",
+                        "variablesReference": MEMORY_REFERENCE,
+                        "indexedVariables": cpu.memory().len() / MEMORY_ROW,
+                        "expensive": true,
+                    },
+                ]
+            }),
+            "variables" => json!({ "variables": variables(cpu, &self.symbols, arguments) }),
+            "readMemory" => match read_memory(cpu, arguments) {
+                Some(body) => body,
+                None => return self.respond_error(request, "Invalid memory reference"),
+            },
+            "continue" => {
+                self.line_step = None;
+                self.debugger.resume(cpu);
+                json!({ "allThreadsContinued": true })
+            }
+            "next" => self.start_step(cpu, StepKind::Over, arguments),
+            "stepIn" => self.start_step(cpu, StepKind::In, arguments),
+            "stepOut" => {
+                self.line_step = None;
+                self.debugger.step_out(cpu);
+                Value::Null
+            }
+            "pause" => {
+                self.respond(request, Value::Null);
+                if !self.debugger.is_halted() {
+                    self.line_step = None;
+                    self.debugger.pause();
+                    self.send_stopped("pause");
+                }
+                return;
+            }
+            "disconnect" | "terminate" => {
+                self.quit = true;
+                Value::Null
+            }
+            _ => return self.respond_error(request, "Unsupported request"),
+        };
+        self.respond(request, body);
+    }
+
+    fn set_breakpoints(&mut self, arguments: &Value) -> Value {
+        let path = PathBuf::from(arguments["source"]["path"].as_str().unwrap_or(""));
+        let lines = arguments["breakpoints"].as_array().cloned().unwrap_or_default();
+
+        let mut addresses = Vec::new();
+        let breakpoints: Vec<Value> = lines
+            .iter()
+            .map(|breakpoint| {
+                let line = breakpoint["line"].as_u64().unwrap_or(0) as u32;
+                match self.source_map.addresses(&path, line) {
+                    Some((line, line_addresses)) => {
+                        addresses.extend(line_addresses);
+                        json!({ "verified": true, "line": line })
+                    }
+                    None => json!({ "verified": false, "line": line, "message": "No code on this line" }),
+                }
+            })
+            .collect();
+
+        self.breakpoints.insert(path, addresses);
+        self.update_breakpoints();
+        json!({ "breakpoints": breakpoints })
+    }
+
+    fn set_function_breakpoints(&mut self, arguments: &Value) -> Value {
+        let names = arguments["breakpoints"].as_array().cloned().unwrap_or_default();
+
+        self.function_breakpoints.clear();
+        let breakpoints: Vec<Value> = names
+            .iter()
+            .map(|breakpoint| {
+                let name = breakpoint["name"].as_str().unwrap_or("");
+                match self.symbols.resolve(name) {
+                    Some(address) => {
+                        self.function_breakpoints.push(address);
+                        json!({ "verified": true, "instructionReference": format!("0x{:X}", address) })
+                    }
+                    None => json!({ "verified": false, "message": format!("Unknown symbol {}", name) }),
+                }
+            })
+            .collect();
+
+        self.update_breakpoints();
+        json!({ "breakpoints": breakpoints })
+    }
+
+    /// Gives the debugger the breakpoints of all sources and symbols.
+    fn update_breakpoints(&mut self) {
+        self.debugger.clear_breakpoints();
+        for &address in self.breakpoints.values().flatten().chain(&self.function_breakpoints) {
+            self.debugger.add_breakpoint(address);
+        }
+    }
+
+    /// The current instruction followed by the calls of the subroutines being run.
+    fn stack_trace(&self, cpu: &CPU) -> Value {
+        let mut addresses = vec![cpu.pc()];
+        addresses.extend(cpu.call_stack().iter().rev().map(|&return_address| return_address.saturating_sub(2)));
+
+        let frames: Vec<Value> = addresses
+            .iter()
+            .enumerate()
+            .map(|(id, &address)| {
+                let opcode = cpu.memory().get(address..address + 2).map_or(0, |bytes| u16::from_be_bytes([bytes[0], bytes[1]]));
+                let mut frame = json!({
+                    "id": id,
+                    "name": format!("{}  {}", self.symbols.describe(address), disassemble_with_symbols(opcode, &self.symbols)),
+                    "line": 0,
+                    "column": 0,
+                    "instructionPointerReference": format!("0x{:X}", address),
+                });
+                if let Some((file, line)) = self.source_map.location(address) {
+                    frame["source"] = source(file);
+                    frame["line"] = json!(line);
+                    frame["column"] = json!(1);
+                }
+                frame
+            })
+            .collect();
+        json!({ "stackFrames": frames, "totalFrames": frames.len() })
+    }
+
+    fn start_step(&mut self, cpu: &CPU, kind: StepKind, arguments: &Value) -> Value {
+        let by_instruction = arguments["granularity"].as_str() == Some("instruction");
+        self.line_step = if by_instruction || self.source_map.is_empty() {
+            None
+        } else {
+            let from = self.source_map.location(cpu.pc()).map(|(file, line)| (file.to_path_buf(), line));
+            Some(LineStep { kind, from })
+        };
+        self.step(cpu, kind);
+        Value::Null
+    }
+
+    fn step(&mut self, cpu: &CPU, kind: StepKind) {
+        match kind {
+            StepKind::In => self.debugger.step(),
+            StepKind::Over => self.debugger.step_over(cpu),
+        }
+    }
+
+    /// Steps again while a line step has not reached another line with code.
+    fn continue_line_step(&mut self, cpu: &CPU) -> bool {
+        let kind = match &self.line_step {
+            Some(line_step) => {
+                let location = self.source_map.location(cpu.pc());
+                let reached = location.is_some_and(|(file, line)| {
+                    line_step.from.as_ref().is_none_or(|(from_file, from_line)| (file, line) != (from_file.as_path(), *from_line))
+                });
+                if reached {
+                    return false;
+                }
+                line_step.kind
+            }
+            None => return false,
+        };
+        self.step(cpu, kind);
+        true
+    }
+
+    fn send_stopped(&mut self, reason: &str) {
+        self.send_event("stopped", json!({ "reason": reason, "threadId": THREAD_ID, "allThreadsStopped": true }));
+    }
+
+    fn respond(&mut self, request: &Value, body: Value) {
+        self.send(json!({
+            "type": "response",
+            "request_seq": request["seq"],
+            "success": true,
+            "command": request["command"],
+            "body": body,
+        }));
+    }
+
+    fn respond_error(&mut self, request: &Value, message: &str) {
+        self.send(json!({
+            "type": "response",
+            "request_seq": request["seq"],
+            "success": false,
+            "command": request["command"],
+            "message": message,
+        }));
+    }
+
+    fn send_event(&mut self, event: &str, body: Value) {
+        self.send(json!({ "type": "event", "event": event, "body": body }));
+    }
+
+    fn send(&mut self, mut message: Value) {
+        self.sequence += 1;
+        message["seq"] = json!(self.sequence);
+        let body = message.to_string();
+        // A client that stopped reading is noticed when its requests stop too.
+        let _ = write!(self.output, "Content-Length: {}\r\n\r\n{}", body.len(), body);
+        let _ = self.output.flush();
+    }
+}
+
+impl Frontend for DapServer {
+    fn run_frame(&mut self, cpu: &mut CPU, keypad: [bool; 16], timing: Timing) -> bool {
+        self.poll(cpu);
+        loop {
+            match self.debugger.run_frame(cpu, keypad, timing) {
+                Run::Completed => return true,
+                Run::Halted => return false,
+                Run::Stopped(Stop::Step) if self.continue_line_step(cpu) => {}
+                Run::Stopped(stop) => {
+                    self.line_step = None;
+                    match stop {
+                        Stop::Breakpoint => self.send_stopped("breakpoint"),
+                        Stop::Step => self.send_stopped("step"),
+                        Stop::Pause => self.send_stopped("pause"),
+                        Stop::Fault(error) => self.send_event(
+                            "stopped",
+                            json!({
+                                "reason": "exception",
+                                "description": error.to_string(),
+                                "threadId": THREAD_ID,
+                                "allThreadsStopped": true,
+                            }),
+                        ),
+                    }
+                    return false;
+                }
+            }
+        }
+    }
+
+    fn is_halted(&self) -> bool {
+        self.debugger.is_halted()
+    }
+
+    fn quit_requested(&self) -> bool {
+        self.quit
+    }
+
+    fn set_symbols(&mut self, symbols: Rc<Symbols>) {
+        self.symbols = symbols;
+    }
+}
+
+impl Drop for DapServer {
+    fn drop(&mut self) {
+        self.send_event("terminated", Value::Null);
+    }
+}
+
+/// Reads a message framed by a `Content-Length` header. Returns None at the end of the input.
+pub fn read_message<R: BufRead>(input: &mut R) -> io::Result<Option<Value>> {
+    let mut length = None;
+    loop {
+        let mut header = String::new();
+        if input.read_line(&mut header)? == 0 {
+            return Ok(None);
+        }
+        match header.trim_end() {
+            "" if length.is_some() => break,
+            header => {
+                if let Some(value) = header.strip_prefix("Content-Length:") {
+                    length = value.trim().parse::<usize>().ok();
+                }
+            }
+        }
+    }
+
+    let mut body = vec![0; length.unwrap_or(0)];
+    input.read_exact(&mut body)?;
+    serde_json::from_slice(&body).map(Some).map_err(|error| io::Error::new(io::ErrorKind::InvalidData, error))
+}
+
+fn capabilities() -> Value {
+    json!({
+        "supportsConfigurationDoneRequest": true,
+        "supportsFunctionBreakpoints": true,
+        "supportsReadMemoryRequest": true,
+        "supportsSteppingGranularity": true,
+        "supportsTerminateRequest": true,
+    })
+}
+
+fn source(file: &Path) -> Value {
+    let name = file.file_name().map(|name| name.to_string_lossy().into_owned());
+    json!({ "name": name, "path": file.to_string_lossy() })
+}
+
+fn variables(cpu: &CPU, symbols: &Symbols, arguments: &Value) -> Vec<Value> {
+    match arguments["variablesReference"].as_u64() {
+        Some(REGISTERS_REFERENCE) => REGISTERS
+            .iter()
+            .map(|&(name, register, size)| {
+                let value = cpu.register(register);
+                json!({
+                    "name": name.to_uppercase(),
+                    "value": format!("0x{:01$X}", value, size * 2),
+                    "variablesReference": 0,
+                    "memoryReference": format!("0x{:X}", value),
+                })
+            })
+            .collect(),
+        Some(STACK_REFERENCE) => cpu
+            .call_stack()
+            .iter()
+            .enumerate()
+            .rev()
+            .map(|(depth, &return_address)| {
+                let value = match symbols.label(return_address) {
+                    Some(label) => format!("0x{:03X} ({})", return_address, label),
+                    None => format!("0x{:03X}", return_address),
+                };
+                json!({ "name": format!("#{}", depth), "value": value, "variablesReference": 0 })
+            })
+            .collect(),
+        Some(MEMORY_REFERENCE) => {
+            let rows = cpu.memory().chunks(MEMORY_ROW).enumerate();
+            let start = arguments["start"].as_u64().unwrap_or(0) as usize;
+            let count = arguments["count"].as_u64().map_or(usize::MAX, |count| count as usize);
+            rows.skip(start)
+                .take(count)
+                .map(|(row, bytes)| {
+                    let address = row * MEMORY_ROW;
+                    let hex: Vec<String> = bytes.iter().map(|byte| format!("{:02X}", byte)).collect();
+                    json!({
+                        "name": format!("{:03X}", address),
+                        "value": hex.join(" "),
+                        "variablesReference": 0,
+                        "memoryReference": format!("0x{:X}", address),
+                    })
+                })
+                .collect()
+        }
+        _ => Vec::new(),
+    }
+}
+
+fn read_memory(cpu: &CPU, arguments: &Value) -> Option<Value> {
+    let reference = arguments["memoryReference"].as_str()?;
+    let base = match reference.strip_prefix("0x") {
+        Some(hex) => i64::from_str_radix(hex, 16).ok()?,
+        None => reference.parse::<i64>().ok()?,
+    };
+    let address = (base + arguments["offset"].as_i64().unwrap_or(0)).max(0) as usize;
+    let count = arguments["count"].as_u64()? as usize;
+
+    let memory = cpu.memory();
+    let end = memory.len().min(address.saturating_add(count));
+    let data = memory.get(address..end).unwrap_or(&[]);
+    Some(json!({
+        "address": format!("0x{:X}", address),
+        "data": base64::engine::general_purpose::STANDARD.encode(data),
+        "unreadableBytes": count - data.len(),
+    }))
+}
+
+#[cfg(test)]
+#[path = "./dap_server_tests.rs"]
+mod dap_server_tests;
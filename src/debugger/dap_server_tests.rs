@@ -0,0 +1,223 @@
+use super::*;
+use std::cell::RefCell;
+use std::io::Cursor;
+use std::rc::Rc;
+use std::sync::mpsc::Sender;
+
+const TIMING: Timing = Timing::Instructions(10);
+
+// 200: v0 := 1, 202: call 208, 204: v1 += 1, 206: jump 204, 208: v0 += 1, 20A: return
+const PROGRAM: [u8; 12] = [0x60, 0x01, 0x22, 0x08, 0x71, 0x01, 0x12, 0x04, 0x70, 0x01, 0x00, 0xEE];
+
+const SOURCE_MAP: &str = "200 game.8o:2
+202 game.8o:3
+204 game.8o:5
+206 game.8o:6
+208 game.8o:9
+20A game.8o:10
+";
+
+#[derive(Clone, Default)]
+struct SharedOutput(Rc<RefCell<Vec<u8>>>);
+
+impl Write for SharedOutput {
+    fn write(&mut self, bytes: &[u8]) -> io::Result<usize> {
+        self.0.borrow_mut().write(bytes)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+struct TestClient {
+    requests: Sender<Value>,
+    output: SharedOutput,
+    read: usize,
+    /// Messages received but not looked at yet.
+    pending: Vec<Value>,
+    sequence: u64,
+    server: DapServer,
+    cpu: CPU,
+}
+
+impl TestClient {
+    /// A client that went through the launch and configuration requests, with the CPU halted on entry.
+    fn launch() -> Self {
+        let (requests, receiver) = mpsc::channel();
+        let output = SharedOutput::default();
+        let mut server = DapServer::new(receiver, Box::new(output.clone()));
+        server.set_source_map(SourceMap::parse(SOURCE_MAP, Path::new("/src")).unwrap());
+        let mut cpu = CPU::new();
+        cpu.load(&PROGRAM).unwrap();
+
+        let mut client = TestClient { requests, output, read: 0, pending: Vec::new(), sequence: 0, server, cpu };
+        client.send("initialize", json!({ "adapterID": "rs-chip8" }));
+        client.send("launch", json!({ "program": "game.ch8", "stopOnEntry": true }));
+        let launch = client.server.wait_for_launch().unwrap();
+        assert_eq!(launch.program.as_deref(), Some("game.ch8"));
+        client.server.finish_launch(Ok(()));
+        client.send("configurationDone", Value::Null);
+        client
+    }
+
+    fn send(&mut self, command: &str, arguments: Value) {
+        self.sequence += 1;
+        let request = json!({ "seq": self.sequence, "type": "request", "command": command, "arguments": arguments });
+        self.requests.send(request).unwrap();
+    }
+
+    fn receive(&mut self) {
+        let output = self.output.0.borrow();
+        let mut input = Cursor::new(&output[self.read..]);
+        while let Some(message) = read_message(&mut input).unwrap() {
+            self.pending.push(message);
+        }
+        self.read = output.len();
+    }
+
+    /// Runs frames until a pending message matches, and takes it.
+    fn take(&mut self, matches: impl Fn(&Value) -> bool) -> Value {
+        for _ in 0..100 {
+            self.receive();
+            if let Some(index) = self.pending.iter().position(&matches) {
+                return self.pending.remove(index);
+            }
+            self.server.run_frame(&mut self.cpu, [false; 16], TIMING);
+        }
+        panic!("no matching message from the server");
+    }
+
+    /// Sends a request and returns the body of its response.
+    fn request(&mut self, command: &str, arguments: Value) -> Value {
+        self.send(command, arguments);
+        let response = self.take(|message| message["type"] == "response" && message["command"] == command);
+        assert_eq!(response["success"], true, "{}", response);
+        response["body"].clone()
+    }
+
+    /// Runs frames until the server reports the CPU stopped, and returns the reason.
+    fn wait_stopped(&mut self) -> String {
+        let stopped = self.take(|message| message["event"] == "stopped");
+        stopped["body"]["reason"].as_str().unwrap().to_string()
+    }
+
+    fn current_line(&mut self) -> Value {
+        self.request("stackTrace", json!({ "threadId": THREAD_ID }))["stackFrames"][0]["line"].clone()
+    }
+}
+
+#[test]
+fn test_reads_framed_messages() {
+    let mut input = Cursor::new(b"Content-Length: 7\r\n\r\n{\"a\":1}Content-Length: 2\r\n\r\n[]".to_vec());
+
+    assert_eq!(read_message(&mut input).unwrap(), Some(json!({ "a": 1 })));
+    assert_eq!(read_message(&mut input).unwrap(), Some(json!([])));
+    assert_eq!(read_message(&mut input).unwrap(), None);
+}
+
+#[test]
+fn test_stops_on_entry_then_at_breakpoint_on_line() {
+    let mut client = TestClient::launch();
+    assert_eq!(client.wait_stopped(), "entry");
+
+    let body = client.request(
+        "setBreakpoints",
+        json!({ "source": { "path": "/src/game.8o" }, "breakpoints": [{ "line": 4 }, { "line": 20 }] }),
+    );
+    assert_eq!(body["breakpoints"][0], json!({ "verified": true, "line": 5 }));
+    assert_eq!(body["breakpoints"][1]["verified"], false);
+
+    client.request("continue", json!({ "threadId": THREAD_ID }));
+    assert_eq!(client.wait_stopped(), "breakpoint");
+    assert_eq!(client.cpu.pc(), 0x204);
+
+    let frame = &client.request("stackTrace", json!({ "threadId": THREAD_ID }))["stackFrames"][0];
+    assert_eq!(frame["line"], 5);
+    assert_eq!(frame["source"]["path"], "/src/game.8o");
+    assert_eq!(frame["name"], "204  v1 += 0x01");
+}
+
+#[test]
+fn test_stops_at_function_breakpoint_with_labels() {
+    let mut client = TestClient::launch();
+    client.server.set_symbols(Rc::new(Symbols::parse("204 main_loop\n208 increment\n").unwrap()));
+    client.wait_stopped();
+
+    let body = client.request("setFunctionBreakpoints", json!({ "breakpoints": [{ "name": "increment" }, { "name": "nope" }] }));
+    assert_eq!(body["breakpoints"][0]["verified"], true);
+    assert_eq!(body["breakpoints"][1]["verified"], false);
+
+    client.request("continue", json!({ "threadId": THREAD_ID }));
+    assert_eq!(client.wait_stopped(), "breakpoint");
+    let frames = client.request("stackTrace", json!({ "threadId": THREAD_ID }))["stackFrames"].clone();
+    assert_eq!(frames[0]["name"], "increment  v0 += 0x01");
+    assert_eq!(frames[1]["name"], "202  :call increment");
+
+    let stack = client.request("variables", json!({ "variablesReference": STACK_REFERENCE }))["variables"].clone();
+    assert_eq!(stack[0]["value"], "0x204 (main_loop)");
+}
+
+#[test]
+fn test_steps_over_into_and_out_of_subroutines() {
+    let mut client = TestClient::launch();
+    client.wait_stopped();
+
+    client.request("next", json!({ "threadId": THREAD_ID }));
+    assert_eq!(client.wait_stopped(), "step");
+    assert_eq!(client.current_line(), 3);
+
+    client.request("stepIn", json!({ "threadId": THREAD_ID }));
+    client.wait_stopped();
+    assert_eq!(client.current_line(), 9);
+    let frames = client.request("stackTrace", json!({ "threadId": THREAD_ID }))["stackFrames"].clone();
+    assert_eq!(frames[1]["line"], 3);
+
+    client.request("stepOut", json!({ "threadId": THREAD_ID }));
+    client.wait_stopped();
+    assert_eq!(client.current_line(), 5);
+
+    // Stepping over a call runs the whole subroutine.
+    client.cpu.reset(crate::cpu::Reset::Soft);
+    client.request("next", json!({ "threadId": THREAD_ID }));
+    client.wait_stopped();
+    client.request("next", json!({ "threadId": THREAD_ID }));
+    client.wait_stopped();
+    assert_eq!(client.current_line(), 5);
+    assert_eq!(client.cpu.register(crate::cpu::Register::V(0)), 2);
+}
+
+#[test]
+fn test_shows_registers_stack_and_memory() {
+    let mut client = TestClient::launch();
+    client.wait_stopped();
+    client.request("stepIn", json!({ "threadId": THREAD_ID, "granularity": "instruction" }));
+    client.wait_stopped();
+    client.request("stepIn", json!({ "threadId": THREAD_ID, "granularity": "instruction" }));
+    client.wait_stopped();
+
+    let registers = client.request("variables", json!({ "variablesReference": REGISTERS_REFERENCE }))["variables"].clone();
+    assert_eq!(registers[0]["name"], "V0");
+    assert_eq!(registers[0]["value"], "0x01");
+    assert_eq!(registers[17]["value"], "0x0208");
+
+    let stack = client.request("variables", json!({ "variablesReference": STACK_REFERENCE }))["variables"].clone();
+    assert_eq!(stack, json!([{ "name": "#0", "value": "0x204", "variablesReference": 0 }]));
+
+    let memory = client.request("variables", json!({ "variablesReference": MEMORY_REFERENCE, "start": 32, "count": 1 }));
+    assert_eq!(memory["variables"][0]["name"], "200");
+    assert!(memory["variables"][0]["value"].as_str().unwrap().starts_with("60 01 22 08"));
+
+    let read = client.request("readMemory", json!({ "memoryReference": "0x200", "offset": 2, "count": 2 }));
+    assert_eq!(read["address"], "0x202");
+    assert_eq!(read["data"], "Igg=");
+}
+
+#[test]
+fn test_quits_on_disconnect() {
+    let mut client = TestClient::launch();
+
+    client.request("disconnect", json!({}));
+
+    assert!(client.server.quit_requested());
+}
@@ -0,0 +1,386 @@
+use std::io::{self, ErrorKind, Read, Write};
+use std::net::{Ipv4Addr, TcpListener, TcpStream};
+use std::rc::Rc;
+
+use crate::cpu::{Register, Timing, CPU};
+use crate::symbols::Symbols;
+use super::control::{Debugger, Run, Stop, REGISTERS};
+use super::Frontend;
+
+/// Byte sent by the client to interrupt the running target.
+const INTERRUPT: u8 = 0x03;
+
+/// Serves the GDB remote serial protocol on a loopback TCP port, one client at a time.
+pub struct GdbServer {
+    listener: TcpListener,
+    client: Option<Client>,
+    debugger: Debugger,
+    last_stop: Stop,
+    symbols: Rc<Symbols>,
+}
+
+struct Client {
+    stream: TcpStream,
+    input: Vec<u8>,
+}
+
+enum Event {
+    Packet(String),
+    Interrupt,
+    /// A packet whose checksum does not match, which the client sends again.
+    Corrupted,
+}
+
+enum Reply {
+    Packet(String),
+    /// Sent later, when the resumed CPU stops.
+    Deferred,
+    /// Closes the connection after the optional reply.
+    Close(Option<String>),
+}
+
+impl GdbServer {
+    /// Listens on `port`, or on any free port for 0. The CPU stays halted until a client attaches and resumes it.
+    pub fn bind(port: u16) -> io::Result<Self> {
+        let listener = TcpListener::bind((Ipv4Addr::LOCALHOST, port))?;
+        listener.set_nonblocking(true)?;
+        Ok(GdbServer {
+            listener,
+            client: None,
+            debugger: Debugger::new(),
+            last_stop: Stop::Pause,
+            symbols: Rc::default(),
+        })
+    }
+
+    pub fn port(&self) -> u16 {
+        self.listener.local_addr().map(|address| address.port()).unwrap_or(0)
+    }
+
+    fn poll(&mut self, cpu: &mut CPU) {
+        if self.client.is_none() {
+            if let Ok((stream, _)) = self.listener.accept() {
+                if stream.set_nonblocking(true).is_ok() {
+                    let _ = stream.set_nodelay(true);
+                    self.client = Some(Client { stream, input: Vec::new() });
+                }
+            }
+        }
+
+        let connected = match &mut self.client {
+            Some(client) => client.receive(),
+            None => return,
+        };
+        if !connected {
+            self.disconnect(cpu);
+            return;
+        }
+
+        while let Some(event) = self.client.as_mut().and_then(|client| client.next_event()) {
+            match event {
+                Event::Packet(packet) => match self.handle(cpu, &packet) {
+                    Reply::Packet(reply) => self.send(&reply),
+                    Reply::Deferred => {}
+                    Reply::Close(reply) => {
+                        if let Some(reply) = reply {
+                            self.send(&reply);
+                        }
+                        self.disconnect(cpu);
+                    }
+                },
+                Event::Interrupt => {
+                    if !self.debugger.is_halted() {
+                        self.debugger.pause();
+                        self.report_stop(Stop::Pause);
+                    }
+                }
+                Event::Corrupted => {
+                    if let Some(client) = &mut self.client {
+                        client.write(b"-");
+                    }
+                }
+            }
+        }
+    }
+
+    /// The game carries on once the client goes away, without the breakpoints that only the client could resume from.
+    fn disconnect(&mut self, cpu: &CPU) {
+        self.client = None;
+        self.debugger.clear_breakpoints();
+        self.debugger.resume(cpu);
+    }
+
+    fn report_stop(&mut self, stop: Stop) {
+        self.last_stop = stop;
+        self.send(&stop_reply(stop));
+    }
+
+    fn send(&mut self, packet: &str) {
+        if let Some(client) = &mut self.client {
+            client.write(format!("${}#{:02x}", packet, checksum(packet.as_bytes())).as_bytes());
+        }
+    }
+
+    fn handle(&mut self, cpu: &mut CPU, packet: &str) -> Reply {
+        let command = packet.get(..1).unwrap_or("");
+        let arguments = packet.get(1..).unwrap_or("");
+        let reply = match command {
+            "?" => stop_reply(self.last_stop),
+            "g" => REGISTERS.iter().map(|&(_, register, size)| encode_register(cpu.register(register), size)).collect(),
+            "G" => {
+                let mut values = arguments;
+                for &(_, register, size) in REGISTERS.iter() {
+                    match values.get(..size * 2).and_then(decode_register) {
+                        Some(value) => cpu.set_register(register, value),
+                        None => break,
+                    }
+                    values = &values[size * 2..];
+                }
+                "OK".to_string()
+            }
+            "p" => match usize::from_str_radix(arguments, 16).ok().and_then(|number| REGISTERS.get(number)) {
+                Some(&(_, register, size)) => encode_register(cpu.register(register), size),
+                None => "E01".to_string(),
+            },
+            "P" => {
+                let write = arguments.split_once('=').and_then(|(number, value)| {
+                    let &(_, register, _) = REGISTERS.get(usize::from_str_radix(number, 16).ok()?)?;
+                    cpu.set_register(register, decode_register(value)?);
+                    Some(())
+                });
+                ok_or_error(write)
+            }
+            "m" => {
+                let memory = parse_address_length(arguments).and_then(|(address, length)| {
+                    let memory = cpu.memory().get(address..)?;
+                    Some(&memory[..length.min(memory.len())])
+                });
+                match memory {
+                    Some(memory) if !memory.is_empty() => encode_hex(memory),
+                    _ => "E01".to_string(),
+                }
+            }
+            "M" => {
+                let write = arguments.split_once(':').and_then(|(range, data)| {
+                    let (address, length) = parse_address_length(range)?;
+                    let data = decode_hex(data).filter(|data| data.len() == length)?;
+                    cpu.memory_mut().get_mut(address..address + length)?.copy_from_slice(&data);
+                    Some(())
+                });
+                ok_or_error(write)
+            }
+            "Z" | "z" => {
+                // Software and hardware breakpoints are the same thing to an interpreter.
+                let mut fields = arguments.split(',');
+                let kind = fields.next();
+                let address = fields.next().and_then(|address| usize::from_str_radix(address, 16).ok());
+                match (kind, address) {
+                    (Some("0") | Some("1"), Some(address)) => {
+                        if command == "Z" {
+                            self.debugger.add_breakpoint(address);
+                        } else {
+                            self.debugger.remove_breakpoint(address);
+                        }
+                        "OK".to_string()
+                    }
+                    _ => String::new(),
+                }
+            }
+            "c" | "s" => {
+                if let Ok(address) = u16::from_str_radix(arguments, 16) {
+                    cpu.set_register(Register::Pc, address);
+                }
+                if command == "c" {
+                    self.debugger.resume(cpu);
+                } else {
+                    self.debugger.step();
+                }
+                return Reply::Deferred;
+            }
+            "H" => "OK".to_string(),
+            "D" => return Reply::Close(Some("OK".to_string())),
+            "k" => return Reply::Close(None),
+            "q" => self.handle_query(arguments),
+            _ => String::new(),
+        };
+        Reply::Packet(reply)
+    }
+
+    fn handle_query(&mut self, query: &str) -> String {
+        if query.starts_with("Supported") {
+            "PacketSize=1000;qXfer:features:read+".to_string()
+        } else if query == "Attached" {
+            "1".to_string()
+        } else if let Some(range) = query.strip_prefix("Xfer:features:read:target.xml:") {
+            let description = target_description();
+            match parse_address_length(range) {
+                Some((offset, _)) if offset >= description.len() => "l".to_string(),
+                Some((offset, length)) if offset + length >= description.len() => format!("l{}", &description[offset..]),
+                Some((offset, length)) => format!("m{}", &description[offset..offset + length]),
+                None => "E01".to_string(),
+            }
+        } else if let Some(command) = query.strip_prefix("Rcmd,") {
+            match decode_hex(command).map(|command| String::from_utf8_lossy(&command).into_owned()) {
+                Some(command) => encode_hex(self.monitor(&command).as_bytes()),
+                None => "E01".to_string(),
+            }
+        } else {
+            String::new()
+        }
+    }
+
+    /// Runs a `monitor` command, which can name addresses by their symbols, and returns its output.
+    fn monitor(&mut self, command: &str) -> String {
+        let (name, argument) = command.trim().split_once(' ').unwrap_or((command.trim(), ""));
+        let address = self.symbols.resolve(argument.trim());
+        match (name, address) {
+            ("break", Some(address)) => {
+                self.debugger.add_breakpoint(address);
+                format!("Breakpoint at {:03X} ({})\n", address, self.symbols.describe(address))
+            }
+            ("delete", Some(address)) => {
+                self.debugger.remove_breakpoint(address);
+                format!("Deleted breakpoint at {:03X}\n", address)
+            }
+            ("symbol", Some(address)) => format!("{:03X} is {}\n", address, self.symbols.describe(address)),
+            ("break" | "delete" | "symbol", None) => format!("Unknown symbol or address: {}\n", argument.trim()),
+            _ => "Commands: break SYMBOL, delete SYMBOL, symbol SYMBOL\n".to_string(),
+        }
+    }
+}
+
+impl Frontend for GdbServer {
+    fn run_frame(&mut self, cpu: &mut CPU, keypad: [bool; 16], timing: Timing) -> bool {
+        self.poll(cpu);
+        match self.debugger.run_frame(cpu, keypad, timing) {
+            Run::Completed => true,
+            Run::Stopped(stop) => {
+                self.report_stop(stop);
+                false
+            }
+            Run::Halted => false,
+        }
+    }
+
+    fn is_halted(&self) -> bool {
+        self.debugger.is_halted()
+    }
+
+    fn set_symbols(&mut self, symbols: Rc<Symbols>) {
+        self.symbols = symbols;
+    }
+}
+
+impl Client {
+    /// Reads what the client sent so far. Returns false once the connection is closed.
+    fn receive(&mut self) -> bool {
+        let mut buffer = [0u8; 1024];
+        loop {
+            match self.stream.read(&mut buffer) {
+                Ok(0) => return false,
+                Ok(read) => self.input.extend_from_slice(&buffer[..read]),
+                Err(error) if error.kind() == ErrorKind::WouldBlock => return true,
+                Err(error) if error.kind() == ErrorKind::Interrupted => {}
+                Err(_) => return false,
+            }
+        }
+    }
+
+    fn next_event(&mut self) -> Option<Event> {
+        loop {
+            match *self.input.first()? {
+                b'$' => {
+                    let end = self.input.iter().position(|&byte| byte == b'#')?;
+                    let sent = self.input.get(end + 1..end + 3)?;
+                    let sent = std::str::from_utf8(sent).ok().and_then(|sent| u8::from_str_radix(sent, 16).ok());
+                    let packet = String::from_utf8_lossy(&self.input[1..end]).into_owned();
+                    self.input.drain(..end + 3);
+
+                    if sent != Some(checksum(packet.as_bytes())) {
+                        return Some(Event::Corrupted);
+                    }
+                    self.write(b"+");
+                    return Some(Event::Packet(packet));
+                }
+                INTERRUPT => {
+                    self.input.remove(0);
+                    return Some(Event::Interrupt);
+                }
+                // Acknowledgements of our replies, which are never lost over TCP.
+                _ => {
+                    self.input.remove(0);
+                }
+            }
+        }
+    }
+
+    fn write(&mut self, bytes: &[u8]) {
+        // Replies are written in one go, the socket is only non-blocking so reading never stalls the emulator.
+        let _ = self.stream.set_nonblocking(false);
+        let _ = self.stream.write_all(bytes);
+        let _ = self.stream.set_nonblocking(true);
+    }
+}
+
+fn stop_reply(stop: Stop) -> String {
+    match stop {
+        Stop::Pause => "S02".to_string(),
+        Stop::Breakpoint | Stop::Step => "S05".to_string(),
+        // Reported as a segmentation fault, the closest signal to running off memory or the stack.
+        Stop::Fault(_) => "S0B".to_string(),
+    }
+}
+
+/// Describes the registers in the order of the `g` packet.
+fn target_description() -> String {
+    let mut description = String::from(
+        "<?xml version=\"1.0\"?>\n<!DOCTYPE target SYSTEM \"gdb-target.dtd\">\n<target version=\"1.0\">\n<feature name=\"org.rs-chip8.cpu\">\n",
+    );
+    for (number, &(name, _, size)) in REGISTERS.iter().enumerate() {
+        let kind = match name {
+            "pc" => "code_ptr",
+            "i" => "data_ptr",
+            _ => "uint8",
+        };
+        description += &format!("<reg name=\"{}\" bitsize=\"{}\" type=\"{}\" regnum=\"{}\"/>\n", name, size * 8, kind, number);
+    }
+    description + "</feature>\n</target>\n"
+}
+
+fn ok_or_error(result: Option<()>) -> String {
+    if result.is_some() { "OK" } else { "E01" }.to_string()
+}
+
+fn checksum(bytes: &[u8]) -> u8 {
+    bytes.iter().fold(0u8, |sum, &byte| sum.wrapping_add(byte))
+}
+
+fn parse_address_length(text: &str) -> Option<(usize, usize)> {
+    let (address, length) = text.split_once(',')?;
+    Some((usize::from_str_radix(address, 16).ok()?, usize::from_str_radix(length, 16).ok()?))
+}
+
+fn encode_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|byte| format!("{:02x}", byte)).collect()
+}
+
+fn decode_hex(text: &str) -> Option<Vec<u8>> {
+    if text.len() & 1 == 1 {
+        return None;
+    }
+    (0..text.len()).step_by(2).map(|index| u8::from_str_radix(text.get(index..index + 2)?, 16).ok()).collect()
+}
+
+/// Registers are sent least significant byte first, like those of little-endian targets.
+fn encode_register(value: u16, size: usize) -> String {
+    encode_hex(&value.to_le_bytes()[..size])
+}
+
+fn decode_register(text: &str) -> Option<u16> {
+    let bytes = decode_hex(text).filter(|bytes| !bytes.is_empty() && bytes.len() <= 2)?;
+    Some(bytes.iter().rev().fold(0u16, |value, &byte| (value << 8) | byte as u16))
+}
+
+#[cfg(test)]
+#[path = "./gdb_server_tests.rs"]
+mod gdb_server_tests;
@@ -0,0 +1,152 @@
+use super::*;
+use std::time::Duration;
+
+const TIMING: Timing = Timing::Instructions(10);
+
+/// A GDB client on the loopback interface, driving the server from the same thread.
+struct TestClient {
+    stream: TcpStream,
+    server: GdbServer,
+    cpu: CPU,
+}
+
+impl TestClient {
+    fn connect(program: &[u8]) -> Self {
+        let server = GdbServer::bind(0).unwrap();
+        let stream = TcpStream::connect((Ipv4Addr::LOCALHOST, server.port())).unwrap();
+        stream.set_read_timeout(Some(Duration::from_millis(10))).unwrap();
+        let mut cpu = CPU::new();
+        cpu.load(program).unwrap();
+        TestClient { stream, server, cpu }
+    }
+
+    fn send(&mut self, packet: &str) {
+        let bytes = format!("${}#{:02x}", packet, checksum(packet.as_bytes()));
+        self.stream.write_all(bytes.as_bytes()).unwrap();
+    }
+
+    /// Runs frames until the server replies, and returns the reply without its framing.
+    fn reply(&mut self) -> String {
+        let mut received = Vec::new();
+        for _ in 0..100 {
+            self.server.run_frame(&mut self.cpu, [false; 16], TIMING);
+            let mut buffer = [0u8; 1024];
+            if let Ok(read) = self.stream.read(&mut buffer) {
+                received.extend_from_slice(&buffer[..read]);
+            }
+            let text = String::from_utf8_lossy(&received).into_owned();
+            if let Some(start) = text.find('$') {
+                if let Some(end) = text[start..].find('#') {
+                    return text[start + 1..start + end].to_string();
+                }
+            }
+        }
+        panic!("no reply to the packet");
+    }
+
+    fn request(&mut self, packet: &str) -> String {
+        self.send(packet);
+        self.reply()
+    }
+}
+
+#[test]
+fn test_reads_and_writes_registers() {
+    let mut client = TestClient::connect(&[0x60, 0x2A, 0xA3, 0x45]);
+
+    assert_eq!(client.request("?"), "S02");
+    client.send("s");
+    assert_eq!(client.reply(), "S05");
+    client.send("s");
+    assert_eq!(client.reply(), "S05");
+
+    let registers = client.request("g");
+    assert_eq!(&registers[..2], "2a");
+    // I and PC follow the sixteen V registers, least significant byte first.
+    assert_eq!(&registers[32..40], "45030402");
+
+    assert_eq!(client.request("P1=07"), "OK");
+    assert_eq!(client.request("p1"), "07");
+    assert_eq!(client.request("p15"), "E01");
+}
+
+#[test]
+fn test_reads_and_writes_memory() {
+    let mut client = TestClient::connect(&[0x12, 0x00]);
+
+    assert_eq!(client.request("m200,2"), "1200");
+    assert_eq!(client.request("M300,3:aabbcc"), "OK");
+    assert_eq!(client.request("m300,3"), "aabbcc");
+    assert_eq!(client.request("m1000,2"), "E01");
+}
+
+#[test]
+fn test_continues_to_breakpoint() {
+    let mut client = TestClient::connect(&[0x70, 0x01, 0x70, 0x01, 0x70, 0x01, 0x12, 0x00]);
+
+    assert_eq!(client.request("Z0,204,2"), "OK");
+    client.send("c");
+    assert_eq!(client.reply(), "S05");
+    assert_eq!(client.cpu.pc(), 0x204);
+    assert_eq!(client.cpu.register(Register::V(0)), 2);
+
+    assert_eq!(client.request("z0,204,2"), "OK");
+    client.send("c");
+    client.server.run_frame(&mut client.cpu, [false; 16], TIMING);
+    assert!(!client.server.is_halted());
+
+    client.stream.write_all(&[INTERRUPT]).unwrap();
+    assert_eq!(client.reply(), "S02");
+    assert!(client.server.is_halted());
+}
+
+#[test]
+fn test_sets_breakpoints_on_symbols() {
+    let mut client = TestClient::connect(&[0x70, 0x01, 0x70, 0x01, 0x12, 0x00]);
+    client.server.set_symbols(Rc::new(Symbols::parse("202 add_one\n").unwrap()));
+
+    let reply = client.request(&format!("qRcmd,{}", encode_hex(b"break add_one")));
+    assert_eq!(decode_hex(&reply).unwrap(), b"Breakpoint at 202 (add_one)\n");
+    client.send("c");
+    assert_eq!(client.reply(), "S05");
+    assert_eq!(client.cpu.pc(), 0x202);
+
+    let reply = client.request(&format!("qRcmd,{}", encode_hex(b"delete missing")));
+    assert_eq!(decode_hex(&reply).unwrap(), b"Unknown symbol or address: missing\n");
+}
+
+#[test]
+fn test_serves_target_description() {
+    let mut client = TestClient::connect(&[0x12, 0x00]);
+
+    assert!(client.request("qSupported:multiprocess+").contains("qXfer:features:read+"));
+    let description = client.request("qXfer:features:read:target.xml:0,fff");
+    assert!(description.starts_with("l<?xml"));
+    assert!(description.contains("<reg name=\"pc\" bitsize=\"16\" type=\"code_ptr\" regnum=\"17\"/>"));
+}
+
+#[test]
+fn test_resumes_when_client_detaches() {
+    let mut client = TestClient::connect(&[0x60, 0x01, 0x12, 0x00]);
+    assert_eq!(client.request("Z0,202,2"), "OK");
+
+    assert_eq!(client.request("D"), "OK");
+    for _ in 0..3 {
+        client.server.run_frame(&mut client.cpu, [false; 16], TIMING);
+    }
+
+    assert!(!client.server.is_halted());
+}
+
+#[test]
+fn test_resumes_when_client_connection_closes() {
+    let mut client = TestClient::connect(&[0x60, 0x01, 0x12, 0x00]);
+    assert_eq!(client.request("Z0,202,2"), "OK");
+
+    client.stream.shutdown(std::net::Shutdown::Both).unwrap();
+    for _ in 0..3 {
+        client.server.run_frame(&mut client.cpu, [false; 16], TIMING);
+    }
+
+    assert!(!client.server.is_halted());
+}
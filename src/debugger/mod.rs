@@ -0,0 +1,30 @@
+mod control;
+mod dap_server;
+mod gdb_server;
+mod source_map;
+
+use std::rc::Rc;
+
+use crate::cpu::{Timing, CPU};
+use crate::symbols::Symbols;
+
+pub use self::dap_server::DapServer;
+pub use self::gdb_server::GdbServer;
+pub use self::source_map::SourceMap;
+
+/// A debugger client controlling the emulation through some protocol.
+pub trait Frontend {
+    /// Answers the pending requests of the client, then runs the CPU for a frame unless it is halted.
+    /// Returns whether a frame completed.
+    fn run_frame(&mut self, cpu: &mut CPU, keypad: [bool; 16], timing: Timing) -> bool;
+
+    fn is_halted(&self) -> bool;
+
+    /// Whether the client asked to end the emulation.
+    fn quit_requested(&self) -> bool {
+        false
+    }
+
+    /// Names the addresses of the loaded ROM in what is shown to the client and in its breakpoint commands.
+    fn set_symbols(&mut self, symbols: Rc<Symbols>);
+}
@@ -0,0 +1,67 @@
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+/// Source lines of the instructions of a ROM, read from a text file with one `ADDRESS FILE:LINE` entry per line,
+/// the address in hexadecimal and source files relative to the map. Empty lines and lines starting with `#` are skipped.
+#[derive(Debug, Default)]
+pub struct SourceMap {
+    /// Instruction addresses with their source file and 1-based line, sorted by address.
+    entries: Vec<(usize, PathBuf, u32)>,
+}
+
+impl SourceMap {
+    pub fn load(path: &Path) -> io::Result<Self> {
+        let directory = path.parent().unwrap_or(Path::new("."));
+        SourceMap::parse(&fs::read_to_string(path)?, directory)
+            .map_err(|line| io::Error::new(io::ErrorKind::InvalidData, format!("Invalid source map line {}", line)))
+    }
+
+    /// Parses a source map whose source files are relative to `directory`. Fails with the number of the first invalid line.
+    pub fn parse(text: &str, directory: &Path) -> Result<Self, usize> {
+        let mut entries = Vec::new();
+        for (number, line) in text.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let entry = line.split_once(char::is_whitespace).and_then(|(address, location)| {
+                let address = usize::from_str_radix(address.trim_start_matches("0x"), 16).ok()?;
+                let (file, line) = location.trim().rsplit_once(':')?;
+                Some((address, normalize(&directory.join(file)), line.parse::<u32>().ok()?))
+            });
+            entries.push(entry.ok_or(number + 1)?);
+        }
+        entries.sort_by_key(|&(address, _, _)| address);
+        Ok(SourceMap { entries })
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Source file and line of the instruction at `address`.
+    pub fn location(&self, address: usize) -> Option<(&Path, u32)> {
+        let index = self.entries.binary_search_by_key(&address, |&(address, _, _)| address).ok()?;
+        let (_, file, line) = &self.entries[index];
+        Some((file, *line))
+    }
+
+    /// Addresses of the instructions on the first line from `line` of `file` that has any, with that line.
+    pub fn addresses(&self, file: &Path, line: u32) -> Option<(u32, Vec<usize>)> {
+        let file = normalize(file);
+        let in_file = || self.entries.iter().filter(|(_, entry_file, _)| *entry_file == file);
+        let line = in_file().map(|&(_, _, entry_line)| entry_line).filter(|&entry_line| entry_line >= line).min()?;
+        let addresses = in_file().filter(|&&(_, _, entry_line)| entry_line == line).map(|&(address, _, _)| address);
+        Some((line, addresses.collect()))
+    }
+}
+
+/// Makes paths written differently by the map and the client compare equal.
+fn normalize(path: &Path) -> PathBuf {
+    path.canonicalize().unwrap_or_else(|_| path.to_path_buf())
+}
+
+#[cfg(test)]
+#[path = "./source_map_tests.rs"]
+mod source_map_tests;
@@ -0,0 +1,27 @@
+use super::*;
+
+const MAP: &str = "# address file:line
+200 game.8o:3
+202 game.8o:3
+204 game.8o:5
+206 lib/draw.8o:1
+";
+
+#[test]
+fn test_locations_and_addresses() {
+    let map = SourceMap::parse(MAP, Path::new("/src")).unwrap();
+
+    assert_eq!(map.location(0x202), Some((Path::new("/src/game.8o"), 3)));
+    assert_eq!(map.location(0x206), Some((Path::new("/src/lib/draw.8o"), 1)));
+    assert_eq!(map.location(0x208), None);
+
+    assert_eq!(map.addresses(Path::new("/src/game.8o"), 3), Some((3, vec![0x200, 0x202])));
+    // Lines without code move to the next line that has some.
+    assert_eq!(map.addresses(Path::new("/src/game.8o"), 4), Some((5, vec![0x204])));
+    assert_eq!(map.addresses(Path::new("/src/game.8o"), 6), None);
+}
+
+#[test]
+fn test_reports_invalid_line() {
+    assert_eq!(SourceMap::parse("200 game.8o:3\nnot an entry\n", Path::new(".")).unwrap_err(), 2);
+}
@@ -0,0 +1,67 @@
+use crate::symbols::Symbols;
+
+/// Disassembles an instruction into Octo assembly syntax, as this CPU runs it. Instructions no interpreter knows are
+/// printed as raw data.
+pub fn disassemble(opcode: u16) -> String {
+    disassemble_with_symbols(opcode, &Symbols::default())
+}
+
+/// Like `disassemble`, but with the names of the addresses used by jumps, calls and `i :=`.
+pub fn disassemble_with_symbols(opcode: u16, symbols: &Symbols) -> String {
+    let x = (opcode >> 8) & 0xF;
+    let y = (opcode >> 4) & 0xF;
+    let n = opcode & 0xF;
+    let nn = opcode & 0xFF;
+    let nnn = match symbols.name((opcode & 0xFFF) as usize) {
+        Some(name) => name.to_string(),
+        None => format!("0x{:03X}", opcode & 0xFFF),
+    };
+
+    match (opcode >> 12, x, y, n) {
+        (0x0, 0x0, 0xE, 0x0) => "clear".to_string(),
+        (0x0, 0x0, 0xE, 0xE) => "return".to_string(),
+        // Run as 00E0 and 00EE by this CPU, see `OpCode::instruction`, with the opcode for what it is elsewhere.
+        (0x0, 0x0, _, 0x0) => format!("clear (0x{:04X})", opcode),
+        (0x0, 0x0, _, _) => format!("return (0x{:04X})", opcode),
+        (0x0, _, _, _) => format!("native {}", nnn),
+        (0x1, _, _, _) => format!("jump {}", nnn),
+        (0x2, _, _, _) => format!(":call {}", nnn),
+        (0x3, _, _, _) => format!("if v{:X} != 0x{:02X} then", x, nn),
+        (0x4, _, _, _) => format!("if v{:X} == 0x{:02X} then", x, nn),
+        (0x5, _, _, 0x0) => format!("if v{:X} != v{:X} then", x, y),
+        (0x6, _, _, _) => format!("v{:X} := 0x{:02X}", x, nn),
+        (0x7, _, _, _) => format!("v{:X} += 0x{:02X}", x, nn),
+        (0x8, _, _, 0x0) => format!("v{:X} := v{:X}", x, y),
+        (0x8, _, _, 0x1) => format!("v{:X} |= v{:X}", x, y),
+        (0x8, _, _, 0x2) => format!("v{:X} &= v{:X}", x, y),
+        (0x8, _, _, 0x3) => format!("v{:X} ^= v{:X}", x, y),
+        (0x8, _, _, 0x4) => format!("v{:X} += v{:X}", x, y),
+        (0x8, _, _, 0x5) => format!("v{:X} -= v{:X}", x, y),
+        (0x8, _, _, 0x6) => format!("v{:X} >>= v{:X}", x, y),
+        (0x8, _, _, 0x7) => format!("v{:X} =- v{:X}", x, y),
+        (0x8, _, _, 0xE) => format!("v{:X} <<= v{:X}", x, y),
+        (0x9, _, _, 0x0) => format!("if v{:X} == v{:X} then", x, y),
+        (0xA, _, _, _) => format!("i := {}", nnn),
+        (0xB, _, _, _) => format!("jump0 {}", nnn),
+        (0xC, _, _, _) => format!("v{:X} := random 0x{:02X}", x, nn),
+        (0xD, _, _, _) => format!("sprite v{:X} v{:X} {}", x, y, n),
+        (0xE, _, 0x9, 0xE) => format!("if v{:X} -key then", x),
+        (0xE, _, 0xA, 0x1) => format!("if v{:X} key then", x),
+        (0xF, 0x0, 0x0, 0x2) => "audio".to_string(),
+        (0xF, _, 0x0, 0x7) => format!("v{:X} := delay", x),
+        (0xF, _, 0x0, 0xA) => format!("v{:X} := key", x),
+        (0xF, _, 0x1, 0x5) => format!("delay := v{:X}", x),
+        (0xF, _, 0x1, 0x8) => format!("buzzer := v{:X}", x),
+        (0xF, _, 0x1, 0xE) => format!("i += v{:X}", x),
+        (0xF, _, 0x2, 0x9) => format!("i := hex v{:X}", x),
+        (0xF, _, 0x3, 0x3) => format!("bcd v{:X}", x),
+        (0xF, _, 0x3, 0xA) => format!("pitch := v{:X}", x),
+        (0xF, _, 0x5, 0x5) => format!("save v{:X}", x),
+        (0xF, _, 0x6, 0x5) => format!("load v{:X}", x),
+        _ => format!("0x{:02X} 0x{:02X}", opcode >> 8, nn),
+    }
+}
+
+#[cfg(test)]
+#[path = "./disassembler_tests.rs"]
+mod disassembler_tests;
@@ -0,0 +1,35 @@
+use super::*;
+
+#[test]
+fn test_disassemble() {
+    assert_eq!(disassemble(0x00E0), "clear");
+    assert_eq!(disassemble(0x1234), "jump 0x234");
+    assert_eq!(disassemble(0x3A05), "if vA != 0x05 then");
+    assert_eq!(disassemble(0x8127), "v1 =- v2");
+    assert_eq!(disassemble(0xD015), "sprite v0 v1 5");
+    assert_eq!(disassemble(0xF265), "load v2");
+}
+
+#[test]
+fn test_disassemble_00xx_as_run_by_the_cpu() {
+    assert_eq!(disassemble(0x00EE), "return");
+    assert_eq!(disassemble(0x0000), "clear (0x0000)");
+    assert_eq!(disassemble(0x00F0), "clear (0x00F0)");
+    assert_eq!(disassemble(0x00FF), "return (0x00FF)");
+    assert_eq!(disassemble(0x0123), "native 0x123");
+}
+
+#[test]
+fn test_disassemble_unknown_instruction_as_data() {
+    assert_eq!(disassemble(0x5121), "0x51 0x21");
+    assert_eq!(disassemble(0xFFFF), "0xFF 0xFF");
+}
+
+#[test]
+fn test_disassemble_with_symbols() {
+    let symbols = Symbols::parse("204 main_loop\n300 sprite\n").unwrap();
+
+    assert_eq!(disassemble_with_symbols(0x1204, &symbols), "jump main_loop");
+    assert_eq!(disassemble_with_symbols(0xA300, &symbols), "i := sprite");
+    assert_eq!(disassemble_with_symbols(0x2206, &symbols), ":call 0x206");
+}
@@ -1,26 +1,23 @@
-use sdl2;
-use sdl2::audio::{AudioDevice, AudioCallback, AudioSpecDesired};
+use std::fs::File;
+use std::io::{self, BufWriter};
+use std::path::Path;
 
-struct SquareWave {
-    phase_inc: f32,
-    phase: f32,
-    volume: f32,
-}
+use sdl2::audio::{AudioQueue, AudioSpecDesired};
 
-impl AudioCallback for SquareWave {
-    type Channel = f32;
+use rs_chip8::audio::{AudioRenderer, SAMPLE_RATE};
 
-    fn callback(&mut self, out: &mut [f32]) {
-        // Generate a square wave
-        for x in out.iter_mut() {
-            *x = if self.phase >= 0.0 && self.phase < 0.5 { self.volume } else { -self.volume };
-            self.phase = (self.phase + self.phase_inc) % 1.0;
-        }
-    }
-}
+use crate::cpu::OutputState;
+use crate::wav::WavWriter;
+
+/// Frames of audio allowed to pile up in the queue before new frames are dropped,
+/// so a host that falls behind does not accumulate latency.
+const MAX_QUEUED_FRAMES: u32 = 4;
 
 pub struct AudioDriver {
-    device: AudioDevice<SquareWave>,
+    queue: Option<AudioQueue<f32>>,
+    recorder: Option<WavWriter<BufWriter<File>>>,
+    renderer: AudioRenderer,
+    buffer: Vec<f32>,
 }
 
 impl AudioDriver {
@@ -28,29 +25,54 @@ impl AudioDriver {
         let audio_subsystem = sdl_context.audio().unwrap();
 
         let desired_spec = AudioSpecDesired {
-            freq: Some(44100),
+            freq: Some(SAMPLE_RATE),
             channels: Some(1),  // mono
             samples: None,
         };
 
-        let device = audio_subsystem
-            .open_playback(None, &desired_spec, |spec| {
-                // Initialize the audio callback
-                SquareWave {
-                    phase_inc: 440.0 / spec.freq as f32,
-                    phase: 0.0,
-                    volume: 1.0,
-                }
-            })
+        let queue = audio_subsystem
+            .open_queue::<f32, _>(None, &desired_spec)
             .unwrap();
+        let freq = queue.spec().freq;
+        queue.resume();
+
+        AudioDriver::with_queue(Some(queue), freq)
+    }
+
+    /// Creates a driver without an audio device, which only renders frames for recording.
+    pub fn headless() -> Self {
+        AudioDriver::with_queue(None, SAMPLE_RATE)
+    }
 
-        AudioDriver { device: device }
+    fn with_queue(queue: Option<AudioQueue<f32>>, freq: i32) -> Self {
+        AudioDriver {
+            queue,
+            recorder: None,
+            renderer: AudioRenderer::new(freq),
+            buffer: Vec::new(),
+        }
     }
 
-    pub fn start_beep(&self) {
-        self.device.resume();
+    /// Writes every frame produced from now on to a WAV file at `path`.
+    pub fn record<P: AsRef<Path>>(&mut self, path: P) -> io::Result<()> {
+        self.recorder = Some(WavWriter::create(path, self.renderer.sample_rate() as u32)?);
+        Ok(())
     }
-    pub fn stop_beep(&self) {
-        self.device.pause();
+
+    /// Queues the samples for one emulated frame, see `AudioRenderer::render_frame`.
+    pub fn queue_frame(&mut self, output: &OutputState) {
+        self.buffer.clear();
+        self.renderer.render_frame(output, &mut self.buffer);
+
+        if let Some(recorder) = &mut self.recorder {
+            recorder.write_samples(&self.buffer).expect("Could not write WAV file");
+        }
+
+        if let Some(queue) = &self.queue {
+            let bytes_per_frame = self.renderer.samples_per_frame() as u32 * std::mem::size_of::<f32>() as u32;
+            if queue.size() < bytes_per_frame * MAX_QUEUED_FRAMES {
+                queue.queue(&self.buffer);
+            }
+        }
     }
-}
\ No newline at end of file
+}
@@ -1,20 +1,25 @@
-use sdl2;
 use sdl2::pixels;
+use sdl2::pixels::PixelFormatEnum;
 use sdl2::rect::Rect;
-use sdl2::render::Canvas;
+use sdl2::render::{Canvas, Texture};
 use sdl2::video::Window;
 
-const CHIP8_WIDTH: usize = 64;
-const CHIP8_HEIGHT: usize = 32;
+use crate::framebuffer::{Framebuffer, HEIGHT, WIDTH};
+
+use super::osd::{self, Osd};
 
 const SCALE_FACTOR: u32 = 20;
-const SCREEN_WIDTH: u32 = (CHIP8_WIDTH as u32) * SCALE_FACTOR;
-const SCREEN_HEIGHT: u32 = (CHIP8_HEIGHT as u32) * SCALE_FACTOR;
+const SCREEN_WIDTH: u32 = (WIDTH as u32) * SCALE_FACTOR;
+const SCREEN_HEIGHT: u32 = (HEIGHT as u32) * SCALE_FACTOR;
+
+const BYTES_PER_PIXEL: usize = 3;
 
 pub struct DisplayDriver {
     canvas: Canvas<Window>,
+    texture: Texture,
     color_background: sdl2::pixels::Color,
     color_foreground: sdl2::pixels::Color,
+    osd: Osd,
 }
 
 impl DisplayDriver {
@@ -41,32 +46,69 @@ impl DisplayDriver {
         canvas.clear();
         canvas.present();
 
+        // The screen is drawn at its native resolution into a streaming texture that the canvas scales up.
+        let texture = canvas
+            .texture_creator()
+            .create_texture_streaming(PixelFormatEnum::RGB24, WIDTH as u32, HEIGHT as u32)
+            .unwrap();
+
         DisplayDriver {
             canvas,
+            texture,
             color_background,
             color_foreground,
+            osd: Osd::new(),
         }
     }
 
-    pub fn draw(&mut self, pixels: &[[u8; CHIP8_WIDTH]; CHIP8_HEIGHT]) {
-        for (y, row) in pixels.iter().enumerate() {
-            for (x, &col) in row.iter().enumerate() {
-                let x = (x as u32) * SCALE_FACTOR;
-                let y = (y as u32) * SCALE_FACTOR;
-
-                let mut current_color = self.color_background;
-                if col == 1 {
-                    current_color = self.color_foreground;
-                }
-                self.canvas.set_draw_color(current_color);
-                let _ = self.canvas.fill_rect(Rect::new(
-                    x as i32,
-                    y as i32,
-                    SCALE_FACTOR,
-                    SCALE_FACTOR,
-                ));
-            }
+    pub fn osd(&mut self) -> &mut Osd {
+        &mut self.osd
+    }
+
+    /// Uploads the rows changed since the last frame to the texture. They are shown by the next `present`.
+    pub fn update(&mut self, vram: &Framebuffer) {
+        self.upload(vram, vram.dirty_rows());
+    }
+
+    /// Uploads every row to the texture, so that nothing drawn by a previous CPU is left on screen.
+    pub fn redraw_all(&mut self, vram: &Framebuffer) {
+        self.upload(vram, u32::MAX >> (u32::BITS - HEIGHT as u32));
+    }
+
+    fn upload(&mut self, vram: &Framebuffer, dirty_rows: u32) {
+        if dirty_rows != 0 {
+            let first = dirty_rows.trailing_zeros() as usize;
+            let last = (u32::BITS - 1 - dirty_rows.leading_zeros()) as usize;
+            let region = Rect::new(0, first as i32, WIDTH as u32, (last - first + 1) as u32);
+
+            let background = self.color_background.rgb();
+            let foreground = self.color_foreground.rgb();
+            self.texture
+                .with_lock(region, |buffer, pitch| {
+                    for (line, y) in (first..=last).enumerate() {
+                        let row = vram.row(y);
+                        for x in 0..WIDTH {
+                            let (r, g, b) = if (row >> (WIDTH - 1 - x)) & 0x01 == 1 { foreground } else { background };
+                            let offset = line * pitch + x * BYTES_PER_PIXEL;
+                            buffer[offset..offset + BYTES_PER_PIXEL].copy_from_slice(&[r, g, b]);
+                        }
+                    }
+                })
+                .unwrap();
         }
+    }
+
+    /// Draws the ROM launcher in place of the emulated screen.
+    pub fn present_menu(&mut self, lines: &[String], selected: usize, details: &[String]) {
+        osd::draw_menu(&mut self.canvas, "Select a ROM", lines, selected, details);
+        self.osd.draw(&mut self.canvas);
+        self.canvas.present();
+    }
+
+    /// Draws the emulated screen with the on-screen display on top.
+    pub fn present(&mut self) {
+        let _ = self.canvas.copy(&self.texture, None, None);
+        self.osd.draw(&mut self.canvas);
         self.canvas.present();
     }
 }
@@ -0,0 +1,68 @@
+use sdl2::pixels::PixelFormatEnum;
+use sdl2::render::{Canvas, Texture};
+use sdl2::video::Window;
+
+use rs_chip8::profiler::{Heatmap, Profile};
+
+const WINDOW_SIZE: u32 = 512;
+
+const BYTES_PER_PIXEL: usize = 3;
+
+/// A window with a pixel for each byte of memory, in rows from address 0, that shows its `Heatmap`.
+pub struct HeatmapDriver {
+    canvas: Canvas<Window>,
+    texture: Texture,
+    columns: usize,
+    heatmap: Heatmap,
+}
+
+impl HeatmapDriver {
+    pub fn new(sdl_context: &sdl2::Sdl, memory_size: usize) -> Self {
+        // Square, with 64 columns for 4 KB and 256 for 64 KB.
+        let columns = (memory_size as f64).sqrt().ceil() as usize;
+        let rows = memory_size.div_ceil(columns);
+
+        let video_subsystem = sdl_context.video().unwrap();
+        let window = video_subsystem
+            .window("rs-chip8 memory", WINDOW_SIZE, WINDOW_SIZE)
+            .opengl()
+            .build()
+            .unwrap();
+        let canvas = window.into_canvas().build().unwrap();
+        let texture = canvas
+            .texture_creator()
+            .create_texture_streaming(PixelFormatEnum::RGB24, columns as u32, rows as u32)
+            .unwrap();
+
+        HeatmapDriver {
+            canvas,
+            texture,
+            columns,
+            heatmap: Heatmap::new(memory_size),
+        }
+    }
+
+    /// Starts over for the profile of a new CPU, whose counts start again from 0.
+    pub fn reset(&mut self) {
+        self.heatmap.reset();
+    }
+
+    /// Lights up the bytes used since the last update, fades the others and shows the result.
+    pub fn update(&mut self, profile: &Profile) {
+        self.heatmap.update(profile);
+
+        let (heat, columns) = (self.heatmap.heat(), self.columns);
+        self.texture
+            .with_lock(None, |buffer, pitch| {
+                for (address, channels) in heat.iter().enumerate() {
+                    let offset = (address / columns) * pitch + (address % columns) * BYTES_PER_PIXEL;
+                    for (channel, &brightness) in channels.iter().enumerate() {
+                        buffer[offset + channel] = (brightness * 255.0) as u8;
+                    }
+                }
+            })
+            .unwrap();
+        let _ = self.canvas.copy(&self.texture, None, None);
+        self.canvas.present();
+    }
+}
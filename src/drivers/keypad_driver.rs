@@ -1,6 +1,32 @@
-use sdl2;
+use std::path::PathBuf;
+
 use sdl2::event::Event;
-use sdl2::keyboard::Keycode;
+use sdl2::keyboard::{Keycode, Scancode};
+
+/// Emulator controls outside the CHIP-8 keypad, reported when their key is pressed.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Hotkey {
+    TogglePause,
+    FrameAdvance,
+    ToggleSlowMotion,
+    ToggleStatusBar,
+    SoftReset,
+    HardReset,
+    MenuUp,
+    MenuDown,
+    MenuSelect,
+    /// Stops the running game and returns to the ROM launcher.
+    OpenMenu,
+}
+
+pub struct Input {
+    pub keypad: [bool; 16],
+    pub hotkeys: Vec<Hotkey>,
+    /// Whether the fast-forward key is held down.
+    pub fast_forward: bool,
+    /// File dropped onto the window.
+    pub dropped_file: Option<PathBuf>,
+}
 
 pub struct KeypadDriver {
     events: sdl2::EventPump,
@@ -12,16 +38,38 @@ impl KeypadDriver {
     }
 
 
-    pub fn poll(&mut self) -> Result<[bool; 16], ()> {
+    pub fn poll(&mut self) -> Result<Input, ()> {
+        let mut hotkeys = Vec::new();
+        let mut dropped_file = None;
 
         for event in self.events.poll_iter() {
-            if let Event::Quit { .. } = event {
-                return Err(());
-            };
+            match event {
+                Event::Quit { .. } => return Err(()),
+                Event::KeyDown { keycode: Some(keycode), repeat: false, .. } => {
+                    let hotkey = match keycode {
+                        Keycode::P => Some(Hotkey::TogglePause),
+                        Keycode::N => Some(Hotkey::FrameAdvance),
+                        Keycode::M => Some(Hotkey::ToggleSlowMotion),
+                        Keycode::I => Some(Hotkey::ToggleStatusBar),
+                        Keycode::F5 => Some(Hotkey::SoftReset),
+                        Keycode::F6 => Some(Hotkey::HardReset),
+                        Keycode::Up => Some(Hotkey::MenuUp),
+                        Keycode::Down => Some(Hotkey::MenuDown),
+                        Keycode::Return => Some(Hotkey::MenuSelect),
+                        Keycode::Escape => Some(Hotkey::OpenMenu),
+                        _ => None,
+                    };
+                    hotkeys.extend(hotkey);
+                }
+                Event::DropFile { filename, .. } => dropped_file = Some(PathBuf::from(filename)),
+                _ => {}
+            }
         }
 
-        let keys: Vec<Keycode> = self.events
-            .keyboard_state()
+        let keyboard = self.events.keyboard_state();
+        let fast_forward = keyboard.is_scancode_pressed(Scancode::Tab);
+
+        let keys: Vec<Keycode> = keyboard
             .pressed_scancodes()
             .filter_map(Keycode::from_scancode)
             .collect();
@@ -53,7 +101,12 @@ impl KeypadDriver {
                 chip8_keys[i] = true;
             }
         }
-        
-        Ok(chip8_keys)
+
+        Ok(Input {
+            keypad: chip8_keys,
+            hotkeys,
+            fast_forward,
+            dropped_file,
+        })
     }
-}
\ No newline at end of file
+}
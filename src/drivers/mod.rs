@@ -1,7 +1,10 @@
 mod display_driver;
+mod heatmap_driver;
 mod keypad_driver;
 mod audio_driver;
+mod osd;
 
 pub use self::display_driver::DisplayDriver;
-pub use self::keypad_driver::KeypadDriver;
+pub use self::heatmap_driver::HeatmapDriver;
+pub use self::keypad_driver::{Hotkey, KeypadDriver};
 pub use self::audio_driver::AudioDriver;
\ No newline at end of file
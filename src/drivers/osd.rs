@@ -0,0 +1,142 @@
+use std::time::{Duration, Instant};
+
+use sdl2::pixels::Color;
+use sdl2::rect::Rect;
+use sdl2::render::{BlendMode, Canvas};
+use sdl2::video::Window;
+
+use crate::fonts::osd_glyph;
+
+/// Size in screen pixels of one pixel of an OSD glyph.
+const GLYPH_SCALE: u32 = 3;
+const GLYPH_WIDTH: u32 = 4;
+const GLYPH_HEIGHT: u32 = 5;
+const GLYPH_SPACING: u32 = 1;
+const PADDING: u32 = 2 * GLYPH_SCALE;
+
+const MESSAGE_DURATION: Duration = Duration::from_secs(2);
+
+const TEXT_COLOR: Color = Color::RGB(255, 255, 255);
+const BOX_COLOR: Color = Color::RGBA(0, 0, 0, 176);
+
+/// Text drawn over the emulated screen: a transient message, a status indicator and an optional status bar.
+pub struct Osd {
+    message: Option<(String, Instant)>,
+    status: String,
+    status_bar: Option<String>,
+}
+
+impl Osd {
+    pub fn new() -> Self {
+        Osd {
+            message: None,
+            status: String::new(),
+            status_bar: None,
+        }
+    }
+
+    /// Shows `message` in the bottom left corner for a couple of seconds.
+    pub fn show_message(&mut self, message: &str) {
+        self.message = Some((message.to_string(), Instant::now()));
+    }
+
+    /// Shows `status` in the top right corner until it is replaced. An empty status hides it.
+    pub fn set_status(&mut self, status: &str) {
+        self.status = status.to_string();
+    }
+
+    /// Shows a bar along the bottom edge with `text`, or hides it with `None`.
+    pub fn set_status_bar(&mut self, text: Option<String>) {
+        self.status_bar = text;
+    }
+
+    pub fn draw(&mut self, canvas: &mut Canvas<Window>) {
+        let (width, height) = canvas.output_size().unwrap_or((0, 0));
+        canvas.set_blend_mode(BlendMode::Blend);
+
+        let line_height = GLYPH_HEIGHT * GLYPH_SCALE + 2 * PADDING;
+        let mut bottom = height;
+
+        if let Some(text) = &self.status_bar {
+            canvas.set_draw_color(BOX_COLOR);
+            let _ = canvas.fill_rect(Rect::new(0, (height - line_height) as i32, width, line_height));
+            draw_text(canvas, text, PADDING as i32, (height - line_height + PADDING) as i32);
+            bottom -= line_height;
+        }
+
+        if !self.status.is_empty() {
+            let x = width.saturating_sub(text_width(&self.status) + 2 * PADDING);
+            draw_boxed_text(canvas, &self.status, x as i32, 0);
+        }
+
+        if let Some((message, shown)) = &self.message {
+            if shown.elapsed() < MESSAGE_DURATION {
+                draw_boxed_text(canvas, message, 0, (bottom - line_height) as i32);
+            } else {
+                self.message = None;
+            }
+        }
+    }
+}
+
+/// Draws a full screen list of `lines` with the `selected` one highlighted, and `details` below it.
+pub fn draw_menu(canvas: &mut Canvas<Window>, title: &str, lines: &[String], selected: usize, details: &[String]) {
+    let (width, height) = canvas.output_size().unwrap_or((0, 0));
+    let line_height = (GLYPH_HEIGHT * GLYPH_SCALE + 2 * PADDING) as i32;
+    let details_top = height as i32 - line_height * (details.len() as i32 + 1);
+
+    canvas.set_draw_color(Color::RGB(0, 0, 0));
+    canvas.clear();
+    draw_text(canvas, title, PADDING as i32, PADDING as i32);
+
+    // Scrolls the list so the selected line stays visible.
+    let list_top = 2 * line_height;
+    let visible = ((details_top - list_top) / line_height).max(1) as usize;
+    let first = (selected + 1).saturating_sub(visible);
+    for (i, line) in lines.iter().enumerate().skip(first).take(visible) {
+        let y = list_top + (i - first) as i32 * line_height;
+        if i == selected {
+            canvas.set_draw_color(Color::RGB(64, 64, 64));
+            let _ = canvas.fill_rect(Rect::new(0, y, width, line_height as u32));
+        }
+        draw_text(canvas, line, PADDING as i32, y + PADDING as i32);
+    }
+
+    for (i, line) in details.iter().enumerate() {
+        draw_text(canvas, line, PADDING as i32, details_top + line_height * i as i32 + PADDING as i32);
+    }
+}
+
+fn text_width(text: &str) -> u32 {
+    let glyphs = text.chars().count() as u32;
+    (glyphs * (GLYPH_WIDTH + GLYPH_SPACING)).saturating_sub(GLYPH_SPACING) * GLYPH_SCALE
+}
+
+fn draw_boxed_text(canvas: &mut Canvas<Window>, text: &str, x: i32, y: i32) {
+    let box_width = text_width(text) + 2 * PADDING;
+    let box_height = GLYPH_HEIGHT * GLYPH_SCALE + 2 * PADDING;
+    canvas.set_draw_color(BOX_COLOR);
+    let _ = canvas.fill_rect(Rect::new(x, y, box_width, box_height));
+    draw_text(canvas, text, x + PADDING as i32, y + PADDING as i32);
+}
+
+fn draw_text(canvas: &mut Canvas<Window>, text: &str, x: i32, y: i32) {
+    let mut pixels = Vec::new();
+    for (i, c) in text.chars().enumerate() {
+        let glyph_x = x + (i as u32 * (GLYPH_WIDTH + GLYPH_SPACING) * GLYPH_SCALE) as i32;
+        for (row, bits) in osd_glyph(c).iter().enumerate() {
+            for column in 0..GLYPH_WIDTH {
+                if (bits >> (7 - column)) & 0x01 == 1 {
+                    pixels.push(Rect::new(
+                        glyph_x + (column * GLYPH_SCALE) as i32,
+                        y + (row as u32 * GLYPH_SCALE) as i32,
+                        GLYPH_SCALE,
+                        GLYPH_SCALE,
+                    ));
+                }
+            }
+        }
+    }
+    canvas.set_draw_color(TEXT_COLOR);
+    let _ = canvas.fill_rects(&pixels);
+}
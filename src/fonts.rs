@@ -79,4 +79,56 @@ pub const FONT_SET: [u8; 80] = [
     0xF0,
     0x80,
     0x80,
-];
\ No newline at end of file
+];
+
+/// 4x5 glyph for the on-screen display, in the same format as `FONT_SET`. Letters are drawn in upper case and
+/// characters without a glyph as a question mark.
+pub fn osd_glyph(c: char) -> [u8; 5] {
+    match c.to_ascii_uppercase() {
+        '0'..='9' => {
+            let start = (c as usize - '0' as usize) * 5;
+            let mut glyph = [0u8; 5];
+            glyph.copy_from_slice(&FONT_SET[start..start + 5]);
+            glyph
+        }
+        'A' => [0x60, 0x90, 0xF0, 0x90, 0x90],
+        'B' => [0xE0, 0x90, 0xE0, 0x90, 0xE0],
+        'C' => [0x70, 0x80, 0x80, 0x80, 0x70],
+        'D' => [0xE0, 0x90, 0x90, 0x90, 0xE0],
+        'E' => [0xF0, 0x80, 0xE0, 0x80, 0xF0],
+        'F' => [0xF0, 0x80, 0xE0, 0x80, 0x80],
+        'G' => [0x70, 0x80, 0xB0, 0x90, 0x70],
+        'H' => [0x90, 0x90, 0xF0, 0x90, 0x90],
+        'I' => [0xE0, 0x40, 0x40, 0x40, 0xE0],
+        'J' => [0x10, 0x10, 0x10, 0x90, 0x60],
+        'K' => [0x90, 0xA0, 0xC0, 0xA0, 0x90],
+        'L' => [0x80, 0x80, 0x80, 0x80, 0xF0],
+        'M' => [0x90, 0xF0, 0xF0, 0x90, 0x90],
+        'N' => [0x90, 0xD0, 0xB0, 0x90, 0x90],
+        'O' => [0x60, 0x90, 0x90, 0x90, 0x60],
+        'P' => [0xE0, 0x90, 0xE0, 0x80, 0x80],
+        'Q' => [0x60, 0x90, 0x90, 0xB0, 0x70],
+        'R' => [0xE0, 0x90, 0xE0, 0xA0, 0x90],
+        'S' => [0x70, 0x80, 0x60, 0x10, 0xE0],
+        'T' => [0xE0, 0x40, 0x40, 0x40, 0x40],
+        'U' => [0x90, 0x90, 0x90, 0x90, 0x60],
+        'V' => [0x90, 0x90, 0x90, 0x60, 0x60],
+        'W' => [0x90, 0x90, 0xF0, 0xF0, 0x90],
+        'X' => [0x90, 0x90, 0x60, 0x90, 0x90],
+        'Y' => [0xA0, 0xA0, 0x40, 0x40, 0x40],
+        'Z' => [0xF0, 0x10, 0x60, 0x80, 0xF0],
+        ' ' => [0x00, 0x00, 0x00, 0x00, 0x00],
+        '.' => [0x00, 0x00, 0x00, 0x00, 0x40],
+        ',' => [0x00, 0x00, 0x00, 0x40, 0x80],
+        ':' => [0x00, 0x40, 0x00, 0x40, 0x00],
+        '-' => [0x00, 0x00, 0xE0, 0x00, 0x00],
+        '_' => [0x00, 0x00, 0x00, 0x00, 0xF0],
+        '/' => [0x10, 0x20, 0x20, 0x40, 0x80],
+        '%' => [0x90, 0x10, 0x20, 0x40, 0x90],
+        '>' => [0x80, 0x40, 0x20, 0x40, 0x80],
+        '<' => [0x20, 0x40, 0x80, 0x40, 0x20],
+        '(' => [0x20, 0x40, 0x40, 0x40, 0x20],
+        ')' => [0x40, 0x20, 0x20, 0x20, 0x40],
+        _ => [0x60, 0x90, 0x20, 0x00, 0x20],
+    }
+}
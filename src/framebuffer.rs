@@ -0,0 +1,77 @@
+pub const WIDTH: usize = 64;
+pub const HEIGHT: usize = 32;
+
+/// Monochrome screen with one bit per pixel. Each row is a `u64` with the leftmost pixel in the most significant bit.
+/// Rows changed since the last call to `clear_dirty` are tracked so frontends only redraw what changed.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Framebuffer {
+    rows: [u64; HEIGHT],
+    dirty_rows: u32,
+}
+
+impl Default for Framebuffer {
+    fn default() -> Self {
+        Framebuffer::new()
+    }
+}
+
+impl Framebuffer {
+    pub fn new() -> Self {
+        Framebuffer {
+            rows: [0; HEIGHT],
+            dirty_rows: 0,
+        }
+    }
+
+    #[cfg(test)]
+    pub fn pixel(&self, x: usize, y: usize) -> bool {
+        (self.rows[y] >> (WIDTH - 1 - x)) & 0x01 == 1
+    }
+
+    #[cfg(test)]
+    pub fn set_pixel(&mut self, x: usize, y: usize, on: bool) {
+        let mask = 1 << (WIDTH - 1 - x);
+        if on {
+            self.rows[y] |= mask;
+        } else {
+            self.rows[y] &= !mask;
+        }
+        self.dirty_rows |= 1 << y;
+    }
+
+    pub fn row(&self, y: usize) -> u64 {
+        self.rows[y]
+    }
+
+    pub fn clear(&mut self) {
+        for (y, row) in self.rows.iter_mut().enumerate() {
+            if *row != 0 {
+                *row = 0;
+                self.dirty_rows |= 1 << y;
+            }
+        }
+    }
+
+    /// XORs `bits` onto row `y` and returns whether any pixel was turned off.
+    pub fn xor_row(&mut self, y: usize, bits: u64) -> bool {
+        let collided = self.rows[y] & bits != 0;
+        self.rows[y] ^= bits;
+        if bits != 0 {
+            self.dirty_rows |= 1 << y;
+        }
+        collided
+    }
+
+    /// Bit mask of the rows changed since the last call to `clear_dirty`, row 0 in the least significant bit.
+    pub fn dirty_rows(&self) -> u32 {
+        self.dirty_rows
+    }
+
+    pub fn clear_dirty(&mut self) {
+        self.dirty_rows = 0;
+    }
+}
+
+#[cfg(test)]
+#[path = "./framebuffer_tests.rs"]
+mod framebuffer_tests;
@@ -0,0 +1,30 @@
+use super::*;
+
+#[test]
+fn test_xor_row_reports_collision_and_dirty_row() {
+    let mut vram = Framebuffer::new();
+
+    assert!(!vram.xor_row(3, 0xF000_0000_0000_0000));
+    assert!(vram.pixel(0, 3));
+    assert!(vram.pixel(3, 3));
+    assert!(!vram.pixel(4, 3));
+    assert_eq!(vram.dirty_rows(), 1 << 3);
+
+    vram.clear_dirty();
+    assert!(vram.xor_row(3, 0x8000_0000_0000_0000));
+    assert!(!vram.pixel(0, 3));
+    assert_eq!(vram.dirty_rows(), 1 << 3);
+}
+
+#[test]
+fn test_clear_only_marks_lit_rows_dirty() {
+    let mut vram = Framebuffer::new();
+    vram.set_pixel(63, 31, true);
+    vram.set_pixel(10, 4, true);
+    vram.clear_dirty();
+
+    vram.clear();
+
+    assert!(!vram.pixel(63, 31));
+    assert_eq!(vram.dirty_rows(), (1 << 31) | (1 << 4));
+}
@@ -0,0 +1,401 @@
+//! Translates basic blocks of CHIP-8 code to x86-64 machine code, for running under instruction timing far faster than
+//! the interpreter. A block is a run of instructions that only touch V0-VF, I, the timers and the keypad, which follows
+//! jumps and is optionally ended by a skip or a computed jump. Anything else (the stack, memory, the screen and random
+//! numbers) is left to the interpreter, which runs the instruction that ended the block before the next block starts.
+//!
+//! Compiled code is thrown away when the program writes over it, and the bytes written are never compiled again, so
+//! self-modifying code always runs in the interpreter.
+#[cfg(not(target_arch = "x86_64"))]
+compile_error!("The jit feature only supports x86-64 targets");
+
+use std::io;
+use std::ops::Range;
+
+use memmap2::{Mmap, MmapMut};
+
+/// Instructions compiled into one block at most, to bound how far a block can overrun the instructions of a frame.
+const MAX_BLOCK_INSTRUCTIONS: usize = 32;
+
+/// Bytes of executable memory for compiled code. Once full, every block is compiled again from an empty arena.
+const CODE_SIZE: usize = 256 * 1024;
+
+/// Bytes of x86-64 code a CHIP-8 instruction compiles to at most, plus the start and end of a block.
+const MAX_INSTRUCTION_CODE: usize = 32;
+
+/// Compiled code of a block, which returns the address the program counter goes to next.
+type Entry = unsafe extern "sysv64" fn(
+    registers: *mut u8,
+    index: *mut u16,
+    delay_timer: *mut u8,
+    sound_timer: *mut u8,
+    keypad: *const bool,
+) -> u32;
+
+/// The state of the CPU compiled code reads and writes.
+pub struct Machine<'a> {
+    pub registers: &'a mut [u8; 16],
+    pub index: &'a mut u16,
+    pub delay_timer: &'a mut u8,
+    pub sound_timer: &'a mut u8,
+    pub keypad: &'a [bool; 16],
+}
+
+#[derive(Clone, Copy)]
+enum Slot {
+    NotCompiled,
+    /// The instruction at the address is left to the interpreter.
+    Interpreted,
+    /// Code starting with the instruction at the address, in the arena.
+    Block { entry: Entry, instructions: u32 },
+}
+
+pub struct Jit {
+    /// What runs at each address.
+    slots: Vec<Slot>,
+    /// Executable memory holding the code of every block, `None` while it is being written or if it could not be
+    /// made writable again.
+    code: Option<Mmap>,
+    /// Bytes of `code` in use.
+    code_used: usize,
+    /// Bytes inside a compiled block, to tell quickly whether a write changes code.
+    compiled: Vec<bool>,
+    /// Bytes of code the program wrote over, which are left to the interpreter from then on.
+    modified: Vec<bool>,
+    blocks_run: u64,
+    instructions_run: u64,
+}
+
+impl Jit {
+    pub fn new(memory_size: usize) -> io::Result<Self> {
+        Ok(Jit {
+            slots: vec![Slot::NotCompiled; memory_size],
+            code: Some(MmapMut::map_anon(CODE_SIZE)?.make_exec()?),
+            code_used: 0,
+            compiled: vec![false; memory_size],
+            modified: vec![false; memory_size],
+            blocks_run: 0,
+            instructions_run: 0,
+        })
+    }
+
+    /// Runs the block at `pc` if it can be compiled and has no more than `max_instructions`, and returns the new
+    /// program counter and the instructions run.
+    pub fn run(&mut self, pc: usize, memory: &[u8], machine: Machine, max_instructions: u32) -> Option<(usize, u32)> {
+        let mut slot = *self.slots.get(pc)?;
+        if let Slot::NotCompiled = slot {
+            slot = self.compile(pc, memory);
+            self.slots[pc] = slot;
+        }
+        let (entry, instructions) = match slot {
+            Slot::Block { entry, instructions } if instructions <= max_instructions => (entry, instructions),
+            _ => return None,
+        };
+
+        // The block only accesses the machine through the pointers it is given, within the 16 registers and keys.
+        let next = unsafe {
+            entry(
+                machine.registers.as_mut_ptr(),
+                machine.index,
+                machine.delay_timer,
+                machine.sound_timer,
+                machine.keypad.as_ptr(),
+            )
+        };
+        self.blocks_run += 1;
+        self.instructions_run += instructions as u64;
+        Some((next as usize, instructions))
+    }
+
+    /// Throws away every block once the program writes over compiled code in `range`, and leaves the bytes written to
+    /// the interpreter.
+    pub fn written(&mut self, range: Range<usize>) {
+        if !self.compiled[range.clone()].contains(&true) {
+            return;
+        }
+        self.modified[range.clone()].fill(true);
+        self.clear_blocks();
+    }
+
+    /// Throws away every block, after memory changed from outside the program.
+    pub fn clear(&mut self) {
+        self.modified.fill(false);
+        self.clear_blocks();
+    }
+
+    fn clear_blocks(&mut self) {
+        self.slots.fill(Slot::NotCompiled);
+        self.compiled.fill(false);
+        self.code_used = 0;
+    }
+
+    /// Blocks of compiled code executed so far.
+    pub fn blocks_run(&self) -> u64 {
+        self.blocks_run
+    }
+
+    /// Instructions executed as compiled code so far.
+    pub fn instructions_run(&self) -> u64 {
+        self.instructions_run
+    }
+
+    fn compile(&mut self, start: usize, memory: &[u8]) -> Slot {
+        if self.code_used + MAX_BLOCK_INSTRUCTIONS * MAX_INSTRUCTION_CODE > CODE_SIZE {
+            self.clear_blocks();
+        }
+        let mut assembler = Assembler::new();
+        let mut pc = start;
+        // Addresses of the instructions in the block, which may jump around.
+        let mut addresses = Vec::new();
+        let mut ended = false;
+        while addresses.len() < MAX_BLOCK_INSTRUCTIONS && pc + 1 < memory.len() {
+            if self.modified[pc] || self.modified[pc + 1] {
+                break;
+            }
+            let opcode = (memory[pc] as u16) << 8 | memory[pc + 1] as u16;
+            let next = match assembler.instruction(opcode, pc) {
+                Compiled::Next => pc + 2,
+                Compiled::Jump(address) => address,
+                Compiled::End => {
+                    ended = true;
+                    pc
+                }
+                Compiled::Unsupported => break,
+            };
+            addresses.push(pc);
+            pc = next;
+            if ended {
+                break;
+            }
+        }
+        if addresses.is_empty() {
+            return Slot::Interpreted;
+        }
+        if !ended {
+            assembler.return_address(pc);
+        }
+
+        let entry = match self.write_code(&assembler.code) {
+            Ok(entry) => entry,
+            Err(_) => return Slot::Interpreted,
+        };
+        for &address in addresses.iter() {
+            self.compiled[address..address + 2].fill(true);
+        }
+        Slot::Block { entry, instructions: addresses.len() as u32 }
+    }
+
+    /// Appends `code` to the arena, making it writable while it is copied.
+    fn write_code(&mut self, code: &[u8]) -> io::Result<Entry> {
+        let memory = self.code.take().ok_or_else(|| io::Error::other("the code arena is not mapped"))?;
+        let mut memory = memory.make_mut()?;
+        let start = self.code_used;
+        memory[start..start + code.len()].copy_from_slice(code);
+        let memory = memory.make_exec()?;
+        self.code_used += code.len();
+        // The code was just written for this ABI, and the arena stays mapped as long as the JIT.
+        let entry = unsafe { std::mem::transmute::<*const u8, Entry>(memory.as_ptr().add(start)) };
+        self.code = Some(memory);
+        Ok(entry)
+    }
+}
+
+enum Compiled {
+    /// The block goes on with the next instruction.
+    Next,
+    /// The block goes on with the instruction at the address, without any code for the jump.
+    Jump(usize),
+    /// The instruction sets the next address and ends the block.
+    End,
+    Unsupported,
+}
+
+// Registers of the System V calling convention used by the blocks. R8, R10 and R11 are encoded as RAX, RDX and RBX
+// with a REX.B prefix.
+const RAX: u8 = 0;
+const RCX: u8 = 1;
+const RDX: u8 = 2;
+const RBX: u8 = 3;
+const RSI: u8 = 6;
+const RDI: u8 = 7;
+/// R/M value that takes the address from a SIB byte.
+const SIB: u8 = 4;
+const REX_B: u8 = 0x41;
+
+/// Emits the few x86-64 instructions the blocks need. V0-VF are addressed from RDI, I through RSI, the delay and sound
+/// timers through R10 and R11 and the keypad from R8, while AL, CL and DL hold operands and flags.
+struct Assembler {
+    code: Vec<u8>,
+}
+
+impl Assembler {
+    /// Starts a block by moving the timer pointers out of RDX and RCX, which hold operands.
+    fn new() -> Self {
+        // MOV R10, RDX and MOV R11, RCX
+        Assembler { code: vec![0x49, 0x89, 0xD2, 0x49, 0x89, 0xCB] }
+    }
+
+    /// Appends `opcode`, found at `pc`, to the block.
+    fn instruction(&mut self, opcode: u16, pc: usize) -> Compiled {
+        let x = (opcode >> 8 & 0xF) as u8;
+        let y = (opcode >> 4 & 0xF) as u8;
+        let n = (opcode & 0xF) as u8;
+        let nn = (opcode & 0xFF) as u8;
+        let nnn = (opcode & 0xFFF) as u32;
+        let next = pc as u32 + 2;
+
+        match (opcode >> 12, n) {
+            (0x1, _) => return Compiled::Jump(nnn as usize),
+            (0x3, _) | (0x4, _) => {
+                // CMP byte [RDI+X], NN
+                self.emit(&[0x80, modrm_disp8(7, RDI), x, nn]);
+                self.select_next(next, opcode >> 12 == 0x3);
+            }
+            (0x5, 0x0) | (0x9, 0x0) => {
+                self.load(RDX, x);
+                // CMP DL, byte [RDI+Y]
+                self.emit(&[0x3A, modrm_disp8(RDX, RDI), y]);
+                self.select_next(next, opcode >> 12 == 0x5);
+            }
+            (0x6, _) => self.emit(&[0xC6, modrm_disp8(0, RDI), x, nn]),
+            (0x7, _) => self.emit(&[0x80, modrm_disp8(0, RDI), x, nn]),
+            (0x8, 0x0) => {
+                self.load(RAX, y);
+                self.store(x, RAX);
+            }
+            (0x8, 0x1) | (0x8, 0x2) | (0x8, 0x3) => {
+                self.load(RAX, x);
+                self.load(RCX, y);
+                // OR, AND or XOR AL, CL
+                let operation = match n { 0x1 => 0x08, 0x2 => 0x20, _ => 0x30 };
+                self.emit(&[operation, modrm_register(RCX, RAX)]);
+                self.store(x, RAX);
+            }
+            (0x8, 0x4) => self.arithmetic(x, y, false, 0x00, 0x92),
+            (0x8, 0x5) => self.arithmetic(x, y, false, 0x28, 0x93),
+            (0x8, 0x7) => self.arithmetic(x, y, true, 0x28, 0x93),
+            (0x8, 0x6) | (0x8, 0xE) => {
+                self.load(RAX, x);
+                // SHR or SHL AL, 1, which moves the bit shifted out to the carry flag.
+                let extension = if n == 0x6 { 5 } else { 4 };
+                self.emit(&[0xD0, modrm_register(extension, RAX)]);
+                self.set_flag(0x92);
+                self.store(x, RAX);
+                self.store(0xF, RDX);
+            }
+            (0xA, _) => {
+                // MOV word [RSI], NNN
+                self.emit(&[0x66, 0xC7, modrm_indirect(0, RSI)]);
+                self.emit(&(nnn as u16).to_le_bytes());
+            }
+            (0xB, _) => {
+                self.load(RAX, 0);
+                // ADD EAX, NNN
+                self.emit(&[0x05]);
+                self.emit(&nnn.to_le_bytes());
+            }
+            (0xE, _) if nn == 0x9E || nn == 0xA1 => {
+                self.load(RAX, x);
+                // AND EAX, 0xF, then CMP byte [R8+RAX], 0
+                self.emit(&[0x83, modrm_register(4, RAX), 0x0F]);
+                self.emit(&[REX_B, 0x80, modrm_indirect(7, SIB), 0x00, 0x00]);
+                self.select_next(next, nn == 0xA1);
+            }
+            (0xF, _) if nn == 0x07 => {
+                // MOVZX EAX, byte [R10]
+                self.emit(&[REX_B, 0x0F, 0xB6, modrm_indirect(RAX, RDX)]);
+                self.store(x, RAX);
+            }
+            (0xF, _) if nn == 0x15 || nn == 0x18 => {
+                self.load(RAX, x);
+                // MOV byte [R10] or [R11], AL
+                let timer = if nn == 0x15 { RDX } else { RBX };
+                self.emit(&[REX_B, 0x88, modrm_indirect(RAX, timer)]);
+            }
+            (0xF, _) if nn == 0x1E => {
+                self.load(RAX, x);
+                // ADD word [RSI], AX
+                self.emit(&[0x66, 0x01, modrm_indirect(RAX, RSI)]);
+            }
+            _ => return Compiled::Unsupported,
+        }
+
+        match opcode >> 12 {
+            0x3 | 0x4 | 0x5 | 0x9 | 0xB | 0xE => {
+                self.emit(&[0xC3]);
+                Compiled::End
+            }
+            _ => Compiled::Next,
+        }
+    }
+
+    /// VX = VX op VY, or VY op VX when `reversed`, with VF set from the carry flag by the SETcc `flag` after VX.
+    fn arithmetic(&mut self, x: u8, y: u8, reversed: bool, operation: u8, flag: u8) {
+        let (left, right) = if reversed { (y, x) } else { (x, y) };
+        self.load(RAX, left);
+        self.load(RCX, right);
+        self.emit(&[operation, modrm_register(RCX, RAX)]);
+        self.set_flag(flag);
+        self.store(x, RAX);
+        self.store(0xF, RDX);
+    }
+
+    /// MOVZX a 32-bit register, byte [RDI+V].
+    fn load(&mut self, register: u8, v: u8) {
+        self.emit(&[0x0F, 0xB6, modrm_disp8(register, RDI), v]);
+    }
+
+    /// MOV byte [RDI+V], the low byte of a register.
+    fn store(&mut self, v: u8, register: u8) {
+        self.emit(&[0x88, modrm_disp8(register, RDI), v]);
+    }
+
+    /// SETcc DL, such as SETC or SETNC.
+    fn set_flag(&mut self, condition: u8) {
+        self.emit(&[0x0F, condition, modrm_register(0, RDX)]);
+    }
+
+    /// Returns the address after `next` when the last comparison found the operands equal and `skip_if_equal`, or when
+    /// it found them different and not `skip_if_equal`, and `next` otherwise.
+    fn select_next(&mut self, next: u32, skip_if_equal: bool) {
+        self.mov_eax_imm(next);
+        // MOV ECX, next + 2, then CMOVE or CMOVNE EAX, ECX.
+        self.emit(&[0xB9]);
+        self.emit(&(next + 2).to_le_bytes());
+        let condition = if skip_if_equal { 0x44 } else { 0x45 };
+        self.emit(&[0x0F, condition, modrm_register(RAX, RCX)]);
+    }
+
+    fn mov_eax_imm(&mut self, value: u32) {
+        self.emit(&[0xB8]);
+        self.emit(&value.to_le_bytes());
+    }
+
+    /// Ends a block that runs into an instruction it does not include.
+    fn return_address(&mut self, pc: usize) {
+        self.mov_eax_imm(pc as u32);
+        self.emit(&[0xC3]);
+    }
+
+    fn emit(&mut self, bytes: &[u8]) {
+        self.code.extend_from_slice(bytes);
+    }
+}
+
+/// ModR/M byte for `register` and the memory at `base` plus an 8-bit displacement.
+fn modrm_disp8(register: u8, base: u8) -> u8 {
+    0x40 | register << 3 | base
+}
+
+/// ModR/M byte for `register` and the memory at `base`.
+fn modrm_indirect(register: u8, base: u8) -> u8 {
+    register << 3 | base
+}
+
+/// ModR/M byte for two registers.
+fn modrm_register(register: u8, rm: u8) -> u8 {
+    0xC0 | register << 3 | rm
+}
+
+#[cfg(test)]
+#[path = "./jit_tests.rs"]
+mod jit_tests;
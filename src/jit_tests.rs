@@ -0,0 +1,129 @@
+use proptest::collection::vec;
+use proptest::prelude::*;
+
+use crate::cpu::{Register, Timing, CPU};
+use crate::framebuffer::HEIGHT;
+
+/// Instructions in each generated program, which jumps mostly stay inside.
+const PROGRAM_SIZE: usize = 64;
+
+/// Opcodes the compiler translates, with jumps inside the program.
+fn compiled_opcode() -> impl Strategy<Value = u16> {
+    let register = 0..16u16;
+    let address = (0..PROGRAM_SIZE as u16).prop_map(|instruction| 0x200 + instruction * 2);
+    prop_oneof![
+        (prop::sample::select(vec![0x3, 0x4, 0x6, 0x7]), register.clone(), any::<u8>())
+            .prop_map(|(family, x, nn)| family << 12 | x << 8 | nn as u16),
+        (prop::sample::select(vec![0x5, 0x9]), register.clone(), register.clone())
+            .prop_map(|(family, x, y)| family << 12 | x << 8 | y << 4),
+        (register.clone(), register.clone(), prop::sample::select(vec![0x0, 0x1, 0x2, 0x3, 0x4, 0x5, 0x6, 0x7, 0xE]))
+            .prop_map(|(x, y, n)| 0x8000 | x << 8 | y << 4 | n),
+        (prop::sample::select(vec![0xA, 0xB]), 0..0x100u16).prop_map(|(family, nnn)| family << 12 | nnn),
+        (prop::sample::select(vec![0x1, 0xB]), address).prop_map(|(family, nnn)| family << 12 | nnn),
+        (prop::sample::select(vec![0xE09E, 0xE0A1, 0xF007, 0xF015, 0xF018, 0xF01E]), register)
+            .prop_map(|(opcode, x)| opcode | x << 8),
+    ]
+}
+
+/// Mostly compiled opcodes, with some that end blocks, draw, or write over the program.
+fn opcode() -> impl Strategy<Value = u16> {
+    prop_oneof![
+        8 => compiled_opcode(),
+        1 => (0..16u16, 0..16u16, 1..16u16).prop_map(|(x, y, n)| 0xD000 | x << 8 | y << 4 | n),
+        1 => (0..16u16).prop_map(|x| 0xF000 | x << 8 | 0x33),
+        1 => (0..16u16).prop_map(|x| 0xF000 | x << 8 | 0x55),
+        1 => Just(0x00E0),
+    ]
+}
+
+fn keypad(keys: u16) -> [bool; 16] {
+    let mut keypad = [false; 16];
+    for (key, pressed) in keypad.iter_mut().enumerate() {
+        *pressed = keys >> key & 1 == 1;
+    }
+    keypad
+}
+
+fn new_cpus(program: &[u16], registers: &[u8; 16], index: u16) -> (CPU, CPU) {
+    let bytes: Vec<u8> = program.iter().flat_map(|opcode| opcode.to_be_bytes()).collect();
+    let mut interpreter = CPU::new();
+    let mut compiled = CPU::new();
+    compiled.enable_jit().unwrap();
+    for cpu in [&mut interpreter, &mut compiled] {
+        // Jumps into the font can run its bytes as CXNN.
+        cpu.seed_rng(0);
+        cpu.load(&bytes).unwrap();
+        for (x, &value) in registers.iter().enumerate() {
+            cpu.set_register(Register::V(x), value as u16);
+        }
+        cpu.set_register(Register::I, index);
+        cpu.set_register(Register::Delay, registers[0xA] as u16);
+        cpu.set_register(Register::Sound, registers[0xB] as u16);
+    }
+    (interpreter, compiled)
+}
+
+fn assert_same_state(interpreter: &CPU, compiled: &CPU) -> Result<(), TestCaseError> {
+    prop_assert_eq!(interpreter.snapshot(), compiled.snapshot());
+    prop_assert_eq!(interpreter.call_stack(), compiled.call_stack());
+    prop_assert!(interpreter.memory() == compiled.memory(), "memory differs");
+    for y in 0..HEIGHT {
+        prop_assert_eq!(interpreter.vram().row(y), compiled.vram().row(y), "row {}", y);
+    }
+    Ok(())
+}
+
+proptest! {
+    #[test]
+    fn test_each_instruction_matches_interpreter(
+        opcode in compiled_opcode().prop_filter("jumps to themselves loop in one block", |&opcode| opcode != 0x1200),
+        registers in prop::array::uniform16(prop_oneof![any::<u8>(), 0..4u8]),
+        index in any::<u16>(),
+        keys in any::<u16>(),
+    ) {
+        // 00E0 is not compiled, so the block holds the instruction alone.
+        let (mut interpreter, mut compiled) = new_cpus(&[opcode, 0x00E0], &registers, index);
+
+        interpreter.run_frame(keypad(keys), Timing::Instructions(1)).unwrap();
+        compiled.run_frame(keypad(keys), Timing::Instructions(1)).unwrap();
+
+        prop_assert_eq!(compiled.jit().unwrap().instructions_run(), 1);
+        assert_same_state(&interpreter, &compiled)?;
+    }
+
+    #[test]
+    fn test_programs_match_interpreter(
+        program in vec(opcode(), PROGRAM_SIZE),
+        registers in prop::array::uniform16(any::<u8>()),
+        index in 0x200..0x280u16,
+        frames in vec((1..40u32, any::<u16>()), 20),
+    ) {
+        let (mut interpreter, mut compiled) = new_cpus(&program, &registers, index);
+
+        for (instructions, keys) in frames {
+            let expected = interpreter.run_frame(keypad(keys), Timing::Instructions(instructions)).map(|_| ());
+            let actual = compiled.run_frame(keypad(keys), Timing::Instructions(instructions)).map(|_| ());
+            prop_assert_eq!(actual, expected);
+            assert_same_state(&interpreter, &compiled)?;
+            if expected.is_err() {
+                break;
+            }
+        }
+    }
+}
+
+#[test]
+fn test_self_modifying_code_falls_back_to_interpreter() {
+    let mut cpu = CPU::new();
+    cpu.enable_jit().unwrap();
+    // Adds 1 to V1, then writes 05 over the 01 of that addition with FX55 and runs it again before halting.
+    cpu.load(&[
+        0x71, 0x01, 0x40, 0x00, 0x12, 0x08, 0x12, 0x06, 0xA2, 0x01, 0x60, 0x05, 0xF0, 0x55, 0x12, 0x00,
+    ]).unwrap();
+
+    cpu.run_frame([false; 16], Timing::Instructions(20)).unwrap();
+
+    assert_eq!(cpu.register(Register::V(1)), 6);
+    assert_eq!(cpu.pc(), 0x206);
+    assert!(cpu.jit().unwrap().instructions_run() > 0);
+}
@@ -0,0 +1,128 @@
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::rom;
+use crate::rom_db::{self, RomInfo};
+
+const MAX_RECENT: usize = 5;
+
+pub struct RomEntry {
+    pub path: PathBuf,
+    pub recent: bool,
+    pub info: Option<&'static RomInfo>,
+}
+
+impl RomEntry {
+    fn new(path: PathBuf, recent: bool) -> Self {
+        let info = fs::read(&path).ok().and_then(|rom| rom_db::lookup(&rom));
+        RomEntry { path, recent, info }
+    }
+
+    pub fn name(&self) -> String {
+        match self.path.file_name() {
+            Some(name) => name.to_string_lossy().into_owned(),
+            None => self.path.to_string_lossy().into_owned(),
+        }
+    }
+}
+
+/// Menu of the recently played ROMs followed by the ROMs found in a directory.
+pub struct Launcher {
+    entries: Vec<RomEntry>,
+    selected: usize,
+}
+
+impl Launcher {
+    pub fn new(directory: &Path, recent: &[PathBuf]) -> Self {
+        let mut entries: Vec<RomEntry> = recent
+            .iter()
+            .filter(|path| path.is_file())
+            .map(|path| RomEntry::new(path.clone(), true))
+            .collect();
+
+        let mut paths: Vec<PathBuf> = fs::read_dir(directory)
+            .map(|dir| dir.filter_map(|entry| entry.ok()).map(|entry| entry.path()).collect())
+            .unwrap_or_default();
+        paths.retain(|path| is_rom(path));
+        paths.sort();
+        entries.extend(paths.into_iter().map(|path| RomEntry::new(path, false)));
+
+        Launcher {
+            entries,
+            selected: 0,
+        }
+    }
+
+    pub fn selected(&self) -> usize {
+        self.selected
+    }
+
+    pub fn selected_entry(&self) -> Option<&RomEntry> {
+        self.entries.get(self.selected)
+    }
+
+    /// One line per entry, with recently played ROMs marked.
+    pub fn lines(&self) -> Vec<String> {
+        self.entries
+            .iter()
+            .map(|entry| if entry.recent { format!("{} (recent)", entry.name()) } else { entry.name() })
+            .collect()
+    }
+
+    /// Database metadata of the selected ROM.
+    pub fn details(&self) -> Vec<String> {
+        match self.selected_entry().and_then(|entry| entry.info) {
+            Some(info) if info.year.is_empty() => vec![info.title.to_string(), format!("by {}", info.author)],
+            Some(info) => vec![info.title.to_string(), format!("by {}, {}", info.author, info.year)],
+            None if self.entries.is_empty() => vec!["No ROMs found".to_string()],
+            None => vec!["Unknown ROM".to_string()],
+        }
+    }
+
+    pub fn select_previous(&mut self) {
+        if self.selected > 0 {
+            self.selected -= 1;
+        }
+    }
+
+    pub fn select_next(&mut self) {
+        if self.selected + 1 < self.entries.len() {
+            self.selected += 1;
+        }
+    }
+}
+
+fn is_rom(path: &Path) -> bool {
+    path.is_file() && rom::is_rom_name(&path.to_string_lossy())
+}
+
+fn recent_file() -> Option<PathBuf> {
+    env::var_os("HOME").map(|home| Path::new(&home).join(".rs-chip8-recent"))
+}
+
+/// Paths of the most recently played ROMs, the latest first.
+pub fn load_recent() -> Vec<PathBuf> {
+    recent_file()
+        .and_then(|file| fs::read_to_string(file).ok())
+        .map(|contents| contents.lines().map(PathBuf::from).collect())
+        .unwrap_or_default()
+}
+
+/// Moves `path` to the top of the recently played ROMs.
+pub fn add_recent(path: &Path) {
+    let path = path.canonicalize().unwrap_or_else(|_| path.to_path_buf());
+    let mut recent = load_recent();
+    recent.retain(|existing| *existing != path);
+    recent.insert(0, path);
+    recent.truncate(MAX_RECENT);
+
+    if let Some(file) = recent_file() {
+        let contents: Vec<String> = recent.iter().map(|path| path.to_string_lossy().into_owned()).collect();
+        let _ = fs::write(file, contents.join("\n"));
+    }
+}
+
+#[cfg(test)]
+#[path = "./launcher_tests.rs"]
+mod launcher_tests;
@@ -0,0 +1,29 @@
+use super::*;
+
+#[test]
+fn test_lists_bundled_roms_with_metadata() {
+    let launcher = Launcher::new(Path::new("roms"), &[]);
+
+    let names: Vec<String> = launcher.entries.iter().map(|entry| entry.name()).collect();
+
+    assert_eq!(names, ["pong.ch8", "test1.ch8", "test_opcode.ch8", "tetris.ch8"]);
+    assert_eq!(launcher.entries[0].info.unwrap().title, "Pong");
+}
+
+#[test]
+fn test_recent_roms_come_first() {
+    let recent = [PathBuf::from("roms/tetris.ch8"), PathBuf::from("roms/missing.ch8")];
+
+    let mut launcher = Launcher::new(Path::new("roms"), &recent);
+
+    assert_eq!(launcher.entries.len(), 5);
+    assert!(launcher.entries[0].recent);
+    assert_eq!(launcher.entries[0].name(), "tetris.ch8");
+
+    launcher.select_previous();
+    assert_eq!(launcher.selected(), 0);
+    for _ in 0..10 {
+        launcher.select_next();
+    }
+    assert_eq!(launcher.selected(), 4);
+}
@@ -0,0 +1,21 @@
+//! The CHIP-8 interpreter and its tools, without any window or audio device, shared by the emulator and its tests.
+extern crate rand;
+
+pub mod analyzer;
+pub mod audio;
+pub mod cpu;
+pub mod debugger;
+pub mod disassembler;
+pub mod fonts;
+pub mod framebuffer;
+#[cfg(feature = "jit")]
+pub mod jit;
+pub mod profiler;
+pub mod quirks;
+#[cfg(test)]
+mod reference;
+pub mod rom;
+pub mod rom_db;
+pub mod symbols;
+pub mod trace;
+pub mod wav;
@@ -1,28 +1,70 @@
-extern crate rand;
 extern crate sdl2;
 mod drivers;
-mod cpu;
-mod fonts;
+mod launcher;
+mod scheduler;
+
+use rs_chip8::{analyzer, cpu, debugger, fonts, framebuffer, quirks, rom, rom_db, symbols, trace, wav};
 
 use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process;
+use std::rc::Rc;
 use std::thread;
 use std::time::Duration;
 
-use std::fs::File;
-use std::io::prelude::*;
-
 use sdl2::pixels;
 
-use drivers::DisplayDriver;
-use drivers::KeypadDriver;
+use drivers::{DisplayDriver, HeatmapDriver};
+use drivers::{Hotkey, KeypadDriver};
 use drivers::AudioDriver;
 
-use cpu::CPU;
+use analyzer::Analysis;
+use cpu::{CpuError, Reset, Timing, CPU, FRAME_RATE};
+use framebuffer::Framebuffer;
+use debugger::{DapServer, Frontend, GdbServer, SourceMap};
+use launcher::Launcher;
+use quirks::Quirks;
+use scheduler::Scheduler;
+use symbols::Symbols;
+use trace::{TraceFormat, TraceOptions, Tracer};
 
 fn main() {
     let args: Vec<String> = env::args().collect();
-    let config = parse_args(&args);
+    match args.get(1).map(String::as_str) {
+        Some("analyze") => return analyze(&args[2..]),
+        Some("info") => return info(&args[2..]),
+        _ => {}
+    }
+    let mut config = parse_args(&args);
+    let mut debugger = start_debugger(&mut config);
+
+    match config.headless_frames {
+        Some(frames) => {
+            let rom = rom::load(Path::new(&config.filepath), config.quirks.memory_size).unwrap_or_else(|error| {
+                exit_with_error(&format!("Could not load {}: {}", config.filepath, error))
+            });
+            let mut cpu = new_cpu(&config, Path::new(&config.filepath), &rom, &mut debugger);
+            let result = run_headless(&mut cpu, &config, &mut debugger, frames);
+            // The profile shows what ran up to an error, which makes it most useful then.
+            write_profile(&cpu, &config, Path::new(&config.filepath), &rom);
+            if let Err(error) = result {
+                exit_with_error(&format!("The CPU stopped: {}", error));
+            }
+        }
+        None => run(&config, &mut debugger),
+    }
+}
 
+/// How the emulation of a game ended.
+enum Exit {
+    Quit,
+    Menu,
+    /// A ROM file was dropped onto the window.
+    Load(PathBuf),
+}
+
+fn run(config: &Config, debugger: &mut Option<Box<dyn Frontend>>) {
     let sdl_context = sdl2::init().unwrap();
     let mut display_driver = DisplayDriver::new(
         &sdl_context,
@@ -30,77 +72,628 @@ fn main() {
         config.foreground_color,
     );
 
-    let mut cpu = CPU::new();
-    let mut f = File::open(config.filepath).expect("File not found");
-    let mut rom = [0u8; 3584];
-    f.read(&mut rom).unwrap();
-    cpu.load(&rom);
-
-    let audio_driver = AudioDriver::new(&sdl_context);
+    let mut audio_driver = AudioDriver::new(&sdl_context);
+    if let Some(path) = &config.wav_path {
+        audio_driver.record(path).expect("Could not create WAV file");
+    }
     let mut keypad_driver = KeypadDriver::new(&sdl_context);
+    let mut heatmap_driver = config.heatmap.then(|| HeatmapDriver::new(&sdl_context, config.quirks.memory_size));
+
+    // A directory opens the launcher on it, a file is played directly and Escape opens the launcher on its directory.
+    let filepath = Path::new(&config.filepath);
+    let (rom_directory, mut rom_path) = if filepath.is_dir() {
+        (filepath, None)
+    } else {
+        (filepath.parent().filter(|parent| parent.is_dir()).unwrap_or(Path::new(".")), Some(filepath.to_path_buf()))
+    };
+
+    loop {
+        let path = match rom_path.take() {
+            Some(path) => path,
+            None => match run_launcher(rom_directory, &mut display_driver, &mut keypad_driver) {
+                Some(path) => path,
+                None => return,
+            },
+        };
+
+        let rom = match rom::load(&path, config.quirks.memory_size) {
+            Ok(rom) => rom,
+            Err(error) => {
+                display_driver.osd().show_message(&error.to_string());
+                continue;
+            }
+        };
+        if path != Path::new(rom::STDIN_PATH) {
+            launcher::add_recent(&path);
+        }
+
+        let mut cpu = new_cpu(config, &path, &rom, debugger);
+        if let Some(heatmap_driver) = heatmap_driver.as_mut() {
+            heatmap_driver.reset();
+        }
+        let exit = emulate(
+            &mut cpu,
+            config,
+            &mut display_driver,
+            &mut audio_driver,
+            &mut keypad_driver,
+            &mut heatmap_driver,
+            debugger,
+        );
+        write_profile(&cpu, config, &path, &rom);
+        match exit {
+            Exit::Quit => return,
+            Exit::Menu => {}
+            Exit::Load(path) => rom_path = Some(path),
+        }
+    }
+}
+
+/// Shows the ROM launcher until a ROM is selected. Returns `None` if the window is closed.
+fn run_launcher(directory: &Path, display_driver: &mut DisplayDriver, keypad_driver: &mut KeypadDriver) -> Option<PathBuf> {
+    let mut launcher = Launcher::new(directory, &launcher::load_recent());
+    let mut scheduler = Scheduler::new();
+
+    while let Ok(input) = keypad_driver.poll() {
+        if input.dropped_file.is_some() {
+            return input.dropped_file;
+        }
+        for hotkey in input.hotkeys {
+            match hotkey {
+                Hotkey::MenuUp => launcher.select_previous(),
+                Hotkey::MenuDown => launcher.select_next(),
+                Hotkey::MenuSelect => {
+                    if let Some(entry) = launcher.selected_entry() {
+                        return Some(entry.path.clone());
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        display_driver.present_menu(&launcher.lines(), launcher.selected(), &launcher.details());
+        scheduler.wait();
+    }
+    None
+}
 
-    while let Ok(keypad) = keypad_driver.poll() {
-        let output = cpu.cycle(keypad);
+fn emulate(
+    cpu: &mut CPU,
+    config: &Config,
+    display_driver: &mut DisplayDriver,
+    audio_driver: &mut AudioDriver,
+    keypad_driver: &mut KeypadDriver,
+    heatmap_driver: &mut Option<HeatmapDriver>,
+    debugger: &mut Option<Box<dyn Frontend>>,
+) -> Exit {
+    let mut scheduler = Scheduler::new();
+    let mut status_bar = config.status_bar;
+    // The error that stopped the CPU, until it is reset.
+    let mut crash: Option<CpuError> = None;
+    display_driver.osd().show_message(&format!("{} quirks", config.quirk_profile));
+    // A new CPU has no dirty rows, so the texture would otherwise keep the screen of the previous game.
+    display_driver.redraw_all(cpu.vram());
 
-        if output.vram_changed {
-            display_driver.draw(&output.vram);
+    while let Ok(input) = keypad_driver.poll() {
+        if let Some(path) = input.dropped_file {
+            // Blanks the screen of this game, which the dropped ROM may fail to replace.
+            display_driver.redraw_all(&Framebuffer::new());
+            return Exit::Load(path);
+        }
+        if debugger.as_ref().is_some_and(|debugger| debugger.quit_requested()) {
+            return Exit::Quit;
+        }
+        for hotkey in input.hotkeys {
+            match hotkey {
+                Hotkey::TogglePause => scheduler.toggle_pause(),
+                Hotkey::FrameAdvance => scheduler.frame_advance(),
+                Hotkey::ToggleSlowMotion => scheduler.toggle_slow_motion(),
+                Hotkey::ToggleStatusBar => {
+                    status_bar = !status_bar;
+                    continue;
+                }
+                Hotkey::SoftReset | Hotkey::HardReset => {
+                    let (reset, message) = match hotkey {
+                        Hotkey::SoftReset => (Reset::Soft, "Soft reset"),
+                        _ => (Reset::Hard, "Hard reset"),
+                    };
+                    cpu.reset(reset);
+                    crash = None;
+                    display_driver.update(cpu.vram());
+                    display_driver.osd().show_message(message);
+                    continue;
+                }
+                Hotkey::OpenMenu => return Exit::Menu,
+                Hotkey::MenuUp | Hotkey::MenuDown | Hotkey::MenuSelect => continue,
+            }
+            display_driver.osd().show_message(&scheduler.speed_message());
         }
+        scheduler.set_fast_forward(input.fast_forward);
+        let status = match (crash, debugger.as_ref()) {
+            (Some(error), _) => format!("{}, press F5 to reset", error),
+            (None, Some(debugger)) if debugger.is_halted() => "Halted by debugger".to_string(),
+            _ => scheduler.status(),
+        };
+        display_driver.osd().set_status(&status);
+
+        if scheduler.wait() {
+            let completed = match crash {
+                Some(_) => false,
+                None => run_frame(cpu, config, debugger, input.keypad).unwrap_or_else(|error| {
+                    crash = Some(error);
+                    false
+                }),
+            };
+            if !completed {
+                // Shows what was drawn before the debugger stopped the CPU in the middle of the frame.
+                display_driver.update(cpu.vram());
+                display_driver.present();
+                continue;
+            }
+            let output = cpu.output_state();
+            let instructions = output.instructions;
+
+            if output.vram_changed {
+                display_driver.update(output.vram);
+            }
+            audio_driver.queue_frame(&output);
+            if let (Some(heatmap_driver), Some(profile)) = (heatmap_driver.as_mut(), cpu.profile()) {
+                heatmap_driver.update(profile);
+            }
+
+            let text = format!("IPF {}  FPS {:.1}  PC {:03X}", instructions, scheduler.fps(), cpu.pc());
+            display_driver.osd().set_status_bar(if status_bar { Some(text) } else { None });
+        }
+        display_driver.present();
+    }
+    Exit::Quit
+}
+
+/// Creates a CPU running `rom`, traced to the trace file if one was asked for. The symbols of the ROM at `path` are
+/// given to the tracer and the debugger.
+fn new_cpu(config: &Config, path: &Path, rom: &[u8], debugger: &mut Option<Box<dyn Frontend>>) -> CPU {
+    let symbols = Rc::new(load_symbols(&config.symbols_path, path));
+    let mut cpu = CPU::with_quirks(config.quirks);
+    cpu.load(rom).unwrap_or_else(|error| exit_with_error(&error.to_string()));
+    if let Some(seed) = config.seed {
+        cpu.seed_rng(seed);
+    }
+    if let Some(path) = &config.trace_path {
+        let mut tracer = Tracer::create(path, config.trace_options.clone())
+            .unwrap_or_else(|error| exit_with_error(&format!("Could not create {}: {}", path, error)));
+        tracer.set_symbols(symbols.clone());
+        cpu.set_tracer(tracer);
+    }
+    if let Some(debugger) = debugger {
+        debugger.set_symbols(symbols);
+    }
+    if config.profile_path.is_some() || config.listing_path.is_some() || config.heatmap {
+        cpu.enable_profiling();
+    }
+    // Debuggers stop on instructions inside blocks, and traces log every instruction.
+    if config.jit && debugger.is_none() && config.trace_path.is_none() {
+        enable_jit(&mut cpu);
+    }
+    cpu
+}
+
+#[cfg(feature = "jit")]
+fn enable_jit(cpu: &mut CPU) {
+    cpu.enable_jit().unwrap_or_else(|error| exit_with_error(&format!("Could not start the JIT: {}", error)));
+}
+
+#[cfg(not(feature = "jit"))]
+fn enable_jit(_cpu: &mut CPU) {
+    exit_with_error("--jit needs rs-chip8 built with the jit feature");
+}
+
+/// Writes the profile of the ROM at `path` to the files asked for, once the CPU stops running it.
+fn write_profile(cpu: &CPU, config: &Config, path: &Path, rom: &[u8]) {
+    let profile = match cpu.profile() {
+        Some(profile) => profile,
+        None => return,
+    };
+    if let Some(csv_path) = &config.profile_path {
+        let mut csv = vec![];
+        profile.write_csv(&mut csv).unwrap();
+        write_file(csv_path, &csv);
+    }
+    if let Some(listing_path) = &config.listing_path {
+        let symbols = load_symbols(&config.symbols_path, path);
+        let addresses = rom::PROGRAM_START..rom::PROGRAM_START + rom.len();
+        let mut listing = vec![];
+        profile.write_listing(&mut listing, cpu.memory(), addresses, &symbols).unwrap();
+        write_file(listing_path, &listing);
+    }
+}
 
-        if output.beep {
-            audio_driver.start_beep();
+/// Loads the symbol file given on the command line, or else the one next to the ROM.
+fn load_symbols(symbols_path: &Option<String>, rom_path: &Path) -> Symbols {
+    match symbols_path {
+        Some(path) => Symbols::load(Path::new(path))
+            .unwrap_or_else(|error| exit_with_error(&format!("Could not load {}: {}", path, error))),
+        None => Symbols::load_for_rom(rom_path).unwrap_or_default(),
+    }
+}
+
+/// Writes the control-flow graph of a ROM as Graphviz DOT and what its static analysis found as JSON, to standard
+/// output unless a file is given.
+fn analyze(args: &[String]) {
+    let mut dot_path = None;
+    let mut json_path = None;
+    let mut symbols_path = None;
+    let mut rom_path = None;
+
+    let mut iter = args.iter();
+    while let Some(arg) = iter.next() {
+        match arg.as_str() {
+            "--dot" => dot_path = iter.next().cloned(),
+            "--json" => json_path = iter.next().cloned(),
+            "--symbols" => symbols_path = iter.next().cloned(),
+            "--help" | "-h" => {
+                print_analyze_help();
+                process::exit(0);
+            }
+            _ => rom_path = Some(arg.clone()),
+        }
+    }
+
+    let rom_path = rom_path.unwrap_or_else(|| {
+        print_analyze_help();
+        process::exit(1);
+    });
+    // Any ROM that fits in the memory of some platform can be analysed.
+    let rom = rom::load(Path::new(&rom_path), Quirks::xochip().memory_size).unwrap_or_else(|error| {
+        exit_with_error(&format!("Could not load {}: {}", rom_path, error))
+    });
+    let symbols = load_symbols(&symbols_path, Path::new(&rom_path));
+    let analysis = Analysis::new(&rom);
+
+    let report = serde_json::to_string_pretty(&analysis.to_json(&symbols)).unwrap();
+    match &json_path {
+        Some(path) => write_file(path, &report),
+        None => println!("{}", report),
+    }
+    if let Some(path) = &dot_path {
+        write_file(path, analysis.to_dot(&symbols));
+    }
+}
+
+/// Prints what is known about a ROM without running it: its size and hashes, its entry in the ROM database, the
+/// platform its instructions belong to, the keys it reads and whether it fits in the memory of CHIP-8.
+fn info(args: &[String]) {
+    let rom_path = match args {
+        [path] if path != "--help" && path != "-h" => path,
+        _ => {
+            println!("Usage: rs-chip8 info FILEPATH_TO_ROM");
+            process::exit(if args.is_empty() { 1 } else { 0 });
+        }
+    };
+    let rom = rom::load(Path::new(rom_path), Quirks::xochip().memory_size).unwrap_or_else(|error| {
+        exit_with_error(&format!("Could not load {}: {}", rom_path, error))
+    });
+    let analysis = Analysis::new(&rom);
+
+    println!("Size:         {} bytes", rom.len());
+    println!("SHA-1:        {}", rom_db::sha1_hex(&rom));
+    println!("CRC-32:       {:08x}", rom_db::crc32(&rom));
+    if let Some(info) = rom_db::lookup(&rom) {
+        println!("Title:        {}", info.title);
+        println!("Author:       {}", info.author);
+        if !info.year.is_empty() {
+            println!("Year:         {}", info.year);
+        }
+        println!("Written for:  {}", info.platform);
+    }
+    println!("Platform:     {}, from the instructions it runs", analysis.platform());
+
+    let keys = analysis.keys();
+    let mut read = vec![];
+    if !keys.tested.is_empty() {
+        read.push(hex_list(keys.tested.iter().map(|&key| key as usize)));
+    }
+    if !keys.computed.is_empty() {
+        read.push(format!("keys in registers at {}", hex_list(keys.computed)));
+    }
+    if !keys.waits.is_empty() {
+        read.push(format!("any key at {}", hex_list(keys.waits)));
+    }
+    println!("Keys:         {}", if read.is_empty() { "none".to_string() } else { read.join(", ") });
+
+    let max_size = rom::max_size(Quirks::chip8().memory_size);
+    if rom.len() <= max_size {
+        println!("Fits:         {} of the {} bytes above 0x200", rom.len(), max_size);
+    } else {
+        println!("Fits:         no, only {} bytes above 0x200 without --quirks xochip", max_size);
+    }
+}
+
+fn hex_list(values: impl IntoIterator<Item = usize>) -> String {
+    values.into_iter().map(|value| format!("{:X}", value)).collect::<Vec<_>>().join(" ")
+}
+
+fn write_file(path: &str, contents: impl AsRef<[u8]>) {
+    fs::write(path, contents).unwrap_or_else(|error| exit_with_error(&format!("Could not write {}: {}", path, error)));
+}
+
+fn print_analyze_help() {
+    println!("Usage: rs-chip8 analyze [OPTIONS] FILEPATH_TO_ROM");
+    println!("Follows every path through the rom without running it and reports its subroutines, unreachable bytes,");
+    println!("computed jumps (BNNN), writes over its own code and the instructions interpreters disagree on.");
+    println!("The report is printed as JSON unless --json is given.");
+    println!();
+    println!("Options:");
+    println!("  --json FILE        Writes the report to FILE");
+    println!("  --dot FILE         Writes the control-flow graph to FILE in the Graphviz DOT language");
+    println!("  --symbols FILE     Names addresses, instead of the .sym file next to the ROM");
+    println!();
+}
+
+fn exit_with_error(error: &str) -> ! {
+    eprintln!("{}", error);
+    process::exit(1);
+}
+
+/// Runs a fixed number of frames as fast as possible without opening a window or an audio device. Stops early with
+/// the error of the CPU if it fails.
+fn run_headless(
+    cpu: &mut CPU,
+    config: &Config,
+    debugger: &mut Option<Box<dyn Frontend>>,
+    frames: u32,
+) -> Result<(), CpuError> {
+    let mut audio_driver = AudioDriver::headless();
+    if let Some(path) = &config.wav_path {
+        audio_driver.record(path).expect("Could not create WAV file");
+    }
+
+    let mut completed = 0;
+    while completed < frames && !debugger.as_ref().is_some_and(|debugger| debugger.quit_requested()) {
+        if run_frame(cpu, config, debugger, [false; 16])? {
+            audio_driver.queue_frame(&cpu.output_state());
+            completed += 1;
         } else {
-            audio_driver.stop_beep();
+            // Waits for the debugger without spinning.
+            thread::sleep(Duration::from_millis(1));
+        }
+    }
+    Ok(())
+}
+
+/// Runs a frame, under the control of the debugger if there is one. Returns whether the frame completed. Errors of
+/// the CPU are left to the debugger, which stops and reports them to its client.
+fn run_frame(
+    cpu: &mut CPU,
+    config: &Config,
+    debugger: &mut Option<Box<dyn Frontend>>,
+    keypad: [bool; 16],
+) -> Result<bool, CpuError> {
+    match debugger {
+        Some(debugger) => Ok(debugger.run_frame(cpu, keypad, config.timing)),
+        None => cpu.run_frame(keypad, config.timing).map(|_| true),
+    }
+}
+
+/// Starts the debugger server asked for on the command line. A DAP client names the ROM to debug, replacing the one
+/// given on the command line.
+fn start_debugger(config: &mut Config) -> Option<Box<dyn Frontend>> {
+    if config.dap {
+        let mut dap_server = DapServer::stdio();
+        let launch = dap_server.wait_for_launch().unwrap_or_else(|| process::exit(0));
+        if let Some(program) = launch.program {
+            config.filepath = program;
         }
 
-        thread::sleep(Duration::from_millis(config.frame_delay));
+        let source_map = launch.source_map.or_else(|| config.source_map_path.clone());
+        let result = rom::load(Path::new(&config.filepath), config.quirks.memory_size)
+            .map_err(|error| format!("Could not load {}: {}", config.filepath, error))
+            .and_then(|_| match &source_map {
+                Some(path) => SourceMap::load(Path::new(path))
+                    .map(|source_map| dap_server.set_source_map(source_map))
+                    .map_err(|error| format!("Could not load {}: {}", path, error)),
+                None => Ok(()),
+            });
+        if let Err(error) = &result {
+            dap_server.finish_launch(result.clone());
+            exit_with_error(error);
+        }
+        dap_server.finish_launch(result);
+        return Some(Box::new(dap_server));
     }
+
+    let port = config.gdb_port?;
+    let gdb_server = GdbServer::bind(port)
+        .unwrap_or_else(|error| exit_with_error(&format!("Could not listen on port {}: {}", port, error)));
+    println!("Waiting for GDB on localhost:{}", gdb_server.port());
+    Some(Box::new(gdb_server))
 }
 
+/// Converts the delay between instructions given on the command line into a number of instructions per frame.
+fn instructions_per_frame(frame_delay: u64) -> u32 {
+    let frame_millis = 1000 / FRAME_RATE as u64;
+    (frame_millis / frame_delay.max(1)).max(1) as u32
+}
+
+const DEFAULT_ROM_DIRECTORY: &str = "roms";
+const DEFAULT_FRAME_DELAY: u64 = 3;
+
 pub struct Config {
     pub filepath: String,
-    pub frame_delay: u64,
+    pub timing: Timing,
+    pub quirks: Quirks,
+    pub quirk_profile: String,
+    pub status_bar: bool,
     pub foreground_color: sdl2::pixels::Color,
     pub background_color: sdl2::pixels::Color,
+    pub wav_path: Option<String>,
+    pub headless_frames: Option<u32>,
+    pub seed: Option<u64>,
+    pub trace_path: Option<String>,
+    pub trace_options: TraceOptions,
+    pub gdb_port: Option<u16>,
+    pub dap: bool,
+    pub source_map_path: Option<String>,
+    pub symbols_path: Option<String>,
+    pub jit: bool,
+    pub profile_path: Option<String>,
+    pub listing_path: Option<String>,
+    pub heatmap: bool,
 }
 
 fn parse_args(args: &[String]) -> Config{
-    if args.len() < 3
-    {
-        print_help();
-        panic!("Wrong number of arguments.");
+    let mut wav_path = None;
+    let mut headless_frames = None;
+    let mut seed = None;
+    let mut trace_path = None;
+    let mut trace_options = TraceOptions::default();
+    let mut gdb_port = None;
+    let mut dap = false;
+    let mut source_map_path = None;
+    let mut symbols_path = None;
+    let mut jit = false;
+    let mut profile_path = None;
+    let mut listing_path = None;
+    let mut heatmap = false;
+    let mut vip_timing = false;
+    let mut quirks = Quirks::default();
+    let mut quirk_profile = "chip8".to_string();
+    let mut status_bar = false;
+    let mut wrap_sprites = false;
+    let mut positional = Vec::new();
+
+    let mut iter = args.iter().skip(1);
+    while let Some(arg) = iter.next() {
+        match arg.as_str() {
+            "--wav" => wav_path = iter.next().cloned(),
+            "--headless" => headless_frames = iter.next().map(|frames| frames.parse::<u32>().unwrap()),
+            "--seed" => seed = iter.next().map(|seed| seed.parse::<u64>().unwrap()),
+            "--trace" => trace_path = iter.next().cloned(),
+            "--trace-format" => {
+                let name = iter.next().map(String::as_str).unwrap_or("");
+                trace_options.format = TraceFormat::from_name(name).expect("Unknown trace format");
+            }
+            "--trace-range" => {
+                let range = iter.next().map(String::as_str).unwrap_or("");
+                trace_options.addresses = Some(trace::parse_addresses(range).expect("Invalid address range"));
+            }
+            "--trace-ops" => {
+                let classes = iter.next().map(String::as_str).unwrap_or("");
+                trace_options.classes = Some(trace::parse_classes(classes).expect("Invalid instruction classes"));
+            }
+            "--trace-limit" => {
+                trace_options.max_size = iter.next().map(|megabytes| megabytes.parse::<u64>().unwrap()).unwrap() * 1024 * 1024;
+            }
+            "--gdb" => gdb_port = iter.next().map(|port| port.parse::<u16>().unwrap()),
+            "--dap" => dap = true,
+            "--source-map" => source_map_path = iter.next().cloned(),
+            "--symbols" => symbols_path = iter.next().cloned(),
+            "--jit" => jit = true,
+            "--profile" => profile_path = iter.next().cloned(),
+            "--listing" => listing_path = iter.next().cloned(),
+            "--heatmap" => heatmap = true,
+            "--vip-timing" => vip_timing = true,
+            "--quirks" => {
+                let name = iter.next().map(String::as_str).unwrap_or("");
+                quirks = Quirks::from_name(name).expect("Unknown quirk profile");
+                quirk_profile = name.to_string();
+            }
+            "--wrap-sprites" => wrap_sprites = true,
+            "--status-bar" => status_bar = true,
+            "--help" | "-h" => {
+                print_help();
+                process::exit(0);
+            }
+            _ => positional.push(arg.clone()),
+        }
     }
 
-    let filepath = args[1].clone();
-    let frame_delay = args[2].parse::<u64>().unwrap();
+    quirks.sprite_wrap |= wrap_sprites;
+
+    let filepath = positional.first().cloned().unwrap_or_else(|| DEFAULT_ROM_DIRECTORY.to_string());
+    let frame_delay = positional.get(1).map(|delay| delay.parse::<u64>().unwrap()).unwrap_or(DEFAULT_FRAME_DELAY);
+    let timing = if vip_timing {
+        Timing::Vip
+    } else {
+        Timing::Instructions(instructions_per_frame(frame_delay))
+    };
     let mut foreground_color = pixels::Color::RGB(200, 200, 200);
     let mut background_color = pixels::Color::RGB(0, 0, 0);
 
-    if args.len() >= 6 {
+    if positional.len() >= 5 {
         foreground_color = pixels::Color::RGB(
-            args[3].parse::<u8>().unwrap(),
-            args[4].parse::<u8>().unwrap(),
-            args[5].parse::<u8>().unwrap());
+            positional[2].parse::<u8>().unwrap(),
+            positional[3].parse::<u8>().unwrap(),
+            positional[4].parse::<u8>().unwrap());
     }
-    if args.len() >= 9 {
+    if positional.len() >= 8 {
         background_color = pixels::Color::RGB(
-            args[6].parse::<u8>().unwrap(),
-            args[7].parse::<u8>().unwrap(),
-            args[8].parse::<u8>().unwrap());
+            positional[5].parse::<u8>().unwrap(),
+            positional[6].parse::<u8>().unwrap(),
+            positional[7].parse::<u8>().unwrap());
     }
 
     Config {
         filepath,
-        frame_delay,
+        timing,
+        quirks,
+        quirk_profile,
+        status_bar,
         foreground_color,
         background_color,
+        wav_path,
+        headless_frames,
+        seed,
+        trace_path,
+        trace_options,
+        gdb_port,
+        dap,
+        source_map_path,
+        symbols_path,
+        jit,
+        profile_path,
+        listing_path,
+        heatmap,
     }
 }
 
 fn print_help() {
-    println!("Usage: rs-chip8 FILEPATH_TO_ROM FRAME_DELAY [FOREGROUND_COLOR] [BACKGROUND_COLOR]");
-    println!("Emulates the rom in FILEPATH_TO_ROM with a delay between frames in miliseconds of FRAME_DELAY");
-    println!("");
+    println!("Usage: rs-chip8 [OPTIONS] [FILEPATH_TO_ROM] [FRAME_DELAY] [FOREGROUND_COLOR] [BACKGROUND_COLOR]");
+    println!("       rs-chip8 analyze [OPTIONS] FILEPATH_TO_ROM");
+    println!("       rs-chip8 info FILEPATH_TO_ROM");
+    println!("Emulates the rom in FILEPATH_TO_ROM running one instruction every FRAME_DELAY miliseconds (3 by default).");
+    println!("The delay and sound timers always count down at 60 Hz.");
+    println!();
+    println!("FILEPATH_TO_ROM can also be a zip archive, ARCHIVE.zip:NAME to pick a ROM in it, or - for standard input.");
+    println!("If FILEPATH_TO_ROM is a directory, or is left out to use ./roms, a launcher lists the ROMs in it.");
+    println!("Pressing Escape while playing returns to the launcher, and dropping a file on the window plays it.");
+    println!();
     println!("Colors are encoded as three RGB numbers with no delimitation.");
     println!("Example: rs-chip8 ./roms/pong.ch8 5 255 255 255 0 0 0");
-    println!("");
+    println!();
+    println!("Hotkeys: hold Tab to fast-forward, M toggles slow motion, P pauses and N advances one frame.");
+    println!("I toggles a status bar with the instructions per frame, frames per second and program counter.");
+    println!("F5 restarts the game keeping RAM with the chip8 quirks (soft reset), F6 also clears RAM and reloads the ROM (hard reset).");
+    println!();
+    println!("Options:");
+    println!("  --wav FILE         Records the emulated audio to a WAV file");
+    println!("  --headless FRAMES  Runs FRAMES frames without a window or audio device");
+    println!("  --seed N           Seeds the random numbers of CXNN so that runs can be repeated");
+    println!("  --trace FILE       Logs every executed instruction to FILE");
+    println!("  --trace-format F   Logs the changed registers (changes, default) or all of them (state)");
+    println!("  --trace-range A-B  Only logs instructions at the hexadecimal addresses A to B");
+    println!("  --trace-ops LIST   Only logs instructions starting with the hexadecimal digits in LIST, e.g. 1,2,D");
+    println!("  --trace-limit MB   Stops the trace at MB megabytes (64 by default)");
+    println!("  --gdb PORT         Waits for a GDB remote protocol client on localhost:PORT before running");
+    println!("  --dap              Serves the Debug Adapter Protocol on standard input and output");
+    println!("  --source-map FILE  Maps the ROM to its source lines for --dap, unless the client names a map");
+    println!("  --symbols FILE     Names addresses in traces and debuggers, instead of the .sym file next to the ROM");
+    println!("  --jit              Runs blocks of code as native x86-64 code, with the jit feature and without --vip-timing");
+    println!("  --profile FILE     Writes the runs and cycles of each address and the reads and writes of each byte as CSV");
+    println!("  --listing FILE     Writes a disassembly of the ROM with the profile of each line and its hot spots");
+    println!("  --heatmap          Shows the use of each byte of memory in a second window");
+    println!("  --vip-timing       Runs at the speed of the COSMAC VIP instead of using FRAME_DELAY");
+    println!("  --quirks PROFILE   Emulates the quirks of chip8 (default), schip or xochip interpreters");
+    println!("  --wrap-sprites     Wraps sprites around the screen edges instead of clipping them");
+    println!("  --status-bar       Shows the status bar from the start");
+    println!();
 }
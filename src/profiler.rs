@@ -0,0 +1,225 @@
+use std::io::{self, Write};
+use std::ops::Range;
+
+use crate::cpu::VIP_CYCLES_PER_FRAME;
+use crate::disassembler::disassemble_with_symbols;
+use crate::symbols::Symbols;
+
+/// Instructions listed as hot spots at the top of the listing.
+const HOT_SPOTS: usize = 10;
+
+/// Share of its brightness a byte of the heatmap keeps each frame after it was last used.
+const HEATMAP_DECAY: f32 = 0.92;
+
+/// Counts, for each address, the instructions run there with their COSMAC VIP machine cycles, and the reads and
+/// writes of each byte of memory by instructions. Instructions being fetched are not counted as reads.
+pub struct Profile {
+    executions: Vec<u64>,
+    cycles: Vec<u64>,
+    reads: Vec<u64>,
+    writes: Vec<u64>,
+    frames: u64,
+    frame_cycles: u64,
+    max_frame_cycles: u64,
+}
+
+impl Profile {
+    pub fn new(memory_size: usize) -> Self {
+        Profile {
+            executions: vec![0; memory_size],
+            cycles: vec![0; memory_size],
+            reads: vec![0; memory_size],
+            writes: vec![0; memory_size],
+            frames: 0,
+            frame_cycles: 0,
+            max_frame_cycles: 0,
+        }
+    }
+
+    pub(crate) fn executed(&mut self, address: usize, cycles: u32) {
+        self.executions[address] += 1;
+        self.cycles[address] += cycles as u64;
+        self.frame_cycles += cycles as u64;
+    }
+
+    pub(crate) fn read(&mut self, range: Range<usize>) {
+        self.reads[range].iter_mut().for_each(|reads| *reads += 1);
+    }
+
+    pub(crate) fn written(&mut self, range: Range<usize>) {
+        self.writes[range].iter_mut().for_each(|writes| *writes += 1);
+    }
+
+    pub(crate) fn end_frame(&mut self) {
+        self.frames += 1;
+        self.max_frame_cycles = self.max_frame_cycles.max(self.frame_cycles);
+        self.frame_cycles = 0;
+    }
+
+    /// Instructions run at each address.
+    pub fn executions(&self) -> &[u64] {
+        &self.executions
+    }
+
+    /// COSMAC VIP machine cycles taken by the instructions at each address.
+    pub fn cycles(&self) -> &[u64] {
+        &self.cycles
+    }
+
+    /// Reads of each byte by instructions.
+    pub fn reads(&self) -> &[u64] {
+        &self.reads
+    }
+
+    /// Writes to each byte by instructions.
+    pub fn writes(&self) -> &[u64] {
+        &self.writes
+    }
+
+    pub fn frames(&self) -> u64 {
+        self.frames
+    }
+
+    /// Writes a line for each address that ran instructions or was read or written, with the address in hexadecimal.
+    pub fn write_csv(&self, writer: &mut dyn Write) -> io::Result<()> {
+        writeln!(writer, "address,executions,cycles,reads,writes")?;
+        for address in 0..self.executions.len() {
+            let counts = [self.executions[address], self.cycles[address], self.reads[address], self.writes[address]];
+            if counts.iter().any(|&count| count > 0) {
+                writeln!(writer, "0x{:03X},{},{},{},{}", address, counts[0], counts[1], counts[2], counts[3])?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Writes the disassembly of the `addresses` of `memory`, with the runs, share of the cycles, reads and writes of
+    /// each line. A summary with the cycles per frame and the instructions that took the most cycles comes first.
+    /// Bytes that never ran are listed as data.
+    pub fn write_listing(
+        &self,
+        writer: &mut dyn Write,
+        memory: &[u8],
+        addresses: Range<usize>,
+        symbols: &Symbols,
+    ) -> io::Result<()> {
+        let total_cycles: u64 = self.cycles.iter().sum();
+        let share = |cycles: u64| if total_cycles == 0 { 0.0 } else { cycles as f64 * 100.0 / total_cycles as f64 };
+        let instruction = |address: usize| {
+            let opcode = u16::from_be_bytes([memory[address], memory.get(address + 1).copied().unwrap_or(0)]);
+            disassemble_with_symbols(opcode, symbols)
+        };
+
+        writeln!(
+            writer,
+            "; {} frames, {} instructions, {} COSMAC VIP cycles",
+            self.frames,
+            self.executions.iter().sum::<u64>(),
+            total_cycles,
+        )?;
+        if let Some(average) = total_cycles.checked_div(self.frames) {
+            writeln!(
+                writer,
+                "; Cycles per frame: {} on average and {} at most, of the {} of a COSMAC VIP frame",
+                average,
+                self.max_frame_cycles,
+                VIP_CYCLES_PER_FRAME,
+            )?;
+        }
+
+        let mut hot_spots: Vec<usize> = (0..self.cycles.len()).filter(|&address| self.cycles[address] > 0).collect();
+        hot_spots.sort_by_key(|&address| std::cmp::Reverse(self.cycles[address]));
+        if !hot_spots.is_empty() {
+            writeln!(writer, ";\n; Hot spots:")?;
+        }
+        for &address in hot_spots.iter().take(HOT_SPOTS) {
+            let name = symbols.describe(address);
+            writeln!(writer, ";   {:5.1}%  {:<16} {}", share(self.cycles[address]), name, instruction(address))?;
+        }
+
+        writeln!(writer, "\n{:>10} {:>6} {:>8} {:>8}", "Runs", "Cycles", "Reads", "Writes")?;
+        let mut address = addresses.start;
+        while address < addresses.end {
+            // Code running at odd addresses splits the line before it.
+            let length = match self.executions.get(address + 1) {
+                Some(&runs) if runs > 0 && self.executions[address] == 0 => 1,
+                _ => 2.min(addresses.end - address),
+            };
+            let line = address..address + length;
+            let runs = self.executions[address];
+            let reads: u64 = self.reads[line.clone()].iter().sum();
+            let writes: u64 = self.writes[line.clone()].iter().sum();
+            if let Some(name) = symbols.name(address) {
+                writeln!(writer, "{}:", name)?;
+            }
+            let text = match runs {
+                0 => memory[line].iter().map(|byte| format!("0x{:02X}", byte)).collect::<Vec<_>>().join(" "),
+                _ => instruction(address),
+            };
+            let count = |count: u64| if count == 0 { String::new() } else { count.to_string() };
+            let cycles = match runs {
+                0 => String::new(),
+                _ => format!("{:.1}%", share(self.cycles[address])),
+            };
+            writeln!(
+                writer,
+                "{:>10} {:>6} {:>8} {:>8}  {:03X}  {}",
+                count(runs),
+                cycles,
+                count(reads),
+                count(writes),
+                address,
+                text,
+            )?;
+            address += length;
+        }
+        Ok(())
+    }
+}
+
+/// The brightness of each byte of memory, red while instructions run there, green while they read it and blue while
+/// they write it, which fades once it is no longer used.
+pub struct Heatmap {
+    /// Runs, reads and writes of each byte at the last update.
+    previous: [Vec<u64>; 3],
+    /// Brightness of the red, green and blue of each byte, from 0 to 1.
+    heat: Vec<[f32; 3]>,
+}
+
+impl Heatmap {
+    pub fn new(memory_size: usize) -> Self {
+        Heatmap {
+            previous: [vec![0; memory_size], vec![0; memory_size], vec![0; memory_size]],
+            heat: vec![[0.0; 3]; memory_size],
+        }
+    }
+
+    /// Forgets the counts and brightness of the last profile, before updating with a new one.
+    pub fn reset(&mut self) {
+        self.previous.iter_mut().for_each(|counts| counts.fill(0));
+        self.heat.fill([0.0; 3]);
+    }
+
+    /// Lights up the bytes used since the last update and fades the others.
+    pub fn update(&mut self, profile: &Profile) {
+        let counts = [profile.executions(), profile.reads(), profile.writes()];
+        for (channel, counts) in counts.iter().enumerate() {
+            for (address, &count) in counts.iter().enumerate() {
+                let uses = count - self.previous[channel][address];
+                let heat = &mut self.heat[address][channel];
+                // On a logarithmic scale, so that bytes used once in a frame still show next to busy loops.
+                let brightness = if uses > 0 { (0.4 + (uses as f32).log2() / 16.0).min(1.0) } else { 0.0 };
+                *heat = (*heat * HEATMAP_DECAY).max(brightness);
+            }
+            self.previous[channel].copy_from_slice(counts);
+        }
+    }
+
+    /// Brightness of the red, green and blue of each byte.
+    pub fn heat(&self) -> &[[f32; 3]] {
+        &self.heat
+    }
+}
+
+#[cfg(test)]
+#[path = "./profiler_tests.rs"]
+mod profiler_tests;
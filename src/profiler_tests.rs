@@ -0,0 +1,116 @@
+use super::*;
+use crate::cpu::{Timing, CPU};
+
+/// Draws a row of the byte at 210, writes V1 there in BCD, loads it back and adds 1 to V0, in a loop of 6 instructions.
+const PROGRAM: [u8; 20] = [
+    0xA2, 0x10, 0xD0, 0x11, 0xF1, 0x33, 0xF1, 0x65, 0x70, 0x01, 0x12, 0x00, 0x00, 0x00, 0x00, 0x00, 0x80, 0x00, 0x00, 0x00,
+];
+
+fn profiled_cpu() -> CPU {
+    let mut cpu = CPU::new();
+    cpu.load(&PROGRAM).unwrap();
+    cpu.enable_profiling();
+    for _ in 0..2 {
+        cpu.run_frame([false; 16], Timing::Instructions(12)).unwrap();
+    }
+    cpu
+}
+
+#[test]
+fn test_counts_instructions_and_memory_accesses() {
+    let cpu = profiled_cpu();
+    let profile = cpu.profile().unwrap();
+
+    assert_eq!(profile.frames(), 2);
+    assert_eq!(&profile.executions()[0x200..0x20E], [4, 0, 4, 0, 4, 0, 4, 0, 4, 0, 4, 0, 0, 0]);
+    assert_eq!(profile.cycles()[0x200], 4 * 12);
+    // Read by DXYN and FX65, and only FX65 reads the bytes after it.
+    assert_eq!(&profile.reads()[0x210..0x214], [8, 4, 0, 0]);
+    assert_eq!(&profile.writes()[0x210..0x214], [4, 4, 4, 0]);
+    assert_eq!(profile.reads()[0x200], 0);
+}
+
+#[test]
+fn test_writes_csv() {
+    let cpu = profiled_cpu();
+    let mut csv = vec![];
+
+    cpu.profile().unwrap().write_csv(&mut csv).unwrap();
+
+    let csv = String::from_utf8(csv).unwrap();
+    let lines: Vec<&str> = csv.lines().collect();
+    assert_eq!(lines[0], "address,executions,cycles,reads,writes");
+    assert_eq!(lines[1], "0x200,4,48,0,0");
+    assert_eq!(lines.len(), 1 + 6 + 3);
+    assert_eq!(lines[9], "0x212,0,0,0,4");
+}
+
+#[test]
+fn test_writes_listing_with_hot_spots() {
+    let cpu = profiled_cpu();
+    let symbols = Symbols::parse("200 main\n210 digits\n").unwrap();
+    let mut listing = vec![];
+
+    cpu.profile().unwrap().write_listing(&mut listing, cpu.memory(), 0x200..0x214, &symbols).unwrap();
+
+    let listing = String::from_utf8(listing).unwrap();
+    assert!(listing.starts_with("; 2 frames, 24 instructions, 868 COSMAC VIP cycles\n"));
+    assert!(listing.contains("; Cycles per frame: 434 on average and 440 at most, of the 3668 of a COSMAC VIP frame\n"));
+    assert!(listing.contains(";\n; Hot spots:\n"));
+    assert!(listing.contains("main:\n         4   5.5%                    200  i := digits\n"));
+    assert!(listing.contains(";    36.9%  main+0x4         bcd v1\n"));
+    assert!(listing.contains("         4  26.3%                    202  sprite v0 v1 1\n"));
+    // Bytes that never ran are data, read and written by the loop.
+    assert!(listing.contains("                                     20C  0x00 0x00\n"));
+    assert!(listing.contains("digits:\n                        12        8  210  0x00 0x00\n"));
+    assert!(listing.contains("                                  4  212  0x00 0x00\n"));
+}
+
+#[test]
+fn test_heatmap_lights_up_bytes_used_since_the_last_update() {
+    let cpu = profiled_cpu();
+    let mut heatmap = Heatmap::new(cpu.memory().len());
+
+    heatmap.update(cpu.profile().unwrap());
+
+    // Four runs of the loop, each reading the byte at 210 twice and writing it once.
+    assert_eq!(heatmap.heat()[0x200], [0.4 + 2.0 / 16.0, 0.0, 0.0]);
+    assert_eq!(heatmap.heat()[0x210], [0.0, 0.4 + 3.0 / 16.0, 0.4 + 2.0 / 16.0]);
+    assert_eq!(heatmap.heat()[0x20C], [0.0; 3]);
+
+    // Nothing ran since.
+    heatmap.update(cpu.profile().unwrap());
+
+    assert_eq!(heatmap.heat()[0x200], [(0.4 + 2.0 / 16.0) * HEATMAP_DECAY, 0.0, 0.0]);
+}
+
+#[test]
+fn test_heatmap_starts_over_with_a_new_profile() {
+    let used = profiled_cpu();
+    let mut heatmap = Heatmap::new(used.memory().len());
+    heatmap.update(used.profile().unwrap());
+
+    // A new CPU has counted fewer runs than the last one.
+    let mut fresh = CPU::new();
+    fresh.load(&PROGRAM).unwrap();
+    fresh.enable_profiling();
+    fresh.run_frame([false; 16], Timing::Instructions(6)).unwrap();
+    heatmap.reset();
+    heatmap.update(fresh.profile().unwrap());
+
+    assert_eq!(heatmap.heat()[0x200], [0.4, 0.0, 0.0]);
+    assert_eq!(heatmap.heat()[0x210], [0.0, 0.4 + 1.0 / 16.0, 0.4]);
+}
+
+#[cfg(feature = "jit")]
+#[test]
+fn test_profiling_counts_every_instruction_with_the_jit() {
+    let mut cpu = CPU::new();
+    cpu.load(&PROGRAM).unwrap();
+    cpu.enable_jit().unwrap();
+    cpu.enable_profiling();
+
+    cpu.run_frame([false; 16], Timing::Instructions(12)).unwrap();
+
+    assert_eq!(cpu.profile().unwrap().executions()[0x208], 2);
+}
@@ -0,0 +1,80 @@
+/// How DXYN reports sprite collisions in VF.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Collision {
+    /// VF is set to 1 if any pixel was turned off.
+    Flag,
+    /// VF is set to the number of sprite rows that turned off a pixel or were clipped at the bottom edge, as in SUPER-CHIP.
+    RowCount,
+}
+
+/// Behaviours that differ between CHIP-8 interpreters.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Quirks {
+    /// FX0A stores the key once it is released instead of as soon as it is pressed, beeping while it is held.
+    pub key_release: bool,
+    /// Sprites crossing a screen edge wrap around to the opposite edge instead of being clipped.
+    pub sprite_wrap: bool,
+    pub collision: Collision,
+    /// A soft reset keeps RAM as the program left it, as the reset switch of the COSMAC VIP does. Otherwise it also
+    /// clears RAM and loads the ROM again.
+    pub keep_ram_on_reset: bool,
+    /// Bytes of RAM, including the interpreter area below 0x200.
+    pub memory_size: usize,
+}
+
+impl Quirks {
+    /// The original COSMAC VIP interpreter.
+    pub fn chip8() -> Self {
+        Quirks {
+            key_release: true,
+            sprite_wrap: false,
+            collision: Collision::Flag,
+            keep_ram_on_reset: true,
+            memory_size: 4096,
+        }
+    }
+
+    /// SUPER-CHIP 1.1 on the HP 48.
+    pub fn schip() -> Self {
+        Quirks {
+            key_release: false,
+            sprite_wrap: false,
+            collision: Collision::RowCount,
+            keep_ram_on_reset: false,
+            memory_size: 4096,
+        }
+    }
+
+    /// XO-CHIP as implemented by Octo.
+    pub fn xochip() -> Self {
+        Quirks {
+            key_release: true,
+            sprite_wrap: true,
+            collision: Collision::Flag,
+            keep_ram_on_reset: false,
+            memory_size: 65536,
+        }
+    }
+
+    /// All quirk profiles with the names used to select them.
+    pub fn profiles() -> [(&'static str, Quirks); 3] {
+        [
+            ("chip8", Quirks::chip8()),
+            ("schip", Quirks::schip()),
+            ("xochip", Quirks::xochip()),
+        ]
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        Quirks::profiles()
+            .iter()
+            .find(|(profile, _)| *profile == name)
+            .map(|(_, quirks)| *quirks)
+    }
+}
+
+impl Default for Quirks {
+    fn default() -> Self {
+        Quirks::chip8()
+    }
+}
@@ -0,0 +1,271 @@
+//! A deliberately simple interpreter written straight from the description of each instruction, sharing no code with
+//! `CPU` so that the two can be run side by side and compared. It favours obviousness over speed: the screen is a grid
+//! of booleans and sprites are drawn pixel by pixel.
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+
+use crate::cpu::CpuError;
+use crate::fonts::FONT_SET;
+use crate::quirks::{Collision, Quirks};
+
+pub const SCREEN_WIDTH: usize = 64;
+pub const SCREEN_HEIGHT: usize = 32;
+
+/// Levels of subroutine calls the stack holds.
+const STACK_SIZE: usize = 16;
+
+/// XO-CHIP pitch register value that plays the audio pattern at 4000 Hz.
+const DEFAULT_PITCH: u8 = 64;
+
+pub struct Reference {
+    pub v: [u8; 16],
+    pub i: u16,
+    pub pc: usize,
+    pub stack: Vec<usize>,
+    pub memory: Vec<u8>,
+    pub screen: [[bool; SCREEN_WIDTH]; SCREEN_HEIGHT],
+    pub delay: u8,
+    pub sound: u8,
+    /// XO-CHIP audio pattern, once F002 has loaded one.
+    pub audio_pattern: Option<[u8; 16]>,
+    pub pitch: u8,
+    quirks: Quirks,
+    /// Register FX0A stores the key in, while it waits for one.
+    waiting_register: Option<usize>,
+    /// Key pressed while waiting, stored once released with the key release quirk.
+    pressed_key: Option<usize>,
+    /// CXNN draws from a generator seeded like the one of `CPU::seed_rng`.
+    rng: StdRng,
+}
+
+impl Reference {
+    pub fn new(quirks: Quirks, program: &[u8], seed: u64) -> Self {
+        let mut memory = vec![0; quirks.memory_size];
+        memory[..FONT_SET.len()].copy_from_slice(&FONT_SET);
+        memory[0x200..0x200 + program.len()].copy_from_slice(program);
+        Reference {
+            v: [0; 16],
+            i: 0,
+            pc: 0x200,
+            stack: Vec::new(),
+            memory,
+            screen: [[false; SCREEN_WIDTH]; SCREEN_HEIGHT],
+            delay: 0,
+            sound: 0,
+            audio_pattern: None,
+            pitch: DEFAULT_PITCH,
+            quirks,
+            waiting_register: None,
+            pressed_key: None,
+            rng: StdRng::seed_from_u64(seed),
+        }
+    }
+
+    pub fn tick_timers(&mut self) {
+        self.delay = self.delay.saturating_sub(1);
+        self.sound = self.sound.saturating_sub(1);
+    }
+
+    /// Executes the instruction at the program counter, or goes on waiting for a key. On error nothing changes.
+    pub fn step(&mut self, keys: [bool; 16]) -> Result<(), CpuError> {
+        if let Some(x) = self.waiting_register {
+            self.wait_for_key(x, keys);
+            return Ok(());
+        }
+
+        let pc = self.pc;
+        if pc + 1 >= self.memory.len() {
+            return Err(CpuError::PcOutOfBounds { pc });
+        }
+        let opcode = (self.memory[pc] as u16) << 8 | self.memory[pc + 1] as u16;
+        self.pc += 2;
+        let result = self.execute(opcode, keys);
+        if result.is_err() {
+            self.pc = pc;
+        }
+        result
+    }
+
+    fn wait_for_key(&mut self, x: usize, keys: [bool; 16]) {
+        let key = match self.pressed_key {
+            Some(key) if keys[key] => return,
+            Some(key) => key,
+            None => match (0..16).find(|&key| keys[key]) {
+                Some(key) if self.quirks.key_release => {
+                    self.pressed_key = Some(key);
+                    return;
+                }
+                Some(key) => key,
+                None => return,
+            },
+        };
+        self.v[x] = key as u8;
+        self.waiting_register = None;
+        self.pressed_key = None;
+    }
+
+    /// Checks that the `length` bytes starting at I are in memory.
+    fn check_memory(&self, length: usize) -> Result<(), CpuError> {
+        if self.i as usize + length <= self.memory.len() {
+            Ok(())
+        } else {
+            Err(CpuError::MemoryOutOfBounds { pc: self.pc - 2, address: self.i as usize })
+        }
+    }
+
+    fn execute(&mut self, opcode: u16, keys: [bool; 16]) -> Result<(), CpuError> {
+        let x = (opcode >> 8 & 0xF) as usize;
+        let y = (opcode >> 4 & 0xF) as usize;
+        let n = (opcode & 0xF) as usize;
+        let nn = (opcode & 0xFF) as u8;
+        let nnn = (opcode & 0xFFF) as usize;
+
+        match opcode >> 12 {
+            0x0 if opcode == 0x00E0 => self.screen = [[false; SCREEN_WIDTH]; SCREEN_HEIGHT],
+            0x0 if opcode == 0x00EE => match self.stack.pop() {
+                Some(address) => self.pc = address,
+                None => return Err(CpuError::StackUnderflow { pc: self.pc - 2 }),
+            },
+            // Machine code routines of the COSMAC VIP, which interpreters on other machines ignore.
+            0x0 => {}
+            0x2 => {
+                if self.stack.len() == STACK_SIZE {
+                    return Err(CpuError::StackOverflow { pc: self.pc - 2 });
+                }
+                self.stack.push(self.pc);
+                self.pc = nnn;
+            }
+            0x1 => self.pc = nnn,
+            0x3 if self.v[x] == nn => self.pc += 2,
+            0x4 if self.v[x] != nn => self.pc += 2,
+            0x5 if n == 0 && self.v[x] == self.v[y] => self.pc += 2,
+            0x6 => self.v[x] = nn,
+            0x7 => self.v[x] = self.v[x].wrapping_add(nn),
+            0x8 => {
+                let (vx, vy) = (self.v[x], self.v[y]);
+                let flag = match n {
+                    0x0 => {
+                        self.v[x] = vy;
+                        None
+                    }
+                    0x1 => {
+                        self.v[x] = vx | vy;
+                        None
+                    }
+                    0x2 => {
+                        self.v[x] = vx & vy;
+                        None
+                    }
+                    0x3 => {
+                        self.v[x] = vx ^ vy;
+                        None
+                    }
+                    0x4 => {
+                        self.v[x] = vx.wrapping_add(vy);
+                        Some(vx as u16 + vy as u16 > 255)
+                    }
+                    0x5 => {
+                        self.v[x] = vx.wrapping_sub(vy);
+                        Some(vx >= vy)
+                    }
+                    0x6 => {
+                        self.v[x] = vx / 2;
+                        Some(vx % 2 == 1)
+                    }
+                    0x7 => {
+                        self.v[x] = vy.wrapping_sub(vx);
+                        Some(vy >= vx)
+                    }
+                    0xE => {
+                        self.v[x] = vx.wrapping_mul(2);
+                        Some(vx >= 128)
+                    }
+                    _ => None,
+                };
+                if let Some(flag) = flag {
+                    self.v[0xF] = flag as u8;
+                }
+            }
+            0x9 if n == 0 && self.v[x] != self.v[y] => self.pc += 2,
+            0xA => self.i = nnn as u16,
+            0xB => self.pc = nnn + self.v[0] as usize,
+            0xC => self.v[x] = self.rng.gen::<u8>() & nn,
+            0xD => self.draw(x, y, n)?,
+            0xE if nn == 0x9E && keys[(self.v[x] & 0xF) as usize] => self.pc += 2,
+            0xE if nn == 0xA1 && !keys[(self.v[x] & 0xF) as usize] => self.pc += 2,
+            0xF => match nn {
+                0x02 if x == 0 => {
+                    self.check_memory(16)?;
+                    let mut pattern = [0; 16];
+                    pattern.copy_from_slice(&self.memory[self.i as usize..self.i as usize + 16]);
+                    self.audio_pattern = Some(pattern);
+                }
+                0x07 => self.v[x] = self.delay,
+                0x0A => self.waiting_register = Some(x),
+                0x15 => self.delay = self.v[x],
+                0x18 => self.sound = self.v[x],
+                0x1E => self.i = self.i.wrapping_add(self.v[x] as u16),
+                0x29 => self.i = (self.v[x] % 16) as u16 * 5,
+                0x3A => self.pitch = self.v[x],
+                0x33 => {
+                    self.check_memory(3)?;
+                    let i = self.i as usize;
+                    self.memory[i] = self.v[x] / 100;
+                    self.memory[i + 1] = self.v[x] / 10 % 10;
+                    self.memory[i + 2] = self.v[x] % 10;
+                }
+                0x55 => {
+                    self.check_memory(x + 1)?;
+                    for register in 0..=x {
+                        self.memory[self.i as usize + register] = self.v[register];
+                    }
+                }
+                0x65 => {
+                    self.check_memory(x + 1)?;
+                    for register in 0..=x {
+                        self.v[register] = self.memory[self.i as usize + register];
+                    }
+                }
+                _ => {}
+            },
+            _ => {}
+        }
+        Ok(())
+    }
+
+    /// Draws the N rows sprite at I, one pixel at a time.
+    fn draw(&mut self, x: usize, y: usize, rows: usize) -> Result<(), CpuError> {
+        let left = self.v[x] as usize % SCREEN_WIDTH;
+        let top = self.v[y] as usize % SCREEN_HEIGHT;
+        let visible_rows = if self.quirks.sprite_wrap { rows } else { rows.min(SCREEN_HEIGHT - top) };
+        self.check_memory(visible_rows)?;
+
+        let mut collided_rows = 0;
+        for row in 0..visible_rows {
+            let byte = self.memory[self.i as usize + row];
+            let mut collided = false;
+            for column in 0..8 {
+                let (screen_x, screen_y) = (left + column, top + row);
+                if !self.quirks.sprite_wrap && screen_x >= SCREEN_WIDTH {
+                    break;
+                }
+                if byte & (0x80 >> column) != 0 {
+                    let pixel = &mut self.screen[screen_y % SCREEN_HEIGHT][screen_x % SCREEN_WIDTH];
+                    collided |= *pixel;
+                    *pixel = !*pixel;
+                }
+            }
+            collided_rows += collided as u8;
+        }
+
+        self.v[0xF] = match self.quirks.collision {
+            Collision::Flag => (collided_rows > 0) as u8,
+            Collision::RowCount => collided_rows + (rows - visible_rows) as u8,
+        };
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+#[path = "./reference_tests.rs"]
+mod reference_tests;
@@ -0,0 +1,115 @@
+use proptest::collection::vec;
+use proptest::prelude::*;
+
+use super::*;
+use crate::cpu::{Register, CPU};
+
+/// Instructions run from each generated program, unless one fails first.
+const STEPS: usize = 200;
+
+/// Instructions between two ticks of the timers, as in a frame.
+const STEPS_PER_FRAME: usize = 10;
+
+/// Instructions in each generated program, which jumps and calls mostly stay inside.
+const PROGRAM_SIZE: usize = 128;
+
+/// An address of an instruction of the program.
+fn address() -> impl Strategy<Value = u16> {
+    (0..PROGRAM_SIZE as u16).prop_map(|instruction| 0x200 + instruction * 2)
+}
+
+/// Whether `CPU` runs `opcode` unlike the reference on purpose: any other 00X0 clears the screen and 00XX returns,
+/// and 0NNN calls NNN instead of being ignored, see `OpCode::instruction`.
+fn differs_on_purpose(opcode: u16) -> bool {
+    opcode >> 12 == 0x0 && opcode != 0x00E0 && opcode != 0x00EE
+}
+
+/// Any opcode the two interpreters should agree on, with the families that touch the most state made more likely than
+/// they would be by chance.
+fn opcode() -> impl Strategy<Value = u16> {
+    let register = 0..16u16;
+    prop_oneof![
+        6 => any::<u16>().prop_filter("runs differently on purpose", |&opcode| !differs_on_purpose(opcode)),
+        1 => Just(0x00E0),
+        1 => Just(0x00EE),
+        3 => (prop::sample::select(vec![0x1, 0x2, 0xB]), address()).prop_map(|(family, nnn)| family << 12 | nnn),
+        4 => (register.clone(), register.clone(), 0..16u16).prop_map(|(x, y, n)| 0x8000 | x << 8 | y << 4 | n),
+        2 => (register.clone(), register.clone(), 0..16u16).prop_map(|(x, y, n)| 0xD000 | x << 8 | y << 4 | n),
+        2 => (register, prop::sample::select(vec![0x07, 0x0A, 0x15, 0x18, 0x1E, 0x29, 0x33, 0x3A, 0x55, 0x65]))
+            .prop_map(|(x, nn)| 0xF000 | x << 8 | nn),
+        1 => Just(0xF002),
+    ]
+}
+
+fn keypad(keys: u16) -> [bool; 16] {
+    let mut keypad = [false; 16];
+    for (key, pressed) in keypad.iter_mut().enumerate() {
+        *pressed = keys >> key & 1 == 1;
+    }
+    keypad
+}
+
+fn assert_same_state(cpu: &CPU, reference: &Reference) -> Result<(), TestCaseError> {
+    for x in 0..16 {
+        prop_assert_eq!(cpu.register(Register::V(x)), reference.v[x] as u16, "V{:X}", x);
+    }
+    prop_assert_eq!(cpu.register(Register::I), reference.i);
+    prop_assert_eq!(cpu.pc(), reference.pc);
+    prop_assert_eq!(cpu.call_stack(), &reference.stack[..]);
+    prop_assert_eq!(cpu.register(Register::Delay), reference.delay as u16);
+    prop_assert_eq!(cpu.register(Register::Sound), reference.sound as u16);
+    let output = cpu.output_state();
+    prop_assert_eq!(output.audio_pattern, reference.audio_pattern.as_ref());
+    prop_assert_eq!(output.pitch, reference.pitch);
+    prop_assert!(cpu.memory() == &reference.memory[..], "memory differs");
+    for (y, row) in reference.screen.iter().enumerate() {
+        for (x, &pixel) in row.iter().enumerate() {
+            prop_assert_eq!(cpu.vram().pixel(x, y), pixel, "pixel at {}, {}", x, y);
+        }
+    }
+    Ok(())
+}
+
+proptest! {
+    #[test]
+    fn test_cpu_matches_reference(
+        program in vec(opcode(), PROGRAM_SIZE),
+        registers in prop::array::uniform16(prop_oneof![any::<u8>(), 0..4u8]),
+        index in prop_oneof![0u16..0x1000, any::<u16>()],
+        keys in vec(any::<u16>(), STEPS),
+        seed in any::<u64>(),
+    ) {
+        let bytes: Vec<u8> = program.iter().flat_map(|opcode| opcode.to_be_bytes()).collect();
+        for (_, quirks) in Quirks::profiles().iter() {
+            let mut cpu = CPU::with_quirks(*quirks);
+            cpu.load(&bytes).unwrap();
+            cpu.seed_rng(seed);
+            let mut reference = Reference::new(*quirks, &bytes, seed);
+            for (x, &value) in registers.iter().enumerate() {
+                cpu.set_register(Register::V(x), value as u16);
+                reference.v[x] = value;
+            }
+            cpu.set_register(Register::I, index);
+            reference.i = index;
+
+            for (step, &keys) in keys.iter().enumerate() {
+                // Jumps can also reach such opcodes outside of the program, or ones it wrote.
+                let next = cpu.memory().get(cpu.pc()..cpu.pc() + 2).map(|bytes| u16::from_be_bytes([bytes[0], bytes[1]]));
+                if reference.waiting_register.is_none() && next.is_some_and(differs_on_purpose) {
+                    break;
+                }
+                let expected = reference.step(keypad(keys));
+                let actual = cpu.cycle(keypad(keys)).map(|_| ());
+                prop_assert_eq!(actual, expected, "step {} with {:?}", step, quirks);
+                assert_same_state(&cpu, &reference)?;
+                if expected.is_err() {
+                    break;
+                }
+                if step % STEPS_PER_FRAME == STEPS_PER_FRAME - 1 {
+                    cpu.tick_timers();
+                    reference.tick_timers();
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,137 @@
+use std::fmt;
+use std::fs::File;
+use std::io::{self, Read};
+use std::path::Path;
+
+/// Address programs are loaded at. Memory below it belongs to the interpreter.
+pub const PROGRAM_START: usize = 0x200;
+
+/// Extensions of the ROM files looked for in directories and zip archives.
+pub const ROM_EXTENSIONS: [&str; 3] = ["ch8", "sc8", "xo8"];
+
+/// Path that reads the ROM from standard input.
+pub const STDIN_PATH: &str = "-";
+
+#[derive(Debug)]
+pub enum LoadError {
+    Io(io::Error),
+    Zip(zip::result::ZipError),
+    Empty,
+    /// The ROM is larger than the `max_size` bytes that fit in memory.
+    TooLarge { max_size: usize },
+    NoRomInArchive,
+    /// The archive holds several ROMs and none was picked. Lists their names.
+    SeveralRomsInArchive(Vec<String>),
+}
+
+impl fmt::Display for LoadError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            LoadError::Io(error) => write!(f, "{}", error),
+            LoadError::Zip(error) => write!(f, "{}", error),
+            LoadError::Empty => write!(f, "The ROM is empty"),
+            LoadError::TooLarge { max_size } => write!(f, "The ROM is larger than the {} bytes that fit in memory", max_size),
+            LoadError::NoRomInArchive => write!(f, "The archive has no ROM"),
+            LoadError::SeveralRomsInArchive(names) => {
+                write!(f, "The archive has several ROMs, pick one with ARCHIVE.zip:NAME: {}", names.join(", "))
+            }
+        }
+    }
+}
+
+impl From<io::Error> for LoadError {
+    fn from(error: io::Error) -> Self {
+        LoadError::Io(error)
+    }
+}
+
+impl From<zip::result::ZipError> for LoadError {
+    fn from(error: zip::result::ZipError) -> Self {
+        LoadError::Zip(error)
+    }
+}
+
+/// Largest ROM that fits in `memory_size` bytes of RAM.
+pub fn max_size(memory_size: usize) -> usize {
+    memory_size - PROGRAM_START
+}
+
+/// Loads a ROM from a file, from standard input for `-`, or from a zip archive. An archive with a single ROM loads it,
+/// otherwise one is picked by name with `ARCHIVE.zip:NAME`.
+pub fn load(path: &Path, memory_size: usize) -> Result<Vec<u8>, LoadError> {
+    let max_size = max_size(memory_size);
+    let rom = if path == Path::new(STDIN_PATH) {
+        read_all(io::stdin(), max_size)?
+    } else if let Some((archive, name)) = split_archive_path(path) {
+        load_from_archive(Path::new(archive), name, max_size)?
+    } else {
+        read_all(File::open(path)?, max_size)?
+    };
+
+    if rom.is_empty() {
+        Err(LoadError::Empty)
+    } else {
+        Ok(rom)
+    }
+}
+
+/// Sorted names of the ROMs in a zip archive.
+pub fn list_archive(path: &Path) -> Result<Vec<String>, LoadError> {
+    let archive = zip::ZipArchive::new(File::open(path)?)?;
+    let mut names: Vec<String> = archive.file_names().filter(|name| is_rom_name(name)).map(String::from).collect();
+    names.sort();
+    Ok(names)
+}
+
+pub fn is_rom_name(name: &str) -> bool {
+    has_extension(name, &ROM_EXTENSIONS)
+}
+
+/// Reads at most one byte more than `max_size`, so that a huge file or an endless stream is rejected without being
+/// read to the end.
+fn read_all<R: Read>(reader: R, max_size: usize) -> Result<Vec<u8>, LoadError> {
+    let mut rom = Vec::new();
+    reader.take(max_size as u64 + 1).read_to_end(&mut rom)?;
+    if rom.len() > max_size {
+        return Err(LoadError::TooLarge { max_size });
+    }
+    Ok(rom)
+}
+
+/// Splits `ARCHIVE.zip` or `ARCHIVE.zip:NAME` into the archive and the optional ROM name.
+fn split_archive_path(path: &Path) -> Option<(&str, Option<&str>)> {
+    let path = path.to_str()?;
+    if has_extension(path, &["zip"]) {
+        return Some((path, None));
+    }
+    let index = path.to_ascii_lowercase().rfind(".zip:")?;
+    Some((&path[..index + 4], Some(&path[index + 5..])))
+}
+
+fn load_from_archive(path: &Path, name: Option<&str>, max_size: usize) -> Result<Vec<u8>, LoadError> {
+    let mut archive = zip::ZipArchive::new(File::open(path)?)?;
+    let name = match name {
+        Some(name) => name.to_string(),
+        None => {
+            let mut names = list_archive(path)?;
+            match names.len() {
+                0 => return Err(LoadError::NoRomInArchive),
+                1 => names.remove(0),
+                _ => return Err(LoadError::SeveralRomsInArchive(names)),
+            }
+        }
+    };
+    let rom = read_all(archive.by_name(&name)?, max_size)?;
+    Ok(rom)
+}
+
+fn has_extension(name: &str, extensions: &[&str]) -> bool {
+    match name.rsplit_once('.') {
+        Some((_, extension)) => extensions.iter().any(|rom| extension.eq_ignore_ascii_case(rom)),
+        None => false,
+    }
+}
+
+#[cfg(test)]
+#[path = "./rom_tests.rs"]
+mod rom_tests;
@@ -0,0 +1,62 @@
+/// Metadata about a known ROM.
+#[derive(Debug, PartialEq)]
+pub struct RomInfo {
+    pub title: &'static str,
+    pub author: &'static str,
+    pub year: &'static str,
+    /// Name of the quirk profile the ROM was written for.
+    pub platform: &'static str,
+}
+
+/// Known ROMs by the SHA-1 of their contents.
+const ROMS: [(&str, RomInfo); 4] = [
+    ("607c4f7f4e4dce9f99d96b3182bfe7e88bb090ee", RomInfo {
+        title: "Pong",
+        author: "Paul Vervalin",
+        year: "1990",
+        platform: "chip8",
+    }),
+    ("5f518084744bf3cb8733f6e5454dfd1634320563", RomInfo {
+        title: "Tetris",
+        author: "Fran Dachille",
+        year: "1991",
+        platform: "chip8",
+    }),
+    ("f1cfcffe1937ed6dd6eeed1a7f85dfc777bda700", RomInfo {
+        title: "Opcode test",
+        author: "corax89",
+        year: "2019",
+        platform: "chip8",
+    }),
+    ("9df1689015a0d1d95144f141903296f9f1c35fc5", RomInfo {
+        title: "BC_test",
+        author: "BestCoder",
+        year: "",
+        platform: "chip8",
+    }),
+];
+
+pub fn sha1_hex(rom: &[u8]) -> String {
+    sha1_smol::Sha1::from(rom).digest().to_string()
+}
+
+/// The CRC-32 of a ROM, which other ROM databases and tools identify ROMs with.
+pub fn crc32(rom: &[u8]) -> u32 {
+    let mut crc = !0u32;
+    for &byte in rom {
+        crc ^= byte as u32;
+        for _ in 0..8 {
+            crc = if crc & 1 == 1 { (crc >> 1) ^ 0xEDB8_8320 } else { crc >> 1 };
+        }
+    }
+    !crc
+}
+
+pub fn lookup(rom: &[u8]) -> Option<&'static RomInfo> {
+    let hash = sha1_hex(rom);
+    ROMS.iter().find(|(sha1, _)| *sha1 == hash).map(|(_, info)| info)
+}
+
+#[cfg(test)]
+#[path = "./rom_db_tests.rs"]
+mod rom_db_tests;
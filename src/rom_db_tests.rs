@@ -0,0 +1,22 @@
+use super::*;
+
+#[test]
+fn test_lookup_bundled_rom() {
+    let rom = std::fs::read("roms/pong.ch8").unwrap();
+
+    let info = lookup(&rom).unwrap();
+
+    assert_eq!(info.title, "Pong");
+    assert_eq!(info.platform, "chip8");
+}
+
+#[test]
+fn test_lookup_unknown_rom() {
+    assert_eq!(lookup(&[0x12, 0x00]), None);
+}
+
+#[test]
+fn test_crc32() {
+    assert_eq!(crc32(b"123456789"), 0xCBF43926);
+    assert_eq!(crc32(&[]), 0);
+}
@@ -0,0 +1,64 @@
+use super::*;
+use std::io::Write;
+use std::path::PathBuf;
+
+/// A zip archive in the temporary directory, deleted when dropped.
+struct TempArchive(PathBuf);
+
+impl Drop for TempArchive {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_file(&self.0);
+    }
+}
+
+fn write_archive(name: &str, files: &[(&str, &[u8])]) -> TempArchive {
+    let archive = TempArchive(std::env::temp_dir().join(format!("rs-chip8-{}-{}.zip", name, std::process::id())));
+    let mut writer = zip::ZipWriter::new(File::create(&archive.0).unwrap());
+    for (file_name, contents) in files {
+        writer.start_file(*file_name, zip::write::FileOptions::default()).unwrap();
+        writer.write_all(contents).unwrap();
+    }
+    writer.finish().unwrap();
+    archive
+}
+
+#[test]
+fn test_load_file() {
+    let rom = load(Path::new("roms/pong.ch8"), 4096).unwrap();
+
+    assert_eq!(rom.len(), 246);
+}
+
+#[test]
+fn test_load_rejects_rom_larger_than_memory() {
+    let error = load(Path::new("roms/pong.ch8"), PROGRAM_START + 100).unwrap_err();
+
+    assert!(matches!(error, LoadError::TooLarge { max_size: 100 }));
+}
+
+#[test]
+fn test_read_stops_after_max_size() {
+    let error = read_all(std::io::repeat(0), 3584).unwrap_err();
+
+    assert!(matches!(error, LoadError::TooLarge { max_size: 3584 }));
+}
+
+#[test]
+fn test_load_single_rom_archive() {
+    let archive = write_archive("single", &[("readme.txt", b"hello"), ("game.ch8", &[0x12, 0x00])]);
+
+    let rom = load(&archive.0, 4096).unwrap();
+
+    assert_eq!(rom, [0x12, 0x00]);
+}
+
+#[test]
+fn test_load_archive_with_several_roms() {
+    let archive = write_archive("several", &[("a.ch8", &[0x12, 0x00]), ("b.xo8", &[0x00, 0xE0])]);
+
+    let error = load(&archive.0, 4096).unwrap_err();
+    assert!(matches!(error, LoadError::SeveralRomsInArchive(names) if names == ["a.ch8", "b.xo8"]));
+
+    let picked = PathBuf::from(format!("{}:b.xo8", archive.0.display()));
+    assert_eq!(load(&picked, 4096).unwrap(), [0x00, 0xE0]);
+}
@@ -0,0 +1,118 @@
+use std::thread;
+use std::time::{Duration, Instant};
+
+use crate::cpu::FRAME_RATE;
+
+/// Slow motion speeds cycled through by `toggle_slow_motion`, starting from normal speed.
+const SLOW_MOTION_SPEEDS: [f64; 3] = [1.0, 0.5, 0.25];
+
+/// Paces the emulated frames in real time, with fast-forward, slow motion, pause and frame advance.
+pub struct Scheduler {
+    frame_duration: Duration,
+    next_frame: Instant,
+    slow_motion: usize,
+    fast_forward: bool,
+    paused: bool,
+    frame_advance: bool,
+    measure_start: Instant,
+    measured_frames: u32,
+    measured_speed: f64,
+}
+
+impl Scheduler {
+    pub fn new() -> Self {
+        let now = Instant::now();
+        Scheduler {
+            frame_duration: Duration::from_secs(1) / FRAME_RATE,
+            next_frame: now,
+            slow_motion: 0,
+            fast_forward: false,
+            paused: false,
+            frame_advance: false,
+            measure_start: now,
+            measured_frames: 0,
+            measured_speed: 1.0,
+        }
+    }
+
+    /// Runs frames as fast as possible while set.
+    pub fn set_fast_forward(&mut self, fast_forward: bool) {
+        self.fast_forward = fast_forward;
+    }
+
+    pub fn toggle_slow_motion(&mut self) {
+        self.slow_motion = (self.slow_motion + 1) % SLOW_MOTION_SPEEDS.len();
+    }
+
+    pub fn toggle_pause(&mut self) {
+        self.paused = !self.paused;
+    }
+
+    /// Pauses the emulation and lets exactly one more frame run.
+    pub fn frame_advance(&mut self) {
+        self.paused = true;
+        self.frame_advance = true;
+    }
+
+    /// Waits until the next frame is due and returns whether it should be emulated.
+    /// While paused this still returns once per frame so the caller keeps polling input.
+    pub fn wait(&mut self) -> bool {
+        if self.fast_forward && !self.paused {
+            self.next_frame = Instant::now();
+        } else {
+            self.next_frame += self.frame_duration.div_f64(SLOW_MOTION_SPEEDS[self.slow_motion]);
+            let now = Instant::now();
+            if self.next_frame > now {
+                thread::sleep(self.next_frame - now);
+            } else {
+                self.next_frame = now;
+            }
+        }
+
+        let run = !self.paused || std::mem::take(&mut self.frame_advance);
+        if run {
+            self.measure_frame();
+        }
+        run
+    }
+
+    fn measure_frame(&mut self) {
+        self.measured_frames += 1;
+        let elapsed = self.measure_start.elapsed();
+        if elapsed >= Duration::from_secs(1) {
+            self.measured_speed = self.measured_frames as f64 / FRAME_RATE as f64 / elapsed.as_secs_f64();
+            self.measured_frames = 0;
+            self.measure_start = Instant::now();
+        }
+    }
+
+    /// Emulated frames per second, measured over the last second.
+    pub fn fps(&self) -> f64 {
+        self.measured_speed * FRAME_RATE as f64
+    }
+
+    /// Short description of the current speed, empty at normal speed.
+    pub fn status(&self) -> String {
+        if self.paused {
+            "Paused".to_string()
+        } else if self.fast_forward {
+            format!("Fast-forward {:.1}x", self.measured_speed)
+        } else if self.slow_motion != 0 {
+            format!("Slow motion {}x", SLOW_MOTION_SPEEDS[self.slow_motion])
+        } else {
+            String::new()
+        }
+    }
+
+    /// Description of the current speed for a message shown when it changes.
+    pub fn speed_message(&self) -> String {
+        match self.status() {
+            status if status.is_empty() => "Normal speed".to_string(),
+            status => status,
+        }
+    }
+}
+
+#[cfg(test)]
+#[path = "./scheduler_tests.rs"]
+mod scheduler_tests;
@@ -0,0 +1,30 @@
+use super::*;
+
+#[test]
+fn test_frame_advance_runs_one_frame_while_paused() {
+    let mut scheduler = Scheduler::new();
+    scheduler.toggle_pause();
+
+    assert!(!scheduler.wait());
+
+    scheduler.frame_advance();
+
+    assert!(scheduler.wait());
+    assert!(!scheduler.wait());
+    assert_eq!(scheduler.status(), "Paused");
+}
+
+#[test]
+fn test_status_reports_slow_motion() {
+    let mut scheduler = Scheduler::new();
+    assert_eq!(scheduler.status(), "");
+
+    scheduler.toggle_slow_motion();
+    assert_eq!(scheduler.status(), "Slow motion 0.5x");
+
+    scheduler.toggle_slow_motion();
+    assert_eq!(scheduler.status(), "Slow motion 0.25x");
+
+    scheduler.toggle_slow_motion();
+    assert_eq!(scheduler.status(), "");
+}
@@ -0,0 +1,92 @@
+use std::collections::{BTreeMap, HashMap};
+use std::fs;
+use std::io;
+use std::path::Path;
+
+/// Extension of the symbol files loaded along with ROMs of the same name.
+pub const SYMBOL_EXTENSION: &str = "sym";
+
+/// Names of addresses in a ROM, such as the labels of its source.
+#[derive(Debug, Default)]
+pub struct Symbols {
+    names: BTreeMap<usize, String>,
+    addresses: HashMap<String, usize>,
+}
+
+impl Symbols {
+    pub fn load(path: &Path) -> io::Result<Self> {
+        Symbols::parse(&fs::read_to_string(path)?)
+            .map_err(|line| io::Error::new(io::ErrorKind::InvalidData, format!("Invalid symbol on line {}", line)))
+    }
+
+    /// Loads the symbol file next to `rom`, with the same name and the `.sym` extension, if there is one.
+    pub fn load_for_rom(rom: &Path) -> Option<Self> {
+        Symbols::load(&rom.with_extension(SYMBOL_EXTENSION)).ok()
+    }
+
+    /// Parses lines of an address and a name in either order, such as `200 main` or `main 0x200`, optionally
+    /// separated by `=`. Addresses are hexadecimal. Empty lines and lines starting with `#` or `;` are skipped.
+    /// Fails with the number of the first invalid line.
+    pub fn parse(text: &str) -> Result<Self, usize> {
+        let mut symbols = Symbols::default();
+        for (number, line) in text.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') || line.starts_with(';') {
+                continue;
+            }
+            let fields: Vec<&str> = line.split(|c: char| c.is_whitespace() || c == '=').filter(|field| !field.is_empty()).collect();
+            // Names cannot start with a digit, while addresses usually do.
+            let is_number = |field: &str| field.starts_with(|c: char| c.is_ascii_digit());
+            let (address, name) = match fields[..] {
+                [first, second] if is_number(first) && !is_number(second) => (first, second),
+                [first, second] if is_number(second) && !is_number(first) => (second, first),
+                [first, second] if parse_address(second).is_none() => (first, second),
+                [first, second] => (second, first),
+                _ => return Err(number + 1),
+            };
+            match parse_address(address) {
+                Some(address) => symbols.insert(address, name),
+                None => return Err(number + 1),
+            }
+        }
+        Ok(symbols)
+    }
+
+    fn insert(&mut self, address: usize, name: &str) {
+        self.addresses.insert(name.to_string(), address);
+        // The first name given to an address is the one shown for it.
+        self.names.entry(address).or_insert_with(|| name.to_string());
+    }
+
+    /// Name of the exact `address`.
+    pub fn name(&self, address: usize) -> Option<&str> {
+        self.names.get(&address).map(String::as_str)
+    }
+
+    /// Names `address` by the closest name at or before it, such as `main_loop+0x4`.
+    pub fn label(&self, address: usize) -> Option<String> {
+        let (&start, name) = self.names.range(..=address).next_back()?;
+        match address - start {
+            0 => Some(name.clone()),
+            offset => Some(format!("{}+0x{:X}", name, offset)),
+        }
+    }
+
+    /// The label of `address`, or the address in hexadecimal without one.
+    pub fn describe(&self, address: usize) -> String {
+        self.label(address).unwrap_or_else(|| format!("{:03X}", address))
+    }
+
+    /// Address of a name, or of a hexadecimal address written as is.
+    pub fn resolve(&self, text: &str) -> Option<usize> {
+        self.addresses.get(text).copied().or_else(|| parse_address(text))
+    }
+}
+
+fn parse_address(text: &str) -> Option<usize> {
+    usize::from_str_radix(text.strip_prefix("0x").unwrap_or(text), 16).ok()
+}
+
+#[cfg(test)]
+#[path = "./symbols_tests.rs"]
+mod symbols_tests;
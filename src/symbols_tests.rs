@@ -0,0 +1,29 @@
+use super::*;
+
+#[test]
+fn test_parses_both_orders() {
+    let symbols = Symbols::parse("# labels\n200 main\nmain_loop 0x204\ndraw = 0x20A\nadd 300\nA00 sprite\n").unwrap();
+
+    assert_eq!(symbols.name(0x200), Some("main"));
+    assert_eq!(symbols.name(0x204), Some("main_loop"));
+    assert_eq!(symbols.name(0x20A), Some("draw"));
+    assert_eq!(symbols.name(0x300), Some("add"));
+    assert_eq!(symbols.name(0xA00), Some("sprite"));
+}
+
+#[test]
+fn test_describes_and_resolves_addresses() {
+    let symbols = Symbols::parse("200 main\n204 main_loop\n").unwrap();
+
+    assert_eq!(symbols.describe(0x204), "main_loop");
+    assert_eq!(symbols.describe(0x20A), "main_loop+0x6");
+    assert_eq!(symbols.describe(0x100), "100");
+    assert_eq!(symbols.resolve("main_loop"), Some(0x204));
+    assert_eq!(symbols.resolve("0x2F0"), Some(0x2F0));
+    assert_eq!(symbols.resolve("missing"), None);
+}
+
+#[test]
+fn test_reports_invalid_line() {
+    assert_eq!(Symbols::parse("200 main\nmain\n").unwrap_err(), 2);
+}
@@ -0,0 +1,165 @@
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::ops::RangeInclusive;
+use std::rc::Rc;
+
+use crate::cpu::Snapshot;
+use crate::disassembler::disassemble_with_symbols;
+use crate::symbols::Symbols;
+
+/// Trace files stop growing at this size unless another limit is given.
+pub const DEFAULT_MAX_SIZE: u64 = 64 * 1024 * 1024;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum TraceFormat {
+    /// The registers changed by each instruction, for reading.
+    Changes,
+    /// Every register before each instruction, in fixed columns that are easy to produce with other emulators and diff.
+    State,
+}
+
+impl TraceFormat {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "changes" => Some(TraceFormat::Changes),
+            "state" => Some(TraceFormat::State),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct TraceOptions {
+    pub format: TraceFormat,
+    /// Only instructions at these addresses are logged.
+    pub addresses: Option<RangeInclusive<usize>>,
+    /// Only instructions whose first nibble has its bit set in this mask are logged.
+    pub classes: Option<u16>,
+    pub max_size: u64,
+}
+
+impl Default for TraceOptions {
+    fn default() -> Self {
+        TraceOptions {
+            format: TraceFormat::Changes,
+            addresses: None,
+            classes: None,
+            max_size: DEFAULT_MAX_SIZE,
+        }
+    }
+}
+
+impl TraceOptions {
+    fn logs(&self, snapshot: &Snapshot) -> bool {
+        let in_range = self.addresses.as_ref().is_none_or(|addresses| addresses.contains(&snapshot.pc));
+        let in_class = self.classes.is_none_or(|classes| classes & (1 << (snapshot.opcode >> 12)) != 0);
+        in_range && in_class
+    }
+}
+
+/// Parses an inclusive range of hexadecimal addresses such as `200-2FF`.
+pub fn parse_addresses(text: &str) -> Option<RangeInclusive<usize>> {
+    let (start, end) = text.split_once('-')?;
+    let start = usize::from_str_radix(start.trim_start_matches("0x"), 16).ok()?;
+    let end = usize::from_str_radix(end.trim_start_matches("0x"), 16).ok()?;
+    Some(start..=end)
+}
+
+/// Parses a comma separated list of instruction classes, the first hexadecimal digit of the opcodes, such as `1,2,D`.
+pub fn parse_classes(text: &str) -> Option<u16> {
+    text.split(',').try_fold(0u16, |classes, class| {
+        let nibble = u8::from_str_radix(class.trim(), 16).ok().filter(|&nibble| nibble < 16)?;
+        Some(classes | (1 << nibble))
+    })
+}
+
+/// Writes a line per executed instruction until the file reaches its maximum size.
+pub struct Tracer {
+    writer: Box<dyn Write>,
+    options: TraceOptions,
+    symbols: Rc<Symbols>,
+    written: u64,
+    stopped: bool,
+}
+
+impl Tracer {
+    pub fn create(path: &str, options: TraceOptions) -> io::Result<Self> {
+        Ok(Tracer::new(Box::new(BufWriter::new(File::create(path)?)), options))
+    }
+
+    pub fn new(writer: Box<dyn Write>, options: TraceOptions) -> Self {
+        Tracer {
+            writer,
+            options,
+            symbols: Rc::default(),
+            written: 0,
+            stopped: false,
+        }
+    }
+
+    pub fn set_symbols(&mut self, symbols: Rc<Symbols>) {
+        self.symbols = symbols;
+    }
+
+    /// Logs the instruction executed between the `before` and `after` states.
+    pub fn log(&mut self, before: &Snapshot, after: &Snapshot) {
+        if self.stopped || !self.options.logs(before) {
+            return;
+        }
+
+        let line = format_line(self.options.format, &self.symbols, before, after);
+        let result = if self.written + line.len() as u64 > self.options.max_size {
+            self.stopped = true;
+            writeln!(self.writer, "Trace stopped at the limit of {} bytes", self.options.max_size)
+        } else {
+            self.written += line.len() as u64;
+            self.writer.write_all(line.as_bytes())
+        };
+        // A trace that cannot be written is abandoned rather than interrupting the game.
+        if result.is_err() {
+            self.stopped = true;
+        }
+    }
+}
+
+fn format_line(format: TraceFormat, symbols: &Symbols, before: &Snapshot, after: &Snapshot) -> String {
+    let instruction = disassemble_with_symbols(before.opcode, symbols);
+    match format {
+        TraceFormat::Changes => {
+            let changes: Vec<String> = (0..16)
+                .filter(|&register| before.registers[register] != after.registers[register])
+                .map(|register| format!("v{:X}={:02X}", register, after.registers[register]))
+                .collect();
+            // Labels get a line of their own, like in the source.
+            let label = symbols.name(before.pc).map(|name| format!("{}:\n", name)).unwrap_or_default();
+            format!(
+                "{}{:03X}  {:04X}  {:<24} {:<24} I={:03X} DT={:02X} ST={:02X}\n",
+                label,
+                before.pc,
+                before.opcode,
+                instruction,
+                changes.join(" "),
+                after.index,
+                after.delay_timer,
+                after.sound_timer,
+            )
+        }
+        TraceFormat::State => {
+            let registers: Vec<String> = before.registers.iter().map(|value| format!("{:02X}", value)).collect();
+            format!(
+                "PC:{:04X} OP:{:04X} V:{} I:{:04X} DT:{:02X} ST:{:02X} ; {}\n",
+                before.pc,
+                before.opcode,
+                registers.join(" "),
+                before.index,
+                before.delay_timer,
+                before.sound_timer,
+                instruction,
+            )
+        }
+    }
+}
+
+#[cfg(test)]
+#[path = "./trace_tests.rs"]
+mod trace_tests;
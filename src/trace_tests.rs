@@ -0,0 +1,82 @@
+use super::*;
+use crate::cpu::{Timing, CPU};
+
+fn trace_program(name: &str, program: &[u8], instructions: u32, options: TraceOptions) -> String {
+    let path = std::env::temp_dir().join(format!("rs-chip8-trace-{}-{}.log", name, std::process::id()));
+    let mut cpu = CPU::new();
+    cpu.load(program).unwrap();
+    cpu.set_tracer(Tracer::create(path.to_str().unwrap(), options).unwrap());
+    cpu.run_frame([false; 16], Timing::Instructions(instructions)).unwrap();
+    drop(cpu);
+    std::fs::read_to_string(path).unwrap()
+}
+
+#[test]
+fn test_trace_changes() {
+    let trace = trace_program("changes", &[0x60, 0x05, 0xA3, 0x00], 2, TraceOptions::default());
+
+    let lines: Vec<&str> = trace.lines().collect();
+    assert_eq!(lines.len(), 2);
+    assert!(lines[0].starts_with("200  6005  v0 := 0x05"));
+    assert!(lines[0].contains(" v0=05 "));
+    assert!(lines[1].starts_with("202  A300  i := 0x300"));
+    assert!(lines[1].ends_with("I=300 DT=00 ST=00"));
+}
+
+#[test]
+fn test_trace_state() {
+    let options = TraceOptions { format: TraceFormat::State, ..TraceOptions::default() };
+
+    let trace = trace_program("state", &[0x60, 0x05, 0x61, 0x07], 2, options);
+
+    let lines: Vec<&str> = trace.lines().collect();
+    assert_eq!(
+        lines[1],
+        "PC:0202 OP:6107 V:05 00 00 00 00 00 00 00 00 00 00 00 00 00 00 00 I:0000 DT:00 ST:00 ; v1 := 0x07"
+    );
+}
+
+#[test]
+fn test_trace_filters() {
+    let program = [0x60, 0x05, 0xA3, 0x00, 0x61, 0x07, 0x12, 0x00];
+    let options = TraceOptions {
+        addresses: Some(0x202..=0x204),
+        classes: parse_classes("6,1"),
+        ..TraceOptions::default()
+    };
+
+    let trace = trace_program("filters", &program, 4, options);
+
+    assert_eq!(trace.lines().count(), 1);
+    assert!(trace.starts_with("204  6107"));
+}
+
+#[test]
+fn test_trace_stops_at_max_size() {
+    let options = TraceOptions { max_size: 100, ..TraceOptions::default() };
+
+    let trace = trace_program("limit", &[0x12, 0x00], 10, options);
+
+    assert!(trace.len() <= 100 + 64);
+    assert_eq!(trace.lines().last(), Some("Trace stopped at the limit of 100 bytes"));
+}
+
+#[test]
+fn test_trace_labels() {
+    let symbols = Symbols::parse("200 main\n204 main_loop\n").unwrap();
+    let snapshot = Snapshot { pc: 0x200, opcode: 0x1204, registers: [0; 16], index: 0, delay_timer: 0, sound_timer: 0 };
+
+    let changes = format_line(TraceFormat::Changes, &symbols, &snapshot, &snapshot);
+    let state = format_line(TraceFormat::State, &symbols, &snapshot, &snapshot);
+
+    assert!(changes.starts_with("main:\n200  1204  jump main_loop "));
+    assert!(state.ends_with("; jump main_loop\n"));
+}
+
+#[test]
+fn test_parse_options() {
+    assert_eq!(parse_addresses("200-2ff"), Some(0x200..=0x2FF));
+    assert_eq!(parse_addresses("200"), None);
+    assert_eq!(parse_classes("1,d"), Some(0b0010_0000_0000_0010));
+    assert_eq!(parse_classes("1,g"), None);
+}
@@ -0,0 +1,78 @@
+use std::fs::File;
+use std::io::{self, BufWriter, Seek, SeekFrom, Write};
+use std::path::Path;
+
+const HEADER_SIZE: u32 = 44;
+const BITS_PER_SAMPLE: u16 = 16;
+const CHANNELS: u16 = 1;
+
+/// Writes mono 16-bit PCM samples to a WAV file. The header sizes are patched in when the writer is dropped.
+pub struct WavWriter<W: Write + Seek> {
+    out: W,
+    sample_rate: u32,
+    samples_written: u32,
+}
+
+impl WavWriter<BufWriter<File>> {
+    pub fn create<P: AsRef<Path>>(path: P, sample_rate: u32) -> io::Result<Self> {
+        WavWriter::new(BufWriter::new(File::create(path)?), sample_rate)
+    }
+}
+
+impl<W: Write + Seek> WavWriter<W> {
+    pub fn new(out: W, sample_rate: u32) -> io::Result<Self> {
+        let mut writer = WavWriter {
+            out,
+            sample_rate,
+            samples_written: 0,
+        };
+        writer.write_header()?;
+        Ok(writer)
+    }
+
+    pub fn write_samples(&mut self, samples: &[f32]) -> io::Result<()> {
+        for sample in samples {
+            let value = (sample.clamp(-1.0, 1.0) * i16::MAX as f32) as i16;
+            self.out.write_all(&value.to_le_bytes())?;
+        }
+        self.samples_written += samples.len() as u32;
+        Ok(())
+    }
+
+    /// Patches the header with the final data size and flushes the output.
+    pub fn finish(&mut self) -> io::Result<()> {
+        self.out.seek(SeekFrom::Start(0))?;
+        self.write_header()?;
+        self.out.seek(SeekFrom::End(0))?;
+        self.out.flush()
+    }
+
+    fn write_header(&mut self) -> io::Result<()> {
+        let block_align = CHANNELS * BITS_PER_SAMPLE / 8;
+        let data_size = self.samples_written * block_align as u32;
+
+        self.out.write_all(b"RIFF")?;
+        self.out.write_all(&(HEADER_SIZE - 8 + data_size).to_le_bytes())?;
+        self.out.write_all(b"WAVE")?;
+        self.out.write_all(b"fmt ")?;
+        self.out.write_all(&16u32.to_le_bytes())?;
+        self.out.write_all(&1u16.to_le_bytes())?; // PCM
+        self.out.write_all(&CHANNELS.to_le_bytes())?;
+        self.out.write_all(&self.sample_rate.to_le_bytes())?;
+        self.out.write_all(&(self.sample_rate * block_align as u32).to_le_bytes())?;
+        self.out.write_all(&block_align.to_le_bytes())?;
+        self.out.write_all(&BITS_PER_SAMPLE.to_le_bytes())?;
+        self.out.write_all(b"data")?;
+        self.out.write_all(&data_size.to_le_bytes())
+    }
+}
+
+impl<W: Write + Seek> Drop for WavWriter<W> {
+    fn drop(&mut self) {
+        let _ = self.finish();
+    }
+}
+
+#[cfg(test)]
+#[path = "./wav_tests.rs"]
+mod wav_tests;
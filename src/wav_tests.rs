@@ -0,0 +1,52 @@
+use super::*;
+use std::io::Cursor;
+
+use crate::audio::{AudioRenderer, SAMPLE_RATE};
+use crate::cpu::{Timing, CPU};
+
+#[test]
+fn test_header_sizes_are_patched() {
+    let mut data = Vec::new();
+    {
+        let mut writer = WavWriter::new(Cursor::new(&mut data), 44100).unwrap();
+        writer.write_samples(&[0.0, 1.0, -1.0]).unwrap();
+    }
+
+    assert_eq!(data.len(), 44 + 6);
+    assert_eq!(&data[0..4], b"RIFF");
+    assert_eq!(u32::from_le_bytes([data[4], data[5], data[6], data[7]]), 36 + 6);
+    assert_eq!(u32::from_le_bytes([data[40], data[41], data[42], data[43]]), 6);
+}
+
+#[test]
+fn test_samples_are_converted_to_pcm() {
+    let mut data = Vec::new();
+    {
+        let mut writer = WavWriter::new(Cursor::new(&mut data), 44100).unwrap();
+        writer.write_samples(&[1.0, -1.0, 2.0]).unwrap();
+    }
+
+    assert_eq!(i16::from_le_bytes([data[44], data[45]]), i16::MAX);
+    assert_eq!(i16::from_le_bytes([data[46], data[47]]), -i16::MAX);
+    assert_eq!(i16::from_le_bytes([data[48], data[49]]), i16::MAX);
+}
+
+#[test]
+fn test_headless_beep_is_recorded_in_the_frames_it_sounds() {
+    // Sets the sound timer to 5 and loops. The timer ticks at the end of each frame, so it sounds for four.
+    let mut cpu = CPU::new();
+    cpu.load(&[0x60, 0x05, 0xF0, 0x18, 0x12, 0x04]).unwrap();
+    let mut renderer = AudioRenderer::new(SAMPLE_RATE);
+    let mut data = Vec::new();
+    {
+        let mut writer = WavWriter::new(Cursor::new(&mut data), SAMPLE_RATE as u32).unwrap();
+        for _ in 0..10 {
+            let mut samples = Vec::new();
+            renderer.render_frame(&cpu.run_frame([false; 16], Timing::Instructions(3)).unwrap(), &mut samples);
+            writer.write_samples(&samples).unwrap();
+        }
+    }
+
+    let frames: Vec<bool> = data[44..].chunks(735 * 2).map(|frame| frame.iter().any(|&byte| byte != 0)).collect();
+    assert_eq!(frames, [true, true, true, true, false, false, false, false, false, false]);
+}
@@ -0,0 +1,134 @@
+//! Runs test ROMs headlessly for a fixed number of frames with every quirk profile, and compares the final screen
+//! against the golden images in `tests/golden`. ROMs that are not bundled, such as the Timendus test suite, are
+//! looked for in `roms/timendus` and skipped when missing, unless `CONFORMANCE_REQUIRE_ALL` is set. A ROM without a
+//! golden image fails.
+//!
+//! The profiles only differ in FX0A, sprite clipping, the collision flag, soft resets and memory size. Shifts,
+//! FX55/FX65 and the VF reset of 8XY1 to 8XY3 behave the same with all of them, so the Timendus quirks test is only
+//! run with the chip8 profile: the other profiles would report those quirks the same way.
+//!
+//! Run with `UPDATE_GOLDEN=1` to write the golden images of the cases that have none or no longer match, after
+//! checking on screen that the ROM passes.
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use rs_chip8::cpu::{Timing, CPU};
+use rs_chip8::framebuffer::{Framebuffer, HEIGHT, WIDTH};
+use rs_chip8::quirks::Quirks;
+use rs_chip8::rom;
+
+const TIMING: Timing = Timing::Instructions(50);
+
+/// Address the Timendus test suite reads to pick an option without showing its menu.
+const MENU_CHOICE: usize = 0x1FF;
+
+struct Case {
+    name: &'static str,
+    rom: &'static str,
+    frames: u32,
+    /// Whether the ROM asks which interpreter is emulated, answered with the number of the profile in `MENU_CHOICE`.
+    asks_profile: bool,
+    /// Whether the case runs with every quirk profile or only with chip8, the first one.
+    every_profile: bool,
+}
+
+const CASES: [Case; 6] = [
+    Case { name: "test_opcode", rom: "roms/test_opcode.ch8", frames: 60, asks_profile: false, every_profile: true },
+    Case { name: "corax+", rom: "roms/timendus/3-corax+.ch8", frames: 120, asks_profile: false, every_profile: true },
+    Case { name: "flags", rom: "roms/timendus/4-flags.ch8", frames: 240, asks_profile: false, every_profile: true },
+    Case { name: "quirks", rom: "roms/timendus/5-quirks.ch8", frames: 1200, asks_profile: true, every_profile: false },
+    Case { name: "keypad", rom: "roms/timendus/6-keypad.ch8", frames: 60, asks_profile: false, every_profile: true },
+    Case { name: "beep", rom: "roms/timendus/7-beep.ch8", frames: 60, asks_profile: false, every_profile: true },
+];
+
+enum Outcome {
+    Pass,
+    Fail(String),
+    Skip(&'static str),
+}
+
+fn root() -> &'static Path {
+    Path::new(env!("CARGO_MANIFEST_DIR"))
+}
+
+fn golden_path(case: &Case, profile: &str) -> PathBuf {
+    root().join("tests/golden").join(format!("{}-{}.txt", case.name, profile))
+}
+
+/// Draws the screen with `#` for lit pixels and `.` for the others, a line per row.
+fn render(vram: &Framebuffer) -> String {
+    let mut image = String::new();
+    for y in 0..HEIGHT {
+        let row = vram.row(y);
+        for x in 0..WIDTH {
+            image.push(if (row >> (WIDTH - 1 - x)) & 1 == 1 { '#' } else { '.' });
+        }
+        image.push('\n');
+    }
+    image
+}
+
+fn run(case: &Case, number: usize, quirks: Quirks) -> Result<String, String> {
+    let rom = rom::load(&root().join(case.rom), quirks.memory_size).map_err(|error| error.to_string())?;
+    let mut cpu = CPU::with_quirks(quirks);
+    cpu.load(&rom).map_err(|error| error.to_string())?;
+    if case.asks_profile {
+        cpu.memory_mut()[MENU_CHOICE] = number as u8 + 1;
+    }
+    for _ in 0..case.frames {
+        cpu.run_frame([false; 16], TIMING).map_err(|error| error.to_string())?;
+    }
+    Ok(render(cpu.vram()))
+}
+
+fn check(case: &Case, number: usize, profile: &str, quirks: Quirks) -> Outcome {
+    if !root().join(case.rom).exists() {
+        return Outcome::Skip("ROM not found");
+    }
+    let image = match run(case, number, quirks) {
+        Ok(image) => image,
+        Err(error) => return Outcome::Fail(error),
+    };
+
+    let path = golden_path(case, profile);
+    let golden = fs::read_to_string(&path).ok();
+    if golden.as_deref() == Some(image.as_str()) {
+        return Outcome::Pass;
+    }
+    if env::var_os("UPDATE_GOLDEN").is_some() {
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(&path, &image).unwrap();
+        return Outcome::Pass;
+    }
+    match golden {
+        Some(_) => Outcome::Fail(format!("screen differs from {}:\n{}", path.display(), image)),
+        None => Outcome::Fail(format!("no golden image at {}, run with UPDATE_GOLDEN=1 to write it", path.display())),
+    }
+}
+
+#[test]
+fn test_roms_match_golden_images() {
+    let mut failures = Vec::new();
+    for case in CASES.iter() {
+        let profiles = Quirks::profiles();
+        let count = if case.every_profile { profiles.len() } else { 1 };
+        for (number, (profile, quirks)) in profiles.iter().enumerate().take(count) {
+            let outcome = check(case, number, profile, *quirks);
+            let result = match &outcome {
+                Outcome::Pass => "pass".to_string(),
+                Outcome::Fail(_) => "FAIL".to_string(),
+                Outcome::Skip(reason) => format!("skipped, {}", reason),
+            };
+            println!("{:<12} {:<7} {}", case.name, profile, result);
+            match outcome {
+                Outcome::Fail(error) => failures.push(format!("{} with {}: {}", case.name, profile, error)),
+                Outcome::Skip(reason) if env::var_os("CONFORMANCE_REQUIRE_ALL").is_some() => {
+                    failures.push(format!("{} with {}: {}", case.name, profile, reason))
+                }
+                _ => {}
+            }
+        }
+    }
+    assert!(failures.is_empty(), "{}", failures.join("\n"));
+}
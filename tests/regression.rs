@@ -0,0 +1,89 @@
+//! Plays the bundled ROMs headlessly with scripted input and a fixed random seed, and compares hashes of the screen
+//! taken every second against the expectations in `tests/golden/regression.txt`.
+//!
+//! Write the expectations again after an intentional change of behaviour with `cargo run --example
+//! update_regression`, which shares this file, or by running this test with `UPDATE_GOLDEN=1`.
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use rs_chip8::cpu::{Timing, CPU, FRAME_RATE};
+use rs_chip8::framebuffer::{Framebuffer, HEIGHT};
+use rs_chip8::quirks::Quirks;
+use rs_chip8::rom;
+use rs_chip8::rom_db::sha1_hex;
+
+const TIMING: Timing = Timing::Instructions(8);
+const SEED: u64 = 0xC8;
+
+/// Frames between two hashes of the screen.
+const CHECKPOINT: u32 = FRAME_RATE;
+
+struct Case {
+    name: &'static str,
+    rom: &'static str,
+    frames: u32,
+    /// Keys held from the first frame up to the second, excluded.
+    input: &'static [(u32, u32, usize)],
+}
+
+const CASES: [Case; 3] = [
+    Case { name: "pong", rom: "roms/pong.ch8", frames: 600, input: &[(30, 90, 0x1), (150, 240, 0x4), (300, 330, 0xC)] },
+    Case { name: "tetris", rom: "roms/tetris.ch8", frames: 600, input: &[(60, 64, 0x4), (120, 150, 0x5), (200, 230, 0x6), (300, 400, 0x7)] },
+    Case { name: "test1", rom: "roms/test1.ch8", frames: 300, input: &[] },
+];
+
+fn expectations_path() -> PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/golden/regression.txt")
+}
+
+fn hash(vram: &Framebuffer) -> String {
+    let bytes: Vec<u8> = (0..HEIGHT).flat_map(|y| vram.row(y).to_be_bytes()).collect();
+    sha1_hex(&bytes)
+}
+
+/// Plays every case and writes the lines for their checkpoints to `tests/golden/regression.txt`.
+pub fn write_expectations() -> PathBuf {
+    let lines: Vec<String> = CASES.iter().flat_map(play).collect();
+    let path = expectations_path();
+    fs::write(&path, lines.join("\n") + "\n").unwrap();
+    path
+}
+
+/// Plays a case and returns a line with the name, the frame and the hash of the screen for each checkpoint.
+fn play(case: &Case) -> Vec<String> {
+    let quirks = Quirks::default();
+    let rom = rom::load(&Path::new(env!("CARGO_MANIFEST_DIR")).join(case.rom), quirks.memory_size).unwrap();
+    let mut cpu = CPU::with_quirks(quirks);
+    cpu.seed_rng(SEED);
+    cpu.load(&rom).unwrap();
+
+    let mut lines = Vec::new();
+    for frame in 1..=case.frames {
+        let mut keypad = [false; 16];
+        for &(_, _, key) in case.input.iter().filter(|&&(start, end, _)| (start..end).contains(&frame)) {
+            keypad[key] = true;
+        }
+        cpu.run_frame(keypad, TIMING).unwrap();
+        if frame % CHECKPOINT == 0 {
+            lines.push(format!("{} {} {}", case.name, frame, hash(cpu.vram())));
+        }
+    }
+    lines
+}
+
+#[test]
+fn test_bundled_roms_match_expectations() {
+    if std::env::var_os("UPDATE_GOLDEN").is_some() {
+        write_expectations();
+        return;
+    }
+    let lines: Vec<String> = CASES.iter().flat_map(play).collect();
+    let expected = fs::read_to_string(expectations_path()).unwrap();
+    let differences: Vec<String> = expected
+        .lines()
+        .zip(&lines)
+        .filter(|(expected, actual)| expected != actual)
+        .map(|(expected, actual)| format!("expected {}\n     got {}", expected, actual))
+        .collect();
+    assert!(differences.is_empty() && expected.lines().count() == lines.len(), "{}", differences.join("\n"));
+}